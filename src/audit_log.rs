@@ -0,0 +1,683 @@
+//! Append-only log of verification decisions
+//!
+//! Each decision is appended as one JSON line, so the log can be shipped and queried with common
+//! tools as well. Records are never rewritten; a commit verified several times has several
+//! records, e.g., one per push.
+//!
+//! Records are numbered and chained: each record contains the SHA-256 of the previous line, so
+//! editing, inserting, or removing a record breaks the chain, cf. `AuditLog::verify`. Since the
+//! whole chain can be recomputed by anyone able to write the log, checkpoints of the chain may be
+//! signed periodically with an OpenPGP key and stored next to the log.
+
+use crate::{
+    errors::*,
+    github::commits::Sha,
+    gpg::{CommitVerifier, DetachedSigner},
+    report::{Decision, ReportEntry},
+    utils::sha256,
+    verdict::CommitVerdict,
+};
+
+use chrono::{DateTime, Utc};
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+static CHECKPOINTS_EXTENSION: &str = ".checkpoints";
+const TAIL_CHUNK_SIZE: u64 = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AuditRecord {
+    /// Repository as `owner/name` or path of a local repository
+    pub repository: String,
+    pub sha: Sha,
+    pub timestamp: DateTime<Utc>,
+    /// Identifies the set of trusted keys at verification time, cf.
+    /// `CommitVerifier::keyring_version`
+    pub keyring_version: String,
+    /// Fingerprints of the keys the commit has been verified with
+    pub signers: Vec<String>,
+    pub decision: Decision,
+    pub rule: String,
+    pub details: String,
+    /// ID of the event which triggered the verification, e.g., a GitHub delivery ID
+    pub event_id: Option<String>,
+    /// Position in the log starting at 1; set when appended
+    #[serde(default)]
+    pub sequence: u64,
+    /// SHA-256 of the previous line of the log; set when appended
+    #[serde(default)]
+    pub previous_hash: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn new<T: Into<String>, S: Into<String>>(
+        repository: T,
+        keyring_version: S,
+        verdict: &CommitVerdict,
+    ) -> AuditRecord {
+        let entry = ReportEntry::from(verdict);
+
+        AuditRecord {
+            repository: repository.into(),
+            sha: verdict.sha.clone(),
+            timestamp: Utc::now(),
+            keyring_version: keyring_version.into(),
+            signers: entry.signers,
+            decision: entry.decision,
+            rule: entry.rule,
+            details: entry.details,
+            event_id: None,
+            sequence: 0,
+            previous_hash: None,
+        }
+    }
+
+    pub fn with_event_id<T: Into<String>>(self, event_id: T) -> Self {
+        AuditRecord {
+            event_id: Some(event_id.into()),
+            ..self
+        }
+    }
+}
+
+/// Signed statement that the log contained the record `sequence` with hash `hash`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Checkpoint {
+    pub sequence: u64,
+    pub hash: String,
+    pub timestamp: DateTime<Utc>,
+    /// Detached, ASCII armored OpenPGP signature of `payload`
+    pub signature: String,
+}
+
+impl Checkpoint {
+    /// The signed text
+    pub fn payload(&self) -> String {
+        checkpoint_payload(self.sequence, &self.hash, &self.timestamp)
+    }
+}
+
+fn checkpoint_payload(sequence: u64, hash: &str, timestamp: &DateTime<Utc>) -> String {
+    format!(
+        "github-watchtower audit log checkpoint\nsequence: {}\nhash: {}\ntimestamp: {}\n",
+        sequence,
+        hash,
+        timestamp.to_rfc3339()
+    )
+}
+
+/// Result of walking the hash chain of an audit log, cf. `AuditLog::verify`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainVerification {
+    /// Number of records up to the first broken link
+    pub records: usize,
+    pub checkpoints: usize,
+    pub broken_link: Option<BrokenLink>,
+}
+
+impl ChainVerification {
+    pub fn is_intact(&self) -> bool {
+        self.broken_link.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    /// Line of the log starting at 1; one past the last line if records are missing at the end
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for BrokenLink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+/// Audit log stored as JSON Lines file
+///
+/// Appending is not synchronized between processes; concurrent writers may fork the chain, which
+/// `verify` reports as broken link.
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    checkpoints: Option<(DetachedSigner, u64)>,
+}
+
+impl AuditLog {
+    /// The file is created when the first record is appended
+    pub fn new<P: Into<PathBuf>>(path: P) -> AuditLog {
+        AuditLog {
+            path: path.into(),
+            checkpoints: None,
+        }
+    }
+
+    /// Signs a checkpoint whenever the sequence of an appended record is a multiple of
+    /// `interval`
+    pub fn with_checkpoints(self, signer: DetachedSigner, interval: u64) -> Self {
+        AuditLog {
+            checkpoints: Some((signer, interval.max(1))),
+            ..self
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Checkpoints are stored next to the log in a file with the extension `.checkpoints` appended
+    pub fn checkpoints_path(&self) -> PathBuf {
+        let mut path: OsString = self.path.clone().into_os_string();
+        path.push(CHECKPOINTS_EXTENSION);
+        PathBuf::from(path)
+    }
+
+    pub fn append(&self, record: &AuditRecord) -> Result<()> {
+        self.append_all(::std::slice::from_ref(record))
+    }
+
+    /// Appends records with a single write, so concurrent writers do not interleave lines
+    ///
+    /// The records' `sequence` and `previous_hash` are set to continue the chain.
+    pub fn append_all(&self, records: &[AuditRecord]) -> Result<()> {
+        let (mut sequence, mut previous_hash) = match self.last_line()? {
+            Some(line) => {
+                let last: AuditRecord = serde_json::from_str(&line).map_err(|e| {
+                    e.context(ErrorKind::InvalidAuditLog(format!(
+                        "{}, last line",
+                        self.path.display()
+                    )))
+                })?;
+                (
+                    last.sequence,
+                    Some(sha256::from_bytes_as_str(line.as_bytes())),
+                )
+            }
+            None => (0, None),
+        };
+
+        let mut lines = String::new();
+        let mut checkpoint_due = false;
+        for record in records {
+            sequence += 1;
+            let record = AuditRecord {
+                sequence,
+                previous_hash: previous_hash.take(),
+                ..record.clone()
+            };
+            let line = serde_json::to_string(&record).map_err(|e| {
+                e.context(ErrorKind::FailedToWriteAuditLog(
+                    "failed to serialize record".to_string(),
+                ))
+            })?;
+            previous_hash = Some(sha256::from_bytes_as_str(line.as_bytes()));
+            lines.push_str(&line);
+            lines.push('\n');
+            if let Some((_, interval)) = self.checkpoints {
+                checkpoint_due |= sequence % interval == 0;
+            }
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| self.write_error(e))?;
+        file.write_all(lines.as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(|e| self.write_error(e))?;
+
+        if let (true, Some(hash)) = (checkpoint_due, previous_hash) {
+            self.append_checkpoint(sequence, hash)?;
+        }
+
+        Ok(())
+    }
+
+    fn append_checkpoint(&self, sequence: u64, hash: String) -> Result<()> {
+        let signer = match self.checkpoints {
+            Some((ref signer, _)) => signer,
+            None => return Ok(()),
+        };
+        let timestamp = Utc::now();
+        let signature = signer.sign(checkpoint_payload(sequence, &hash, &timestamp).as_bytes())?;
+        let checkpoint = Checkpoint {
+            sequence,
+            hash,
+            timestamp,
+            signature,
+        };
+
+        let path = self.checkpoints_path();
+        let write_error = |e: io::Error| -> Error {
+            e.context(ErrorKind::FailedToWriteAuditLog(path.display().to_string()))
+                .into()
+        };
+        let mut line = serde_json::to_string(&checkpoint).map_err(|e| {
+            e.context(ErrorKind::FailedToWriteAuditLog(
+                "failed to serialize checkpoint".to_string(),
+            ))
+        })?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(write_error)?;
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.sync_data())
+            .map_err(write_error)?;
+
+        Ok(())
+    }
+
+    /// Last non-empty line without reading the whole log
+    fn last_line(&self) -> Result<Option<String>> {
+        let mut file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(self.read_error(e, 0)),
+        };
+
+        let mut pos = file
+            .seek(SeekFrom::End(0))
+            .map_err(|e| self.read_error(e, 0))?;
+        let mut tail: Vec<u8> = Vec::new();
+        loop {
+            let end = tail
+                .iter()
+                .rposition(|x| !x.is_ascii_whitespace())
+                .map(|x| x + 1)
+                .unwrap_or(0);
+            if let Some(start) = tail[..end].iter().rposition(|x| *x == b'\n') {
+                tail = tail[start + 1..end].to_vec();
+                break;
+            }
+            if pos == 0 {
+                tail.truncate(end);
+                break;
+            }
+
+            let chunk_size = pos.min(TAIL_CHUNK_SIZE);
+            pos -= chunk_size;
+            let mut chunk = vec![0; chunk_size as usize];
+            file.seek(SeekFrom::Start(pos))
+                .and_then(|_| file.read_exact(&mut chunk))
+                .map_err(|e| self.read_error(e, 0))?;
+            chunk.extend_from_slice(&tail);
+            tail = chunk;
+        }
+
+        if tail.is_empty() {
+            return Ok(None);
+        }
+        let line = String::from_utf8(tail).map_err(|e| self.read_error(e, 0))?;
+
+        Ok(Some(line))
+    }
+
+    /// All records, oldest first; none if the log does not exist yet
+    pub fn records(&self) -> Result<Vec<AuditRecord>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(self.read_error(e, 0)),
+        };
+
+        let mut records = Vec::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| self.read_error(e, i + 1))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str(&line).map_err(|e| self.read_error(e, i + 1))?;
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+
+    /// All decisions made for commit `sha` of `repository`, oldest first
+    pub fn history(&self, repository: &str, sha: &Sha) -> Result<Vec<AuditRecord>> {
+        let records = self
+            .records()?
+            .into_iter()
+            .filter(|x| x.repository == repository && &x.sha == sha)
+            .collect();
+
+        Ok(records)
+    }
+
+    /// Walks the hash chain and reports the first broken link
+    ///
+    /// Checkpoints are checked against the chain. Their signatures are only verified if a
+    /// `checkpoint_verifier` is given; otherwise, the checkpoints could have been rewritten along
+    /// with the log.
+    pub fn verify(
+        &self,
+        checkpoint_verifier: Option<&CommitVerifier>,
+    ) -> Result<ChainVerification> {
+        let lines = match File::open(&self.path) {
+            Ok(file) => BufReader::new(file)
+                .lines()
+                .enumerate()
+                .map(|(i, x)| x.map_err(|e| self.read_error(e, i + 1)))
+                .collect::<Result<Vec<_>>>()?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(self.read_error(e, 0)),
+        };
+
+        let mut records = 0;
+        let mut hashes: HashMap<u64, (usize, String)> = HashMap::new();
+        let mut previous: Option<(u64, String)> = None;
+        let mut broken_links = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let broken_link = |reason: String| BrokenLink {
+                line: i + 1,
+                reason,
+            };
+
+            let record: AuditRecord = match serde_json::from_str(line) {
+                Ok(record) => record,
+                Err(e) => {
+                    broken_links.push(broken_link(format!("invalid record, {}", e)));
+                    break;
+                }
+            };
+            let (expected_sequence, expected_hash) = match previous {
+                Some((sequence, ref hash)) => (sequence + 1, Some(hash.clone())),
+                None => (1, None),
+            };
+            if record.previous_hash != expected_hash {
+                broken_links.push(broken_link(
+                    "hash of previous record does not match".to_string(),
+                ));
+                break;
+            }
+            if record.sequence != expected_sequence {
+                broken_links.push(broken_link(format!(
+                    "expected record {}, found {}",
+                    expected_sequence, record.sequence
+                )));
+                break;
+            }
+
+            let hash = sha256::from_bytes_as_str(line.as_bytes());
+            hashes.insert(record.sequence, (i + 1, hash.clone()));
+            previous = Some((record.sequence, hash));
+            records += 1;
+        }
+
+        let checkpoints = self.checkpoints()?;
+        for checkpoint in &checkpoints {
+            let (line, hash) = match hashes.get(&checkpoint.sequence) {
+                Some((line, hash)) => (*line, Some(hash)),
+                None => (lines.len() + 1, None),
+            };
+            if let Some(verifier) = checkpoint_verifier {
+                if let Err(e) = verifier.verify_message(
+                    checkpoint.payload().as_bytes(),
+                    checkpoint.signature.as_bytes(),
+                ) {
+                    broken_links.push(BrokenLink {
+                        line,
+                        reason: format!(
+                            "signature of checkpoint of record {} is invalid, {}",
+                            checkpoint.sequence, e
+                        ),
+                    });
+                    continue;
+                }
+            }
+            match hash {
+                Some(hash) if hash != &checkpoint.hash => broken_links.push(BrokenLink {
+                    line,
+                    reason: format!("record {} differs from checkpoint", checkpoint.sequence),
+                }),
+                Some(_) => {}
+                None => broken_links.push(BrokenLink {
+                    line,
+                    reason: format!("checkpointed record {} is missing", checkpoint.sequence),
+                }),
+            }
+        }
+
+        Ok(ChainVerification {
+            records,
+            checkpoints: checkpoints.len(),
+            broken_link: broken_links.into_iter().min_by_key(|x| x.line),
+        })
+    }
+
+    /// All checkpoints, oldest first; none if there are none yet
+    pub fn checkpoints(&self) -> Result<Vec<Checkpoint>> {
+        let path = self.checkpoints_path();
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(file_read_error(&path, e, 0)),
+        };
+
+        let mut checkpoints = Vec::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| file_read_error(&path, e, i + 1))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let checkpoint =
+                serde_json::from_str(&line).map_err(|e| file_read_error(&path, e, i + 1))?;
+            checkpoints.push(checkpoint);
+        }
+
+        Ok(checkpoints)
+    }
+
+    fn write_error<E: Fail>(&self, e: E) -> Error {
+        e.context(ErrorKind::FailedToWriteAuditLog(
+            self.path.display().to_string(),
+        ))
+        .into()
+    }
+
+    fn read_error<E: Fail>(&self, e: E, line: usize) -> Error {
+        file_read_error(&self.path, e, line)
+    }
+}
+
+fn file_read_error<E: Fail>(path: &Path, e: E, line: usize) -> Error {
+    e.context(ErrorKind::InvalidAuditLog(format!(
+        "{}, line {}",
+        path.display(),
+        line
+    )))
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{github::commits::Reason, utils::test};
+
+    use spectral::prelude::*;
+    use std::{env, fs};
+
+    fn verdict(sha: &str) -> CommitVerdict {
+        CommitVerdict {
+            sha: Sha::new(sha),
+            author: "Mallory <mallory@example.com>".to_string(),
+            signed: false,
+            reason: Some(Reason::Unsigned),
+            keys: Err(Error::from(ErrorKind::FailedToVerify(
+                "verification object is invalid".to_string(),
+            ))),
+        }
+    }
+
+    fn log_path(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("github-watchtower-{}-test.jsonl", name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn append_and_query_history() {
+        test::init();
+
+        let path = log_path("audit-log-history");
+        let log = AuditLog::new(&path);
+        let first = AuditRecord::new("lukaspustina/github-watchtower", "abc", &verdict("1"))
+            .with_event_id("delivery-1");
+        let other = AuditRecord::new("lukaspustina/github-watchtower", "abc", &verdict("2"));
+        let second = AuditRecord::new("lukaspustina/github-watchtower", "def", &verdict("1"))
+            .with_event_id("delivery-2");
+
+        log.append(&first).expect("failed to append record");
+        log.append_all(&[other, second.clone()])
+            .expect("failed to append records");
+        let history = log
+            .history("lukaspustina/github-watchtower", &Sha::new("1"))
+            .map(|x| {
+                x.into_iter()
+                    .map(|x| (x.sequence, x.keyring_version, x.event_id))
+                    .collect::<Vec<_>>()
+            });
+        let _ = fs::remove_file(&path);
+
+        asserting("all decisions for the commit are returned, oldest first")
+            .that(&history)
+            .is_ok()
+            .is_equal_to(&vec![
+                (1, first.keyring_version, first.event_id),
+                (3, second.keyring_version, second.event_id),
+            ]);
+    }
+
+    #[test]
+    fn chain_detects_modified_record() {
+        test::init();
+
+        let path = log_path("audit-log-chain");
+        let log = AuditLog::new(&path);
+        for sha in &["1", "2", "3"] {
+            log.append(&AuditRecord::new(
+                "lukaspustina/github-watchtower",
+                "abc",
+                &verdict(sha),
+            ))
+            .expect("failed to append record");
+        }
+        let intact = log.verify(None);
+
+        let content = fs::read_to_string(&path).expect("failed to read log");
+        let mut lines: Vec<_> = content.lines().map(str::to_string).collect();
+        let mut record: AuditRecord = serde_json::from_str(&lines[1]).unwrap();
+        record.decision = Decision::Verified;
+        lines[1] = serde_json::to_string(&record).unwrap();
+        fs::write(&path, lines.join("\n") + "\n").expect("failed to write log");
+        let modified = log.verify(None);
+        let _ = fs::remove_file(&path);
+
+        asserting("untouched log is intact")
+            .that(&intact)
+            .is_ok()
+            .is_equal_to(&ChainVerification {
+                records: 3,
+                checkpoints: 0,
+                broken_link: None,
+            });
+        asserting("record after modified record breaks the chain")
+            .that(&modified)
+            .is_ok()
+            .map(|x| &x.broken_link)
+            .is_equal_to(&Some(BrokenLink {
+                line: 3,
+                reason: "hash of previous record does not match".to_string(),
+            }));
+    }
+
+    #[test]
+    fn checkpoints_detect_truncated_log() {
+        test::init();
+
+        let path = log_path("audit-log-checkpoints");
+        let signer =
+            DetachedSigner::from_key_file("tests/checkpoint.sec").expect("failed to load key");
+        let log = AuditLog::new(&path).with_checkpoints(signer, 2);
+        let _ = fs::remove_file(log.checkpoints_path());
+        for sha in &["1", "2", "3"] {
+            log.append(&AuditRecord::new(
+                "lukaspustina/github-watchtower",
+                "abc",
+                &verdict(sha),
+            ))
+            .expect("failed to append record");
+        }
+        let checkpoints = log.checkpoints();
+
+        let content = fs::read_to_string(&path).expect("failed to read log");
+        let first_line = content.lines().next().unwrap().to_string();
+        fs::write(&path, first_line + "\n").expect("failed to write log");
+        let truncated = log.verify(None);
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(log.checkpoints_path());
+
+        asserting("checkpoint is written every second record")
+            .that(&checkpoints)
+            .is_ok()
+            .map(|x| &x[0].sequence)
+            .is_equal_to(&2);
+        asserting("removed checkpointed record is reported")
+            .that(&truncated)
+            .is_ok()
+            .map(|x| &x.broken_link)
+            .is_equal_to(&Some(BrokenLink {
+                line: 2,
+                reason: "checkpointed record 2 is missing".to_string(),
+            }));
+    }
+
+    #[test]
+    fn missing_log_is_empty() {
+        test::init();
+
+        let log = AuditLog::new(log_path("audit-log-missing"));
+
+        asserting("missing log has no records")
+            .that(&log.records())
+            .is_ok()
+            .has_length(0);
+    }
+
+    #[test]
+    fn corrupt_log_failed() {
+        test::init();
+
+        let path = log_path("audit-log-corrupt");
+        fs::write(&path, "{\"repository\":\n").expect("failed to write log");
+        let log = AuditLog::new(&path);
+        let records = log.records();
+        let _ = fs::remove_file(&path);
+
+        asserting("corrupt line is reported")
+            .that(&records)
+            .is_err()
+            .map(|x| x.kind())
+            .is_equal_to(&ErrorKind::InvalidAuditLog(format!(
+                "{}, line 1",
+                path.display()
+            )));
+    }
+}
@@ -1,25 +1,531 @@
+use github_watchtower::{
+    audit_log::{AuditLog, AuditRecord},
+    bootstrap::{ConfigIntegrity, CONFIG_PATH_ENV},
+    cache::{self, FileCache, SqliteCache, VerificationCache},
+    config::GitHubWatchTowerConfig,
+    config_check,
+    git::LocalRepository,
+    github::{commits::Sha, webhook, Client, OAuthToken, Repository},
+    gpg::{CommitVerifier, DetachedSigner},
+    keyring,
+    repo_policy::{self, EffectiveConfigs},
+    report::{self, Format},
+    source::{CommitSource, GitHubSource, SignedCommit},
+    verdict,
+};
 use lambda_http::{http, lambda, Body, IntoResponse, Request, RequestExt, Response};
 use lambda_runtime::{error::HandlerError, Context};
-use log::debug;
-use std::error::Error;
+use log::{debug, info, warn};
+use std::{
+    convert::TryFrom,
+    env,
+    error::Error,
+    io,
+    path::{Path, PathBuf},
+    process,
+};
+use structopt::StructOpt;
+
+/// Without a subcommand, the Lambda handler is run
+#[derive(Debug, StructOpt)]
+#[structopt(name = "github-watchtower")]
+struct Opt {
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Verifies the commits of a repository and prints a verdict per commit
+    Audit {
+        /// Repository as `owner/name`
+        #[structopt(long, required_unless = "git-dir", conflicts_with = "git-dir")]
+        repo: Option<String>,
+        /// Retrieves commits via GitHub's GraphQL API instead of its REST API
+        #[structopt(long, conflicts_with = "git-dir")]
+        graphql: bool,
+        /// Local repository to verify offline instead of a GitHub repository
+        #[structopt(long, parse(from_os_str))]
+        git_dir: Option<PathBuf>,
+        /// Oldest commit to verify; all commits if not set
+        #[structopt(long)]
+        from: Option<String>,
+        /// Newest commit to verify; the head of the default branch, or `HEAD` for a local
+        /// repository, if not set
+        #[structopt(long)]
+        to: Option<String>,
+        #[structopt(long, parse(from_os_str))]
+        config: PathBuf,
+        /// Output format
+        #[structopt(long, default_value = "table", possible_values = Format::NAMES)]
+        format: Format,
+        /// Appends a record per verdict to this audit log
+        #[structopt(long, parse(from_os_str))]
+        audit_log: Option<PathBuf>,
+        /// ID of the event triggering this audit, recorded in the audit log
+        #[structopt(long, requires = "audit-log")]
+        event_id: Option<String>,
+        /// Secret OpenPGP key to sign checkpoints of the audit log with
+        #[structopt(long, parse(from_os_str), requires = "audit-log")]
+        checkpoint_key: Option<PathBuf>,
+        /// Number of records between signed checkpoints
+        #[structopt(long, default_value = "100")]
+        checkpoint_interval: u64,
+        /// Caches successful verifications in this file to speed up subsequent audits; entries are
+        /// authenticated with the secret in GITHUB_WATCHTOWER_CACHE_SECRET. Files ending in
+        /// .sqlite, .sqlite3, or .db are used as SQLite database, others as JSON file
+        #[structopt(long, parse(from_os_str))]
+        cache: Option<PathBuf>,
+    },
+    /// Prints all recorded decisions for a commit
+    History {
+        #[structopt(long, parse(from_os_str))]
+        audit_log: PathBuf,
+        /// Repository as recorded, i.e., `owner/name` or the path of a local repository
+        #[structopt(long)]
+        repo: String,
+        #[structopt(long)]
+        sha: String,
+    },
+    /// Verifies the hash chain of an audit log and reports the first broken link
+    VerifyLog {
+        #[structopt(long, parse(from_os_str))]
+        audit_log: PathBuf,
+        /// Public OpenPGP key to verify signatures of checkpoints with
+        #[structopt(long, parse(from_os_str))]
+        checkpoint_pub_key: Option<PathBuf>,
+    },
+    /// Works with configuration files
+    Config(ConfigCommand),
+}
+
+#[derive(Debug, StructOpt)]
+enum ConfigCommand {
+    /// Checks a configuration file and reports all problems found
+    Check {
+        #[structopt(parse(from_os_str))]
+        config: PathBuf,
+    },
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
+    let opt = Opt::from_args();
+
+    match opt.command {
+        Some(Command::Audit {
+            repo: Some(repo),
+            graphql,
+            from,
+            to,
+            config,
+            format,
+            audit_log,
+            event_id,
+            checkpoint_key,
+            checkpoint_interval,
+            cache,
+            ..
+        }) => audit(
+            &repo,
+            graphql,
+            from,
+            to,
+            config,
+            cache,
+            Output::new(
+                format,
+                open_audit_log(audit_log, checkpoint_key, checkpoint_interval)?,
+                event_id,
+            ),
+        ),
+        Some(Command::Audit {
+            git_dir: Some(git_dir),
+            from,
+            to,
+            config,
+            format,
+            audit_log,
+            event_id,
+            checkpoint_key,
+            checkpoint_interval,
+            cache,
+            ..
+        }) => audit_local(
+            git_dir,
+            from,
+            to,
+            config,
+            cache,
+            Output::new(
+                format,
+                open_audit_log(audit_log, checkpoint_key, checkpoint_interval)?,
+                event_id,
+            ),
+        ),
+        Some(Command::Audit { .. }) => Err("either --repo or --git-dir is required".into()),
+        Some(Command::History {
+            audit_log,
+            repo,
+            sha,
+        }) => history(audit_log, &repo, &sha),
+        Some(Command::VerifyLog {
+            audit_log,
+            checkpoint_pub_key,
+        }) => verify_log(audit_log, checkpoint_pub_key),
+        Some(Command::Config(ConfigCommand::Check { config })) => check_config(config),
+        None => run_lambda(),
+    }
+}
+
+fn audit(
+    repo: &str,
+    graphql: bool,
+    from: Option<String>,
+    to: Option<String>,
+    config: PathBuf,
+    cache: Option<PathBuf>,
+    output: Output,
+) -> Result<(), Box<dyn Error>> {
+    simple_logger::init_with_level(log::Level::Warn)?;
+
+    let mut parts = repo.splitn(2, '/');
+    let repository = match (parts.next(), parts.next()) {
+        (Some(owner), Some(name)) if !owner.is_empty() && !name.is_empty() => {
+            Repository::new(owner, name)
+        }
+        _ => return Err(format!("repository '{}' is not of the form owner/name", repo).into()),
+    };
+    let config = ConfigIntegrity::from_env()
+        .and_then(|x| x.load(&config))
+        .map_err(|e| e.to_string())?;
+
+    let token = github_token()?;
+    let client = Client::with_oauth_token(&token).with_base_url(config.github.base_url.as_str());
+
+    let from = from.map(Sha::new);
+    let to = to.map(Sha::new);
+    let graphql_client = client.graphql();
+    let graphql_source = GitHubSource::new(&graphql_client, &repository);
+    let rest_source = GitHubSource::new(&client, &repository);
+    let source: &dyn CommitSource = if graphql {
+        &graphql_source
+    } else {
+        &rest_source
+    };
+    let commits = source
+        .commits(from.as_ref(), to.as_ref())
+        .map_err(|e| e.to_string())?;
+
+    let shas: Vec<_> = commits.iter().map(|x| &x.sha).collect();
+    let configs = repo_policy::effective_configs(&client, &repository, &shas, &config)
+        .map_err(|e| e.to_string())?;
+
+    print_verdicts(repo, &configs, &commits, source, cache, &output)
+}
+
+/// Per-repository policy files are not applied, because they are fetched from GitHub
+fn audit_local(
+    git_dir: PathBuf,
+    from: Option<String>,
+    to: Option<String>,
+    config: PathBuf,
+    cache: Option<PathBuf>,
+    output: Output,
+) -> Result<(), Box<dyn Error>> {
+    simple_logger::init_with_level(log::Level::Warn)?;
+
+    let repository = LocalRepository::open(&git_dir).map_err(|e| e.to_string())?;
+    let config = ConfigIntegrity::from_env()
+        .and_then(|x| x.load(&config))
+        .map_err(|e| e.to_string())?;
+
+    let head = repository
+        .resolve(to.as_ref().map(String::as_str).unwrap_or("HEAD"))
+        .map_err(|e| e.to_string())?;
+    let from = match from {
+        Some(from) => Some(repository.resolve(&from).map_err(|e| e.to_string())?),
+        None => None,
+    };
+    let commits = repository
+        .commits(from.as_ref(), Some(&head))
+        .map_err(|e| e.to_string())?;
+
+    let name = git_dir.display().to_string();
+    let configs = EffectiveConfigs::uniform(config, commits.len());
+    print_verdicts(&name, &configs, &commits, &repository, cache, &output)
+}
+
+/// How verdicts are reported and where they are recorded
+struct Output {
+    format: Format,
+    audit_log: Option<AuditLog>,
+    event_id: Option<String>,
+}
+
+impl Output {
+    fn new(format: Format, audit_log: Option<AuditLog>, event_id: Option<String>) -> Output {
+        Output {
+            format,
+            audit_log,
+            event_id,
+        }
+    }
+}
+
+fn open_audit_log(
+    audit_log: Option<PathBuf>,
+    checkpoint_key: Option<PathBuf>,
+    checkpoint_interval: u64,
+) -> Result<Option<AuditLog>, Box<dyn Error>> {
+    let audit_log = match audit_log {
+        Some(audit_log) => AuditLog::new(audit_log),
+        None => return Ok(None),
+    };
+    let audit_log = match checkpoint_key {
+        Some(checkpoint_key) => {
+            let signer = DetachedSigner::from_key_file(&checkpoint_key)
+                .map_err(|e| format!("failed to load {}, {}", checkpoint_key.display(), e))?;
+            audit_log.with_checkpoints(signer, checkpoint_interval)
+        }
+        None => audit_log,
+    };
+
+    Ok(Some(audit_log))
+}
+
+fn github_token() -> Result<OAuthToken, Box<dyn Error>> {
+    let token =
+        env::var("GITHUB_TOKEN").map_err(|_| "environment variable GITHUB_TOKEN is not set")?;
+
+    Ok(OAuthToken(token))
+}
+
+/// Keys of `github_keyring` members are fetched from GitHub, which requires `GITHUB_TOKEN`
+fn commit_verifier(config: &GitHubWatchTowerConfig) -> Result<CommitVerifier, Box<dyn Error>> {
+    if config.github_keyring.is_none() {
+        return Ok(CommitVerifier::try_from(config).map_err(|e| e.to_string())?);
+    }
+
+    let token = github_token()?;
+    let client = Client::with_oauth_token(&token).with_base_url(config.github.base_url.as_str());
+    let verifier = keyring::verifier(config, &client).map_err(|e| e.to_string())?;
+
+    Ok(verifier)
+}
+
+/// Each commit is verified by the verifier of its effective configuration; parents of web-flow
+/// merges outside of `commits` are retrieved from `source`
+fn print_verdicts(
+    repository: &str,
+    configs: &EffectiveConfigs,
+    commits: &[SignedCommit],
+    source: &dyn CommitSource,
+    cache: Option<PathBuf>,
+    output: &Output,
+) -> Result<(), Box<dyn Error>> {
+    let mut cache = match cache {
+        Some(cache) => {
+            let secret = env::var(cache::CACHE_SECRET_ENV).map_err(|_| {
+                format!(
+                    "environment variable {} is not set",
+                    cache::CACHE_SECRET_ENV
+                )
+            })?;
+            let cache: Box<dyn VerificationCache> = if is_sqlite_cache(&cache) {
+                Box::new(
+                    SqliteCache::open(cache, cache::DEFAULT_CAPACITY, secret.as_bytes())
+                        .map_err(|e| e.to_string())?,
+                )
+            } else {
+                Box::new(
+                    FileCache::open(cache, cache::DEFAULT_CAPACITY, secret.as_bytes())
+                        .map_err(|e| e.to_string())?,
+                )
+            };
+            Some(cache)
+        }
+        None => None,
+    };
+
+    // Commits are verified as a batch per configuration, so web-flow merges find their parents
+    let mut verdicts: Vec<_> = commits.iter().map(|_| None).collect();
+    for (i, config) in configs.configs.iter().enumerate() {
+        let verifier = commit_verifier(config)?;
+        let batch = match cache {
+            Some(ref mut cache) => {
+                verdict::audit_cached(&verifier, cache.as_mut(), repository, commits, Some(source))
+            }
+            None => verdict::audit(&verifier, commits, Some(source)),
+        };
+        let keyring_version = verifier.keyring_version();
+        for ((slot, verdict), index) in verdicts.iter_mut().zip(batch).zip(&configs.indices) {
+            if *index == i {
+                *slot = Some((verdict, keyring_version.clone()));
+            }
+        }
+    }
+    let (verdicts, keyring_versions): (Vec<_>, Vec<_>) = verdicts.into_iter().flatten().unzip();
+
+    if let Some(ref audit_log) = output.audit_log {
+        let records: Vec<_> = verdicts
+            .iter()
+            .zip(keyring_versions.iter())
+            .map(|(x, keyring_version)| {
+                let record = AuditRecord::new(repository, keyring_version.as_str(), x);
+                match output.event_id {
+                    Some(ref event_id) => record.with_event_id(event_id.as_str()),
+                    None => record,
+                }
+            })
+            .collect();
+        audit_log.append_all(&records).map_err(|e| e.to_string())?;
+    }
+    report::write(&mut io::stdout(), output.format, &verdicts)?;
+
+    if verdicts.iter().any(|x| !x.is_verified()) {
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Whether the cache file is a SQLite database, judging by its extension
+fn is_sqlite_cache(path: &Path) -> bool {
+    path.extension()
+        .map_or(false, |x| x == "sqlite" || x == "sqlite3" || x == "db")
+}
+
+fn history(audit_log: PathBuf, repo: &str, sha: &str) -> Result<(), Box<dyn Error>> {
+    let records = AuditLog::new(audit_log)
+        .history(repo, &Sha::new(sha))
+        .map_err(|e| e.to_string())?;
+    for record in records {
+        println!("{}", serde_json::to_string(&record)?);
+    }
+
+    Ok(())
+}
+
+fn verify_log(
+    audit_log: PathBuf,
+    checkpoint_pub_key: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    let verifier = match checkpoint_pub_key {
+        Some(ref pub_key) => {
+            Some(CommitVerifier::from_key_file(pub_key).map_err(|e| e.to_string())?)
+        }
+        None => None,
+    };
+    let verification = AuditLog::new(&audit_log)
+        .verify(verifier.as_ref())
+        .map_err(|e| e.to_string())?;
+
+    if let Some(ref broken_link) = verification.broken_link {
+        println!("{}: broken link at {}", audit_log.display(), broken_link);
+        process::exit(1);
+    }
+    println!(
+        "{} is intact; {} records, {} checkpoints",
+        audit_log.display(),
+        verification.records,
+        verification.checkpoints
+    );
+
+    Ok(())
+}
+
+fn check_config(config: PathBuf) -> Result<(), Box<dyn Error>> {
+    simple_logger::init_with_level(log::Level::Warn)?;
+
+    let check = config_check::check_file(&config);
+    for finding in check.findings() {
+        println!("{}", finding);
+    }
+
+    if !check.is_ok() {
+        process::exit(1);
+    }
+    println!("{} is valid", config.display());
+
+    Ok(())
+}
+
+fn run_lambda() -> Result<(), Box<dyn Error>> {
     simple_logger::init_with_level(log::Level::Debug)?;
-    lambda!(lambda_handler);
+
+    // Fail at start up instead of per request if the configuration cannot be trusted
+    let config = lambda_config()?;
+    if let Some(ref config) = config {
+        commit_verifier(config)?;
+        log::set_max_level(config.logging.level_filter().map_err(|e| e.to_string())?);
+        info!("Loaded configuration with {} keys", config.pub_keys.len());
+        if config.github.webhook_secret.is_none() {
+            info!("No webhook secret is configured, so signatures of deliveries are not checked");
+        }
+    }
+
+    lambda!(move |request: Request, ctx: Context| lambda_handler(request, ctx, config.as_ref()));
 
     Ok(())
 }
 
-fn lambda_handler(request: Request, ctx: Context) -> Result<impl IntoResponse, HandlerError> {
-    handle(request, ctx).map_err(|e| HandlerError::from(e.to_string().as_str()))
+/// Configuration of the Lambda handler if its path is set in the environment
+fn lambda_config() -> Result<Option<GitHubWatchTowerConfig>, Box<dyn Error>> {
+    match env::var_os(CONFIG_PATH_ENV) {
+        Some(config_path) => {
+            let config = ConfigIntegrity::from_env()
+                .and_then(|x| x.load(&config_path))
+                .map_err(|e| e.to_string())?;
+            Ok(Some(config))
+        }
+        None => Ok(None),
+    }
 }
 
-fn handle(request: Request, _ctx: Context) -> Result<impl IntoResponse, Box<dyn Error>> {
+fn lambda_handler(
+    request: Request,
+    ctx: Context,
+    config: Option<&GitHubWatchTowerConfig>,
+) -> Result<impl IntoResponse, HandlerError> {
+    handle(request, ctx, config).map_err(|e| HandlerError::from(e.to_string().as_str()))
+}
+
+fn handle(
+    request: Request,
+    _ctx: Context,
+    config: Option<&GitHubWatchTowerConfig>,
+) -> Result<impl IntoResponse, Box<dyn Error>> {
     debug!("Request headers: {:#?}", request.headers());
     debug!("Request body: {:#?}", request.body());
     debug!("Request payload: {:#?}", request.payload::<String>());
     debug!("Request context: {:#?}", request.request_context());
 
+    // If a webhook secret is configured, deliveries are only processed if GitHub signed them
+    if let Some(secret) = config.and_then(|x| x.github.webhook_secret.as_ref()) {
+        let payload: &[u8] = match request.body() {
+            Body::Empty => &[],
+            Body::Text(text) => text.as_bytes(),
+            Body::Binary(binary) => binary.as_slice(),
+        };
+        let signature = request
+            .headers()
+            .get(webhook::SIGNATURE_256_HEADER)
+            .or_else(|| request.headers().get(webhook::SIGNATURE_HEADER))
+            .and_then(|x| x.to_str().ok());
+        let verified = match signature {
+            Some(signature) => {
+                webhook::verify_signature(secret, signature, payload).map_err(|e| e.to_string())
+            }
+            None => Err("signature header is missing".to_string()),
+        };
+        if let Err(e) = verified {
+            warn!("Rejecting webhook delivery: {}", e);
+            return Ok(Response::builder()
+                .status(http::StatusCode::UNAUTHORIZED)
+                .body(r#"{"message":"Invalid signature."}"#.into())?);
+        }
+    }
+
     match request
         .headers()
         .get(http::header::CONTENT_TYPE)
@@ -0,0 +1,218 @@
+//! Loading of the configuration with optional integrity checks
+//!
+//! The configuration decides which keys are trusted, so it may be checked before it is used:
+//! against an expected SHA-256 checksum and against a detached OpenPGP signature made by a
+//! bootstrap key which is deployed separately from the configuration.
+
+use crate::{config::GitHubWatchTowerConfig, errors::*, gpg::CommitVerifier, utils::sha256};
+
+use failure::Fail;
+use log::{debug, info};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+use toml;
+
+/// Environment variable holding the path of the configuration file
+pub static CONFIG_PATH_ENV: &str = "GITHUB_WATCHTOWER_CONFIG";
+/// Environment variable holding the expected SHA-256 checksum of the configuration file in hex
+pub static CONFIG_SHA256_ENV: &str = "GITHUB_WATCHTOWER_CONFIG_SHA256";
+/// Environment variable holding the path of a detached signature of the configuration file
+pub static CONFIG_SIGNATURE_ENV: &str = "GITHUB_WATCHTOWER_CONFIG_SIGNATURE";
+/// Environment variable holding the path of the bootstrap key verifying the signature
+pub static BOOTSTRAP_KEY_ENV: &str = "GITHUB_WATCHTOWER_BOOTSTRAP_KEY";
+
+#[derive(Debug, Default)]
+pub struct ConfigIntegrity {
+    sha256: Option<String>,
+    signature: Option<PathBuf>,
+    bootstrap_key: Option<PathBuf>,
+}
+
+impl ConfigIntegrity {
+    pub fn new() -> ConfigIntegrity {
+        Default::default()
+    }
+
+    /// Reads the expected checksum, signature, and bootstrap key from the environment
+    pub fn from_env() -> Result<ConfigIntegrity> {
+        let integrity = ConfigIntegrity {
+            sha256: env::var(CONFIG_SHA256_ENV).ok(),
+            signature: env::var_os(CONFIG_SIGNATURE_ENV).map(PathBuf::from),
+            bootstrap_key: env::var_os(BOOTSTRAP_KEY_ENV).map(PathBuf::from),
+        };
+
+        if integrity.signature.is_some() && integrity.bootstrap_key.is_none() {
+            return Err(Error::from(ErrorKind::ConfigIntegrityViolated(format!(
+                "{} is set, but {} is missing",
+                CONFIG_SIGNATURE_ENV, BOOTSTRAP_KEY_ENV
+            ))));
+        }
+
+        Ok(integrity)
+    }
+
+    pub fn with_sha256<T: Into<String>>(self, sha256: T) -> Self {
+        ConfigIntegrity {
+            sha256: Some(sha256.into()),
+            ..self
+        }
+    }
+
+    pub fn with_signature<P: Into<PathBuf>, Q: Into<PathBuf>>(
+        self,
+        signature: P,
+        bootstrap_key: Q,
+    ) -> Self {
+        ConfigIntegrity {
+            signature: Some(signature.into()),
+            bootstrap_key: Some(bootstrap_key.into()),
+            ..self
+        }
+    }
+
+    /// Loads the configuration file after checking its integrity
+    ///
+    /// The file is read only once, so the checked content is the parsed content. Afterwards,
+    /// environment overrides and the Lambda environment are applied and the result is validated.
+    pub fn load<P: AsRef<Path>>(&self, config_path: P) -> Result<GitHubWatchTowerConfig> {
+        let config_path = config_path.as_ref();
+        let content = fs::read(config_path).map_err(|e| {
+            e.context(ErrorKind::InvalidConfig(format!(
+                "failed to read {}",
+                config_path.display()
+            )))
+        })?;
+
+        self.check_sha256(&content)?;
+        self.check_signature(&content)?;
+
+        let content = String::from_utf8(content)
+            .map_err(|e| e.context(ErrorKind::InvalidConfig("not UTF-8".to_string())))?;
+        let mut config: GitHubWatchTowerConfig = toml::from_str(&content).map_err(|e| {
+            let reason = e.to_string();
+            e.context(ErrorKind::InvalidConfig(reason))
+        })?;
+        config.apply_env(|x| env::var(x).ok())?;
+        config.apply_lambda_env(|x| env::var(x).ok())?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn check_sha256(&self, content: &[u8]) -> Result<()> {
+        let expected = match self.sha256 {
+            Some(ref x) => x.trim().to_lowercase(),
+            None => return Ok(()),
+        };
+
+        let actual = sha256::from_bytes_as_str(content);
+        if actual != expected {
+            return Err(Error::from(ErrorKind::ConfigIntegrityViolated(format!(
+                "SHA-256 is {}, but {} is expected",
+                actual, expected
+            ))));
+        }
+        info!("Configuration SHA-256 checksum verified");
+
+        Ok(())
+    }
+
+    fn check_signature(&self, content: &[u8]) -> Result<()> {
+        let (signature_path, bootstrap_key) = match (&self.signature, &self.bootstrap_key) {
+            (Some(signature), Some(bootstrap_key)) => (signature, bootstrap_key),
+            _ => return Ok(()),
+        };
+
+        let signature = fs::read(signature_path).map_err(|e| {
+            e.context(ErrorKind::ConfigIntegrityViolated(format!(
+                "failed to read signature {}",
+                signature_path.display()
+            )))
+        })?;
+        let verifier = CommitVerifier::from_key_file(bootstrap_key)?;
+        let keys = verifier
+            .verify_message(content, &signature)
+            .map_err(|e| ErrorKind::ConfigIntegrityViolated(e.to_string()))?;
+        debug!("Configuration signed by {:?}", keys);
+        info!("Configuration signature verified");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test;
+
+    use spectral::prelude::*;
+
+    static CONFIG_SHA256: &str = "a4a76ada9d345a82ca8ff0e300388870340dee54cb83b2c3a1605452ce357b18";
+
+    #[test]
+    fn load_without_checks() {
+        test::init();
+
+        let config = ConfigIntegrity::new().load("tests/config.toml");
+
+        asserting("Config is loaded")
+            .that(&config)
+            .is_ok()
+            .map(|x| &x.pub_keys)
+            .has_length(1);
+    }
+
+    #[test]
+    fn load_with_sha256_okay() {
+        test::init();
+
+        let config = ConfigIntegrity::new()
+            .with_sha256(CONFIG_SHA256.to_uppercase())
+            .load("tests/config.toml");
+
+        asserting("Config with expected checksum is loaded")
+            .that(&config)
+            .is_ok();
+    }
+
+    #[test]
+    fn load_with_sha256_failed() {
+        test::init();
+
+        let config = ConfigIntegrity::new()
+            .with_sha256("bc2b31c66377bbf592cc11d409b2c44b02dee7f94f755af1417129f9831b377a")
+            .load("tests/config.toml");
+
+        asserting("Config with unexpected checksum is refused")
+            .that(&config)
+            .is_err();
+    }
+
+    #[test]
+    fn load_with_signature_okay() {
+        test::init();
+
+        let config = ConfigIntegrity::new()
+            .with_signature("tests/config.toml.sig", "tests/second.pub")
+            .load("tests/config.toml");
+
+        asserting("Config signed by bootstrap key is loaded")
+            .that(&config)
+            .is_ok();
+    }
+
+    #[test]
+    fn load_with_signature_by_other_key_failed() {
+        test::init();
+
+        let config = ConfigIntegrity::new()
+            .with_signature("tests/config.toml.sig", "tests/lukas.pustina.pub")
+            .load("tests/config.toml");
+
+        asserting("Config not signed by bootstrap key is refused")
+            .that(&config)
+            .is_err();
+    }
+}
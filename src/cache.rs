@@ -0,0 +1,811 @@
+//! Cache of verification results
+//!
+//! The commits of a pull request are verified again on every push to it. A commit cannot change
+//! without changing its sha, so its verification result depends only on the verifier's trusted
+//! keys and policies. Therefore, results are cached per repository, commit, and verifier version,
+//! cf. `CommitVerifier::verification_version`. Adding, removing, or revoking a key changes the
+//! version, so results are never reused with another keyring; stale entries are evicted
+//! eventually.
+//!
+//! Only successful verifications are cached, because failures may be caused by the commit's
+//! source, e.g., GitHub not having verified a commit yet.
+//!
+//! Attention: a cached result is trusted like a verification. Persistent caches must therefore
+//! not be writable by anyone who must not be able to mark commits as verified, e.g., pull request
+//! jobs sharing a CI cache. `FileCache` and `SqliteCache` authenticate their entries with a
+//! secret, cf. `CACHE_SECRET_ENV`.
+
+use crate::{
+    errors::*,
+    github::commits::Sha,
+    gpg::{CommitVerifier, VerificationKey},
+    source::{CommitSource, SignedCommit},
+};
+
+use failure::Fail;
+use log::{trace, warn};
+use ring::{digest, hmac};
+use rusqlite::{params, Connection, OptionalExtension, NO_PARAMS};
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::{
+    collections::{BTreeMap, HashMap},
+    convert::TryFrom,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Number of cached commits if not configured otherwise
+pub const DEFAULT_CAPACITY: usize = 10_000;
+/// Environment variable holding the secret persistent cache entries are authenticated with
+pub static CACHE_SECRET_ENV: &str = "GITHUB_WATCHTOWER_CACHE_SECRET";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct CacheKey {
+    /// Repository as `owner/name` or path of a local repository
+    pub repository: String,
+    pub sha: Sha,
+    /// Cf. `CommitVerifier::verification_version`
+    pub verifier_version: String,
+}
+
+impl CacheKey {
+    pub fn new<T: Into<String>, S: Into<String>>(
+        repository: T,
+        sha: Sha,
+        verifier_version: S,
+    ) -> CacheKey {
+        CacheKey {
+            repository: repository.into(),
+            sha,
+            verifier_version: verifier_version.into(),
+        }
+    }
+}
+
+/// Backend storing verification results
+pub trait VerificationCache {
+    /// Keys of the trusted signatures of a successfully verified commit
+    fn get(&mut self, key: &CacheKey) -> Option<Vec<VerificationKey>>;
+
+    fn insert(&mut self, key: CacheKey, keys: Vec<VerificationKey>);
+
+    /// Persists cached results if the backend supports it
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// In-memory cache evicting the least recently used entry when full
+#[derive(Debug)]
+pub struct MemoryCache {
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<CacheKey, (u64, Vec<VerificationKey>)>,
+    /// Keys of `entries` by the time they were last used
+    recency: BTreeMap<u64, CacheKey>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> MemoryCache {
+        MemoryCache {
+            capacity,
+            clock: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entries, least recently used first
+    fn entries(&self) -> Vec<(&CacheKey, &Vec<VerificationKey>)> {
+        self.recency
+            .values()
+            .filter_map(|key| self.entries.get(key).map(|(_, keys)| (key, keys)))
+            .collect()
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+impl Default for MemoryCache {
+    fn default() -> MemoryCache {
+        MemoryCache::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl VerificationCache for MemoryCache {
+    fn get(&mut self, key: &CacheKey) -> Option<Vec<VerificationKey>> {
+        let now = self.tick();
+        let (used, keys) = self.entries.get_mut(key)?;
+        let key = self.recency.remove(used)?;
+        *used = now;
+        self.recency.insert(now, key);
+
+        Some(keys.clone())
+    }
+
+    fn insert(&mut self, key: CacheKey, keys: Vec<VerificationKey>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some((used, _)) = self.entries.get(&key) {
+            self.recency.remove(used);
+        } else if self.entries.len() >= self.capacity {
+            let least_recently_used = self.recency.keys().next().cloned();
+            if let Some(least_recently_used) = least_recently_used {
+                if let Some(key) = self.recency.remove(&least_recently_used) {
+                    self.entries.remove(&key);
+                }
+            }
+        }
+        let now = self.tick();
+        self.recency.insert(now, key.clone());
+        self.entries.insert(key, (now, keys));
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct FileCacheEntry {
+    key: CacheKey,
+    keys: Vec<VerificationKey>,
+    /// HMAC-SHA256 over `key` and `keys`, hex encoded
+    mac: String,
+}
+
+/// Cache stored as JSON file, e.g., between runs of a CI job
+///
+/// Entries are kept in memory and written by `flush`. The file is replaced atomically, so
+/// concurrent runs may lose entries, but never corrupt the cache. Each entry is authenticated with
+/// an HMAC keyed by a secret; entries failing authentication are dropped when the file is opened.
+pub struct FileCache {
+    path: PathBuf,
+    key: hmac::SigningKey,
+    cache: MemoryCache,
+    dirty: bool,
+}
+
+impl fmt::Debug for FileCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FileCache")
+            .field("path", &self.path)
+            .field("cache", &self.cache)
+            .field("dirty", &self.dirty)
+            .finish()
+    }
+}
+
+impl FileCache {
+    /// The file is created on the first flush; `secret` must not be empty
+    pub fn open<P: Into<PathBuf>>(path: P, capacity: usize, secret: &[u8]) -> Result<FileCache> {
+        let path = path.into();
+        if secret.is_empty() {
+            return Err(Error::from(ErrorKind::InvalidCache(format!(
+                "{}, secret is empty",
+                path.display()
+            ))));
+        }
+        let key = hmac::SigningKey::new(&digest::SHA256, secret);
+        let mut cache = MemoryCache::new(capacity);
+
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => Some(json),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                return Err(e
+                    .context(ErrorKind::InvalidCache(path.display().to_string()))
+                    .into())
+            }
+        };
+        if let Some(json) = json {
+            let entries: Vec<FileCacheEntry> = serde_json::from_str(&json)
+                .map_err(|e| e.context(ErrorKind::InvalidCache(path.display().to_string())))?;
+            for entry in entries {
+                let authentic = hex::decode(&entry.mac)
+                    .ok()
+                    .and_then(|mac| {
+                        let data = mac_data(&entry.key, &entry.keys).ok()?;
+                        hmac::verify_with_own_key(&key, &data, &mac).ok()
+                    })
+                    .is_some();
+                if authentic {
+                    cache.insert(entry.key, entry.keys);
+                } else {
+                    warn!(
+                        "Dropping unauthentic entry for commit {} from {}",
+                        entry.key.sha,
+                        path.display()
+                    );
+                }
+            }
+        }
+
+        Ok(FileCache {
+            path,
+            key,
+            cache,
+            dirty: false,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+impl VerificationCache for FileCache {
+    fn get(&mut self, key: &CacheKey) -> Option<Vec<VerificationKey>> {
+        self.cache.get(key)
+    }
+
+    fn insert(&mut self, key: CacheKey, keys: Vec<VerificationKey>) {
+        self.cache.insert(key, keys);
+        self.dirty = true;
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+
+        let write_error = |e: serde_json::Error| -> Error {
+            e.context(ErrorKind::FailedToWriteCache(
+                self.path.display().to_string(),
+            ))
+            .into()
+        };
+        let entries = self
+            .cache
+            .entries()
+            .into_iter()
+            .map(|(key, keys)| {
+                let data = mac_data(key, keys).map_err(write_error)?;
+                Ok(FileCacheEntry {
+                    key: key.clone(),
+                    keys: keys.clone(),
+                    mac: hex::encode(hmac::sign(&self.key, &data)),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let json = serde_json::to_string(&entries).map_err(write_error)?;
+
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, json)
+            .and_then(|_| fs::rename(&tmp_path, &self.path))
+            .map_err(|e| {
+                e.context(ErrorKind::FailedToWriteCache(
+                    self.path.display().to_string(),
+                ))
+            })?;
+        self.dirty = false;
+
+        Ok(())
+    }
+}
+
+const SQLITE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS verifications (
+        repository TEXT NOT NULL,
+        sha TEXT NOT NULL,
+        verifier_version TEXT NOT NULL,
+        keys TEXT NOT NULL,
+        mac TEXT NOT NULL,
+        used INTEGER NOT NULL,
+        PRIMARY KEY (repository, sha, verifier_version)
+    );
+    CREATE INDEX IF NOT EXISTS verifications_used ON verifications (used);
+";
+/// Time to wait for other connections to release a locked database
+const SQLITE_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Cache stored in a SQLite database, e.g., shared by concurrent runs of a service
+///
+/// In contrast to `FileCache`, entries are read and written on access, so concurrent runs share
+/// their results without losing any. Entries are authenticated like `FileCache` entries; entries
+/// failing authentication are deleted when read. Database errors while reading or writing entries
+/// are logged and treated like cache misses.
+pub struct SqliteCache {
+    path: PathBuf,
+    key: hmac::SigningKey,
+    capacity: i64,
+    connection: Connection,
+}
+
+impl fmt::Debug for SqliteCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SqliteCache")
+            .field("path", &self.path)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl SqliteCache {
+    /// The database is created if it does not exist; `secret` must not be empty
+    pub fn open<P: Into<PathBuf>>(path: P, capacity: usize, secret: &[u8]) -> Result<SqliteCache> {
+        let path = path.into();
+        if secret.is_empty() {
+            return Err(Error::from(ErrorKind::InvalidCache(format!(
+                "{}, secret is empty",
+                path.display()
+            ))));
+        }
+        let key = hmac::SigningKey::new(&digest::SHA256, secret);
+        let capacity = i64::try_from(capacity).unwrap_or(i64::max_value());
+
+        let connection = Connection::open(&path)
+            .and_then(|connection| {
+                connection.busy_timeout(SQLITE_BUSY_TIMEOUT)?;
+                connection.execute_batch(SQLITE_SCHEMA)?;
+                Ok(connection)
+            })
+            .map_err(|e| e.context(ErrorKind::InvalidCache(path.display().to_string())))?;
+
+        Ok(SqliteCache {
+            path,
+            key,
+            capacity,
+            connection,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> Result<usize> {
+        let len: i64 = self
+            .connection
+            .query_row("SELECT COUNT(*) FROM verifications", NO_PARAMS, |row| {
+                row.get(0)
+            })
+            .map_err(|e| self.read_error(e))?;
+
+        Ok(len as usize)
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    fn lookup(&self, key: &CacheKey) -> Result<Option<Vec<VerificationKey>>> {
+        let sha = key.sha.to_string();
+
+        let row: Option<(String, String)> = self
+            .connection
+            .query_row(
+                "SELECT keys, mac FROM verifications \
+                 WHERE repository = ?1 AND sha = ?2 AND verifier_version = ?3",
+                params![key.repository, sha, key.verifier_version],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| self.read_error(e))?;
+        let (keys, mac) = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        let keys = serde_json::from_str::<Vec<VerificationKey>>(&keys)
+            .ok()
+            .filter(|keys| {
+                hex::decode(&mac)
+                    .ok()
+                    .and_then(|mac| {
+                        let data = mac_data(key, keys).ok()?;
+                        hmac::verify_with_own_key(&self.key, &data, &mac).ok()
+                    })
+                    .is_some()
+            });
+        if let Some(keys) = keys {
+            self.connection
+                .execute(
+                    "UPDATE verifications \
+                     SET used = (SELECT COALESCE(MAX(used), 0) + 1 FROM verifications) \
+                     WHERE repository = ?1 AND sha = ?2 AND verifier_version = ?3",
+                    params![key.repository, sha, key.verifier_version],
+                )
+                .map_err(|e| self.read_error(e))?;
+            Ok(Some(keys))
+        } else {
+            warn!(
+                "Deleting unauthentic entry for commit {} from {}",
+                key.sha,
+                self.path.display()
+            );
+            self.connection
+                .execute(
+                    "DELETE FROM verifications \
+                     WHERE repository = ?1 AND sha = ?2 AND verifier_version = ?3",
+                    params![key.repository, sha, key.verifier_version],
+                )
+                .map_err(|e| self.read_error(e))?;
+            Ok(None)
+        }
+    }
+
+    fn store(&self, key: &CacheKey, keys: &[VerificationKey]) -> Result<()> {
+        let data = mac_data(key, keys).map_err(|e| self.write_error(e))?;
+        let keys = serde_json::to_string(keys).map_err(|e| self.write_error(e))?;
+        let mac = hex::encode(hmac::sign(&self.key, &data));
+
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO verifications \
+                 (repository, sha, verifier_version, keys, mac, used) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, \
+                 (SELECT COALESCE(MAX(used), 0) + 1 FROM verifications))",
+                params![
+                    key.repository,
+                    key.sha.to_string(),
+                    key.verifier_version,
+                    keys,
+                    mac
+                ],
+            )
+            .and_then(|_| {
+                self.connection.execute(
+                    "DELETE FROM verifications WHERE used <= \
+                     (SELECT used FROM verifications ORDER BY used DESC LIMIT 1 OFFSET ?1)",
+                    params![self.capacity],
+                )
+            })
+            .map_err(|e| self.write_error(e))?;
+
+        Ok(())
+    }
+
+    fn read_error<E: Fail>(&self, e: E) -> Error {
+        e.context(ErrorKind::InvalidCache(self.path.display().to_string()))
+            .into()
+    }
+
+    fn write_error<E: Fail>(&self, e: E) -> Error {
+        e.context(ErrorKind::FailedToWriteCache(
+            self.path.display().to_string(),
+        ))
+        .into()
+    }
+}
+
+impl VerificationCache for SqliteCache {
+    fn get(&mut self, key: &CacheKey) -> Option<Vec<VerificationKey>> {
+        self.lookup(key).unwrap_or_else(|e| {
+            warn!("Failed to read verification cache: {}", e);
+            None
+        })
+    }
+
+    fn insert(&mut self, key: CacheKey, keys: Vec<VerificationKey>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Err(e) = self.store(&key, &keys) {
+            warn!("Failed to write verification cache: {}", e);
+        }
+    }
+}
+
+/// The data authenticated by the MAC of a `FileCacheEntry` or `SqliteCache` row
+fn mac_data(
+    key: &CacheKey,
+    keys: &[VerificationKey],
+) -> ::std::result::Result<Vec<u8>, serde_json::Error> {
+    serde_json::to_vec(&(key, keys))
+}
+
+/// Verifies commits of `repository` as a batch like `CommitVerifier::verify_signed_commits`, but
+/// looks up results in `cache` first and caches new successful results
+///
+/// Parents of web-flow merges outside of the batch are retrieved from `source`, if set.
+///
+/// The cache is flushed afterwards; failing to do so is logged, but does not fail the
+/// verification.
+pub fn verify_signed_commits<C: VerificationCache + ?Sized>(
+    verifier: &CommitVerifier,
+    cache: &mut C,
+    repository: &str,
+    commits: &[SignedCommit],
+    source: Option<&dyn CommitSource>,
+) -> Vec<Result<Vec<VerificationKey>>> {
+    let verifier_version = match verifier.verification_version() {
+        Ok(verifier_version) => verifier_version,
+        Err(e) => {
+            warn!("Not using verification cache: {}", e);
+            return verifier.verify_signed_commits(commits, source);
+        }
+    };
+
+    let results = verifier.verify_signed_commits_with(commits, source, |commit| {
+        let key = CacheKey::new(repository, commit.sha.clone(), verifier_version.as_str());
+        if let Some(keys) = cache.get(&key) {
+            trace!("Using cached verification of commit {}", commit.sha);
+            return Ok(keys);
+        }
+
+        let res = verifier.verify_signed_commit(commit);
+        if let Ok(ref keys) = res {
+            cache.insert(key, keys.clone());
+        }
+        res
+    });
+    if let Err(e) = cache.flush() {
+        warn!("Failed to flush verification cache: {}", e);
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{source::Person, utils::test};
+
+    use chrono::DateTime;
+    use spectral::prelude::*;
+    use std::env;
+
+    fn key(sha: &str, verifier_version: &str) -> CacheKey {
+        CacheKey::new(
+            "lukaspustina/github-watchtower",
+            Sha::new(sha),
+            verifier_version,
+        )
+    }
+
+    fn keys() -> Vec<VerificationKey> {
+        vec![VerificationKey::from_ssh_key(
+            "SHA256:Bc8bOb5eTLsyi6wiwJhuy5mbhwWGjOFq3bfOu6nvqE8".to_string(),
+            vec!["second@example.com".to_string()],
+        )]
+    }
+
+    fn unsigned_commit(sha: &str) -> SignedCommit {
+        let person = Person {
+            name: "Second Test".to_string(),
+            email: "second@example.com".to_string(),
+            date: DateTime::parse_from_rfc3339("2019-06-25T12:00:00+02:00").unwrap(),
+        };
+
+        SignedCommit {
+            sha: Sha::new(sha),
+            parents: Vec::new(),
+            author: person.clone(),
+            committer: person,
+            message: "Test commit".to_string(),
+            signature: None,
+            payload: None,
+            source_reason: None,
+        }
+    }
+
+    #[test]
+    fn memory_cache_evicts_least_recently_used() {
+        test::init();
+
+        let mut cache = MemoryCache::new(2);
+        cache.insert(key("1", "v1"), keys());
+        cache.insert(key("2", "v1"), keys());
+        let _ = cache.get(&key("1", "v1"));
+        cache.insert(key("3", "v1"), keys());
+
+        asserting("capacity is kept")
+            .that(&cache.len())
+            .is_equal_to(2);
+        asserting("recently used entry is kept")
+            .that(&cache.get(&key("1", "v1")))
+            .is_some();
+        asserting("least recently used entry is evicted")
+            .that(&cache.get(&key("2", "v1")))
+            .is_none();
+        asserting("other verifier version misses")
+            .that(&cache.get(&key("1", "v2")))
+            .is_none();
+    }
+
+    #[test]
+    fn file_cache_flush_and_open() {
+        test::init();
+
+        let path = env::temp_dir().join("github-watchtower-file-cache-test.json");
+        let _ = fs::remove_file(&path);
+
+        let mut cache = FileCache::open(&path, 10, b"secret").expect("failed to open cache");
+        cache.insert(key("1", "v1"), keys());
+        cache.flush().expect("failed to flush cache");
+        let reopened = FileCache::open(&path, 10, b"secret").map(|mut x| x.get(&key("1", "v1")));
+        let other_secret = FileCache::open(&path, 10, b"other").map(|x| x.len());
+        let _ = fs::remove_file(&path);
+
+        asserting("flushed entries are read")
+            .that(&reopened)
+            .is_ok()
+            .is_equal_to(&Some(keys()));
+        asserting("entries authenticated with another secret are dropped")
+            .that(&other_secret)
+            .is_ok()
+            .is_equal_to(&0);
+    }
+
+    #[test]
+    fn file_cache_drops_forged_entries() {
+        test::init();
+
+        let path = env::temp_dir().join("github-watchtower-file-cache-forged-test.json");
+        let _ = fs::remove_file(&path);
+
+        let mut cache = FileCache::open(&path, 10, b"secret").expect("failed to open cache");
+        cache.insert(key("1", "v1"), keys());
+        cache.flush().expect("failed to flush cache");
+        let json = fs::read_to_string(&path)
+            .expect("failed to read cache")
+            .replace(r#""sha":"1""#, r#""sha":"2""#);
+        fs::write(&path, json).expect("failed to write cache");
+        let forged = FileCache::open(&path, 10, b"secret").map(|mut x| x.get(&key("2", "v1")));
+        let _ = fs::remove_file(&path);
+
+        asserting("entry modified without the secret is dropped")
+            .that(&forged)
+            .is_ok()
+            .is_none();
+    }
+
+    #[test]
+    fn sqlite_cache_insert_and_open() {
+        test::init();
+
+        let path = env::temp_dir().join("github-watchtower-sqlite-cache-test.sqlite");
+        let _ = fs::remove_file(&path);
+
+        let mut cache = SqliteCache::open(&path, 2, b"secret").expect("failed to open cache");
+        cache.insert(key("1", "v1"), keys());
+        cache.insert(key("2", "v1"), keys());
+        let _ = cache.get(&key("1", "v1"));
+        cache.insert(key("3", "v1"), keys());
+        let len = cache.len();
+        let reopened = SqliteCache::open(&path, 2, b"secret").map(|mut x| {
+            (
+                x.get(&key("1", "v1")),
+                x.get(&key("2", "v1")),
+                x.get(&key("1", "v2")),
+            )
+        });
+        let other_secret =
+            SqliteCache::open(&path, 2, b"other").map(|mut x| x.get(&key("3", "v1")));
+        let remaining = SqliteCache::open(&path, 2, b"secret").and_then(|x| x.len());
+        let _ = fs::remove_file(&path);
+
+        asserting("capacity is kept")
+            .that(&len)
+            .is_ok()
+            .is_equal_to(&2);
+        asserting("recently used entry is kept, least recently used entry is evicted")
+            .that(&reopened)
+            .is_ok()
+            .is_equal_to(&(Some(keys()), None, None));
+        asserting("entry authenticated with another secret misses")
+            .that(&other_secret)
+            .is_ok()
+            .is_none();
+        asserting("entry authenticated with another secret is deleted")
+            .that(&remaining)
+            .is_ok()
+            .is_equal_to(&1);
+    }
+
+    #[test]
+    fn sqlite_cache_empty_secret_failed() {
+        test::init();
+
+        let path = env::temp_dir().join("github-watchtower-sqlite-cache-secret-test.sqlite");
+        let res = SqliteCache::open(&path, 2, b"");
+
+        asserting("empty secret is rejected").that(&res).is_err();
+    }
+
+    #[test]
+    fn revoked_key_invalidates_cache() {
+        test::init();
+
+        let verifier = CommitVerifier::from_key_file("tests/unrevoked.pub")
+            .expect("failed to load public key");
+        let revoked_verifier =
+            CommitVerifier::from_key_file("tests/revoked.pub").expect("failed to load public key");
+        let commits = vec![unsigned_commit("1")];
+        let mut cache = MemoryCache::default();
+        cache.insert(
+            CacheKey::new(
+                "lukaspustina/github-watchtower",
+                Sha::new("1"),
+                verifier
+                    .verification_version()
+                    .expect("failed to compute version"),
+            ),
+            keys(),
+        );
+
+        let before = verify_signed_commits(
+            &verifier,
+            &mut cache,
+            "lukaspustina/github-watchtower",
+            &commits,
+            None,
+        );
+        let after = verify_signed_commits(
+            &revoked_verifier,
+            &mut cache,
+            "lukaspustina/github-watchtower",
+            &commits,
+            None,
+        );
+
+        asserting("revocation keeps the fingerprint")
+            .that(&revoked_verifier.keyring_version())
+            .is_equal_to(&verifier.keyring_version());
+        asserting("cached result is used before the revocation")
+            .that(&before[0])
+            .is_ok();
+        asserting("cached result is not used after the revocation")
+            .that(&after[0])
+            .is_err();
+    }
+
+    #[test]
+    fn verify_signed_commits_uses_cache() {
+        test::init();
+
+        let verifier =
+            CommitVerifier::from_key_file("tests/second.pub").expect("failed to load public key");
+        let commits = vec![unsigned_commit("1"), unsigned_commit("2")];
+        let mut cache = MemoryCache::default();
+        cache.insert(
+            CacheKey::new(
+                "lukaspustina/github-watchtower",
+                Sha::new("1"),
+                verifier
+                    .verification_version()
+                    .expect("failed to compute version"),
+            ),
+            keys(),
+        );
+
+        let results = verify_signed_commits(
+            &verifier,
+            &mut cache,
+            "lukaspustina/github-watchtower",
+            &commits,
+            None,
+        );
+
+        asserting("cached result is used")
+            .that(&results[0])
+            .is_ok()
+            .is_equal_to(&keys());
+        asserting("uncached commit is verified")
+            .that(&results[1])
+            .is_err();
+        asserting("failed verification is not cached")
+            .that(&cache.len())
+            .is_equal_to(1);
+    }
+}
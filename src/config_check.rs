@@ -0,0 +1,342 @@
+//! Validation of a configuration file before it is deployed
+//!
+//! In contrast to loading a configuration, which stops at the first problem, checking reports all
+//! problems together with the field they have been found at.
+
+use crate::{
+    config::{GitHubWatchTowerConfig, PubKey},
+    errors::*,
+    gpg::check_fingerprint,
+    ssh::SshSigner,
+    x509::X509Verifier,
+};
+
+use openpgp::{parse::Parse, RevocationStatus, TPK};
+use sequoia_openpgp as openpgp;
+use std::{collections::HashMap, convert::TryFrom, fmt, fs, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The configuration cannot be loaded or would not work as intended
+    Error,
+    /// The configuration can be loaded, but is probably not what was intended
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => f.write_str("error"),
+            Severity::Warning => f.write_str("warning"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Path of the offending field, e.g., `pub_key[1].armored_key`
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.severity, self.field, self.message)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ConfigCheck {
+    findings: Vec<Finding>,
+}
+
+impl ConfigCheck {
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+
+    /// A configuration is fine if there are no errors; warnings are acceptable
+    pub fn is_ok(&self) -> bool {
+        !self.findings.iter().any(|x| x.severity == Severity::Error)
+    }
+
+    fn error<T: Into<String>, S: Into<String>>(&mut self, field: T, message: S) {
+        self.push(Severity::Error, field, message);
+    }
+
+    fn warning<T: Into<String>, S: Into<String>>(&mut self, field: T, message: S) {
+        self.push(Severity::Warning, field, message);
+    }
+
+    fn push<T: Into<String>, S: Into<String>>(&mut self, severity: Severity, field: T, message: S) {
+        self.findings.push(Finding {
+            severity,
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+}
+
+/// Checks a configuration file
+pub fn check_file<P: AsRef<Path>>(config_path: P) -> ConfigCheck {
+    match fs::read_to_string(config_path.as_ref()) {
+        Ok(toml) => check_toml(&toml),
+        Err(e) => {
+            let mut check = ConfigCheck::default();
+            check.error(config_path.as_ref().to_string_lossy(), e.to_string());
+            check
+        }
+    }
+}
+
+/// Checks a configuration including fields which would be ignored when loading it
+pub fn check_toml(toml: &str) -> ConfigCheck {
+    let (config, unknown_fields) = match GitHubWatchTowerConfig::from_toml_checked(toml) {
+        Ok(x) => x,
+        Err(e) => {
+            let mut check = ConfigCheck::default();
+            check.error("config", e.to_string());
+            return check;
+        }
+    };
+
+    let mut result = check(&config);
+    for field in unknown_fields {
+        result.warning(field, "unknown field is ignored");
+    }
+
+    result
+}
+
+/// Checks a configuration for problems which would surface only when it is used
+pub fn check(config: &GitHubWatchTowerConfig) -> ConfigCheck {
+    let mut check = ConfigCheck::default();
+
+    for e in config.validation_errors() {
+        match e.kind() {
+            ErrorKind::InvalidConfigField(field, reason) => {
+                check.error(field.as_str(), reason.as_str())
+            }
+            _ => check.error("config", e.to_string()),
+        }
+    }
+    check_pub_keys(&mut check, "pub_key", &config.pub_keys);
+    check_pub_keys(&mut check, "release_pub_key", &config.release_pub_keys);
+
+    for (i, signer) in config.allowed_signers.iter().enumerate() {
+        if let Err(e) = SshSigner::try_from(signer) {
+            check.error(format!("allowed_signer[{}].public_key", i), e.to_string());
+        }
+    }
+    for (i, root_ca) in config.x509_root_cas.iter().enumerate() {
+        if let Err(e) = X509Verifier::from_pem_files(&[root_ca]) {
+            check.error(format!("x509_root_cas[{}]", i), e.to_string());
+        }
+    }
+    if config.policy.trust_web_flow && config.web_flow_key.is_none() {
+        check.error(
+            "web_flow_key",
+            "is required if policy.trust_web_flow is set",
+        );
+    }
+
+    for (i, team) in config.teams.iter().enumerate() {
+        for (j, member) in team.members.iter().enumerate() {
+            let known = config.pub_keys.iter().any(|x| &x.name == member)
+                || config.allowed_signers.iter().any(|x| &x.name == member);
+            if !known {
+                check.warning(
+                    format!("team[{}].members[{}]", i, j),
+                    format!("'{}' has no pub_key or allowed_signer", member),
+                );
+            }
+        }
+    }
+
+    check
+}
+
+fn check_pub_keys(check: &mut ConfigCheck, section: &str, pub_keys: &[PubKey]) {
+    // Fingerprint to the first field it has been found at
+    let mut finger_prints: HashMap<String, String> = HashMap::new();
+
+    for (i, pub_key) in pub_keys.iter().enumerate() {
+        let field = |x: &str| format!("{}[{}].{}", section, i, x);
+
+        let tpk = match TPK::from_bytes(pub_key.armored_key.as_bytes()) {
+            Ok(tpk) => tpk,
+            Err(e) => {
+                check.error(field("armored_key"), format!("failed to parse key, {}", e));
+                continue;
+            }
+        };
+
+        if let Err(e) = check_fingerprint(pub_key, &tpk) {
+            check.error(field("fingerprint"), e.to_string());
+        }
+
+        let finger_print = tpk.fingerprint().to_hex();
+        if let Some(other) = finger_prints.get(&finger_print) {
+            check.error(
+                field("armored_key"),
+                format!(
+                    "key {} is already configured at {}",
+                    tpk.fingerprint(),
+                    other
+                ),
+            );
+        } else {
+            finger_prints.insert(finger_print, field("armored_key"));
+        }
+
+        if let RevocationStatus::Revoked(_) = tpk.revoked(None) {
+            check.error(
+                field("armored_key"),
+                format!("key {} has been revoked", tpk.fingerprint()),
+            );
+            continue;
+        }
+        // Signatures created before expiration are still valid, so this is not an error
+        if let Some(selfsig) = tpk.primary_key_signature() {
+            if selfsig.key_expired(tpk.primary()) {
+                check.warning(
+                    field("armored_key"),
+                    format!("key {} has expired", tpk.fingerprint()),
+                );
+            }
+        }
+
+        let can_sign = tpk.keys_all().any(|(binding, revocation, _)| {
+            let revoked = match revocation {
+                RevocationStatus::Revoked(_) => true,
+                _ => false,
+            };
+            !revoked && binding.map(|x| x.key_flags().can_sign()).unwrap_or(false)
+        });
+        if !can_sign {
+            check.error(
+                field("armored_key"),
+                format!("key {} has no signing capable (sub)key", tpk.fingerprint()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test;
+
+    use spectral::prelude::*;
+
+    fn pub_key_entry(name: &str, armored_key: &str) -> String {
+        format!(
+            r#"
+            [[pub_key]]
+            name = "{}"
+            armored_key = """{}"""
+            "#,
+            name, armored_key
+        )
+    }
+
+    #[test]
+    fn check_okay() {
+        test::init();
+
+        let toml = pub_key_entry("Second", include_str!("../tests/second.pub"));
+
+        let check = check_toml(&toml);
+
+        asserting("valid config has no findings")
+            .that(&check.findings().len())
+            .is_equal_to(0);
+    }
+
+    #[test]
+    fn check_reports_all_broken_keys() {
+        test::init();
+
+        let toml = [
+            pub_key_entry("Second", include_str!("../tests/second.pub")),
+            pub_key_entry("Broken", "-----BEGIN PGP PUBLIC KEY BLOCK-----"),
+            pub_key_entry("Second again", include_str!("../tests/second.pub")),
+            pub_key_entry("Revoked", include_str!("../tests/revoked.pub")),
+            pub_key_entry("Cert only", include_str!("../tests/cert-only.pub")),
+        ]
+        .concat();
+
+        let check = check_toml(&toml);
+        let fields: Vec<_> = check
+            .findings()
+            .iter()
+            .filter(|x| x.severity == Severity::Error)
+            .map(|x| (x.severity, x.field.as_str()))
+            .collect();
+
+        asserting("config is not ok")
+            .that(&check.is_ok())
+            .is_false();
+        asserting("all broken keys are reported")
+            .that(&fields)
+            .is_equal_to(&vec![
+                (Severity::Error, "pub_key[1].armored_key"),
+                (Severity::Error, "pub_key[2].armored_key"),
+                (Severity::Error, "pub_key[3].armored_key"),
+                (Severity::Error, "pub_key[4].armored_key"),
+            ]);
+    }
+
+    #[test]
+    fn check_reports_expired_keys_and_unknown_fields_as_warnings() {
+        test::init();
+
+        let toml = [
+            pub_key_entry("Expired", include_str!("../tests/expired.pub")),
+            "[policy]\nrequire_pusher_owns_keys = true\n".to_string(),
+        ]
+        .concat();
+
+        let check = check_toml(&toml);
+
+        asserting("config is ok").that(&check.is_ok()).is_true();
+        asserting("expired key and unknown field are reported")
+            .that(&check.findings().len())
+            .is_equal_to(2);
+    }
+
+    #[test]
+    fn check_team_members_with_pub_key_or_allowed_signer() {
+        test::init();
+
+        let toml = [
+            pub_key_entry("Second", include_str!("../tests/second.pub")),
+            format!(
+                r#"
+                [[allowed_signer]]
+                name = "Alice"
+                principals = ["alice@example.com"]
+                public_key = "{}"
+
+                [[team]]
+                name = "platform"
+                members = ["Second", "Alice", "Mallory"]
+                "#,
+                include_str!("../tests/ssh-ed25519.pub").trim()
+            ),
+        ]
+        .concat();
+
+        let check = check_toml(&toml);
+        let fields: Vec<_> = check
+            .findings()
+            .iter()
+            .map(|x| (x.severity, x.field.as_str()))
+            .collect();
+
+        asserting("only the member without key is reported")
+            .that(&fields)
+            .is_equal_to(&vec![(Severity::Warning, "team[0].members[2]")]);
+    }
+}
@@ -40,6 +40,72 @@ pub enum ErrorKind {
     #[fail(display = "failed to verify GPG signature because {}", _0)]
     FailedToVerify(String),
 
+    #[fail(display = "failed to create GPG signature because {}", _0)]
+    FailedToSign(String),
+
+    #[fail(display = "signing key {} was expired at signature creation time", _0)]
+    KeyExpired(String),
+
+    #[fail(display = "signing key {} has been revoked", _0)]
+    KeyRevoked(String),
+
+    #[fail(display = "signature has been created before signing key {}", _0)]
+    SignatureBeforeKeyCreation(String),
+
+    #[fail(display = "signing key {} is not allowed by configuration", _0)]
+    SubkeyNotAllowed(String),
+
+    #[fail(
+        display = "key of pub_key entry '{}' has fingerprint {}, but {} is expected",
+        _0, _2, _1
+    )]
+    FingerprintMismatch(String, String, String),
+
+    #[fail(display = "unexpected key {} of GitHub user {}", _1, _0)]
+    UnexpectedKey(String, String),
+
+    #[fail(display = "pushing user {} does not own a signing key", _0)]
+    PusherDoesNotOwnKey(String),
+
+    #[fail(display = "invalid GitHub web-flow key, {}", _0)]
+    InvalidWebFlowKey(String),
+
+    #[fail(display = "configuration integrity violated, {}", _0)]
+    ConfigIntegrityViolated(String),
+
+    #[fail(display = "invalid configuration, {}", _0)]
+    InvalidConfig(String),
+
+    #[fail(display = "invalid configuration field '{}', {}", _0, _1)]
+    InvalidConfigField(String, String),
+
+    #[fail(display = "invalid git repository, {}", _0)]
+    InvalidGitRepository(String),
+
+    #[fail(display = "git object {} not found", _0)]
+    GitObjectNotFound(String),
+
+    #[fail(display = "invalid commit source, {}", _0)]
+    InvalidCommitSource(String),
+
+    #[fail(display = "invalid report format '{}'", _0)]
+    InvalidReportFormat(String),
+
+    #[fail(display = "failed to write audit log {}", _0)]
+    FailedToWriteAuditLog(String),
+
+    #[fail(display = "invalid audit log {}", _0)]
+    InvalidAuditLog(String),
+
+    #[fail(display = "invalid verification cache {}", _0)]
+    InvalidCache(String),
+
+    #[fail(display = "failed to write verification cache {}", _0)]
+    FailedToWriteCache(String),
+
+    #[fail(display = "invalid webhook signature, {}", _0)]
+    InvalidWebhookSignature(String),
+
     #[fail(display = "general error")]
     GeneralError,
 }
@@ -61,6 +127,35 @@ impl Clone for ErrorKind {
             FailedToLoadKey => FailedToLoadKey,
             FailedToCreateVerifier => FailedToCreateVerifier,
             FailedToVerify(ref reason) => FailedToVerify(reason.clone()),
+            FailedToSign(ref reason) => FailedToSign(reason.clone()),
+            KeyExpired(ref fingerprint) => KeyExpired(fingerprint.clone()),
+            KeyRevoked(ref fingerprint) => KeyRevoked(fingerprint.clone()),
+            SignatureBeforeKeyCreation(ref fingerprint) => {
+                SignatureBeforeKeyCreation(fingerprint.clone())
+            }
+            SubkeyNotAllowed(ref fingerprint) => SubkeyNotAllowed(fingerprint.clone()),
+            FingerprintMismatch(ref name, ref expected, ref actual) => {
+                FingerprintMismatch(name.clone(), expected.clone(), actual.clone())
+            }
+            UnexpectedKey(ref login, ref fingerprint) => {
+                UnexpectedKey(login.clone(), fingerprint.clone())
+            }
+            PusherDoesNotOwnKey(ref login) => PusherDoesNotOwnKey(login.clone()),
+            InvalidWebFlowKey(ref reason) => InvalidWebFlowKey(reason.clone()),
+            ConfigIntegrityViolated(ref reason) => ConfigIntegrityViolated(reason.clone()),
+            InvalidConfig(ref reason) => InvalidConfig(reason.clone()),
+            InvalidConfigField(ref field, ref reason) => {
+                InvalidConfigField(field.clone(), reason.clone())
+            }
+            InvalidGitRepository(ref reason) => InvalidGitRepository(reason.clone()),
+            GitObjectNotFound(ref sha) => GitObjectNotFound(sha.clone()),
+            InvalidCommitSource(ref reason) => InvalidCommitSource(reason.clone()),
+            InvalidReportFormat(ref format) => InvalidReportFormat(format.clone()),
+            FailedToWriteAuditLog(ref path) => FailedToWriteAuditLog(path.clone()),
+            InvalidAuditLog(ref reason) => InvalidAuditLog(reason.clone()),
+            InvalidCache(ref path) => InvalidCache(path.clone()),
+            FailedToWriteCache(ref path) => FailedToWriteCache(path.clone()),
+            InvalidWebhookSignature(ref reason) => InvalidWebhookSignature(reason.clone()),
             GeneralError => GeneralError,
         }
     }
@@ -0,0 +1,972 @@
+//! Read-only access to local git repositories for verifying commits without GitHub
+//!
+//! Objects are read from loose object files and from pack files including deltified objects.
+//! Commits are read as `SignedCommit`s so they can be verified and reported exactly like commits
+//! retrieved from GitHub.
+
+use crate::{
+    errors::*,
+    github::commits::Sha,
+    source::{CommitSource, Person, SignedCommit},
+};
+
+use chrono::{DateTime, FixedOffset, TimeZone};
+use failure::Fail;
+use flate2::read::ZlibDecoder;
+use log::{debug, trace};
+use ring::digest;
+use std::{
+    cell::RefCell,
+    collections::{HashSet, VecDeque},
+    fs::{self, File},
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+impl ObjectKind {
+    fn from_name(name: &[u8]) -> Option<ObjectKind> {
+        match name {
+            b"commit" => Some(ObjectKind::Commit),
+            b"tree" => Some(ObjectKind::Tree),
+            b"blob" => Some(ObjectKind::Blob),
+            b"tag" => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ObjectKind::Commit => "commit",
+            ObjectKind::Tree => "tree",
+            ObjectKind::Blob => "blob",
+            ObjectKind::Tag => "tag",
+        }
+    }
+
+    fn from_pack_type(pack_type: u8) -> Option<ObjectKind> {
+        match pack_type {
+            1 => Some(ObjectKind::Commit),
+            2 => Some(ObjectKind::Tree),
+            3 => Some(ObjectKind::Blob),
+            4 => Some(ObjectKind::Tag),
+            _ => None,
+        }
+    }
+}
+
+const PACK_OFS_DELTA: u8 = 6;
+const PACK_REF_DELTA: u8 = 7;
+/// Maximum length of delta chains; git's default for `pack.depth`
+const MAX_DELTA_DEPTH: usize = 50;
+/// Maximum size of objects built from deltas; GitHub rejects files larger than 100 MiB
+const MAX_OBJECT_SIZE: usize = 100 * 1024 * 1024;
+
+#[derive(Debug)]
+pub struct LocalRepository {
+    git_dir: PathBuf,
+    packs: Vec<Pack>,
+}
+
+impl LocalRepository {
+    /// Opens a repository from its work tree or its git directory, e.g., a bare repository
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<LocalRepository> {
+        let path = path.as_ref();
+        let git_dir = if path.join(".git").is_dir() {
+            path.join(".git")
+        } else {
+            path.to_path_buf()
+        };
+        if !git_dir.join("objects").is_dir() {
+            return Err(Error::from(ErrorKind::InvalidGitRepository(format!(
+                "{} is not a git repository",
+                path.display()
+            ))));
+        }
+
+        let mut packs = Vec::new();
+        let pack_dir = git_dir.join("objects").join("pack");
+        if pack_dir.is_dir() {
+            let entries = fs::read_dir(&pack_dir).map_err(|e| {
+                e.context(ErrorKind::InvalidGitRepository(
+                    "failed to list pack files".to_string(),
+                ))
+            })?;
+            for entry in entries {
+                let idx_path = entry
+                    .map_err(|e| {
+                        e.context(ErrorKind::InvalidGitRepository(
+                            "failed to list pack files".to_string(),
+                        ))
+                    })?
+                    .path();
+                if idx_path.extension().map(|x| x == "idx").unwrap_or(false) {
+                    packs.push(Pack::open(&idx_path)?);
+                }
+            }
+        }
+        debug!("Opened {} with {} packs", git_dir.display(), packs.len());
+
+        Ok(LocalRepository { git_dir, packs })
+    }
+
+    /// Resolves a full sha, `HEAD`, a full ref name, or a branch or tag name to a commit
+    pub fn resolve(&self, rev: &str) -> Result<Sha> {
+        let sha = if is_sha(rev) {
+            rev.to_lowercase()
+        } else {
+            let candidates = [
+                rev.to_string(),
+                format!("refs/heads/{}", rev),
+                format!("refs/tags/{}", rev),
+            ];
+            candidates
+                .iter()
+                .filter_map(|x| self.read_ref(x, 0).transpose())
+                .next()
+                .unwrap_or_else(|| {
+                    Err(Error::from(ErrorKind::GitObjectNotFound(rev.to_string())))
+                })?
+        };
+
+        self.peel(sha)
+    }
+
+    /// Reads a commit including its signature, if any
+    ///
+    /// Local commits have not been verified by any source, so they do not have a source reason.
+    pub fn commit(&self, sha: &Sha) -> Result<SignedCommit> {
+        let sha = sha.to_string();
+        let (kind, data) = self.read_object(&sha)?;
+        if kind != ObjectKind::Commit {
+            return Err(Error::from(ErrorKind::InvalidGitRepository(format!(
+                "object {} is a {:?}, not a commit",
+                sha, kind
+            ))));
+        }
+
+        parse_commit(&sha, &data)
+    }
+
+    /// Reads an object either from its loose object file or from a pack
+    ///
+    /// The object's content is checked against its name, so objects cannot be swapped.
+    pub fn read_object(&self, sha: &str) -> Result<(ObjectKind, Vec<u8>)> {
+        self.read_object_at_depth(sha, 0)
+    }
+
+    /// `depth` is the number of deltas already being resolved
+    fn read_object_at_depth(&self, sha: &str, depth: usize) -> Result<(ObjectKind, Vec<u8>)> {
+        if !is_sha(sha) {
+            return Err(Error::from(ErrorKind::InvalidGitRepository(format!(
+                "'{}' is not a sha",
+                sha
+            ))));
+        }
+        let sha = sha.to_lowercase();
+
+        let loose = self.git_dir.join("objects").join(&sha[..2]).join(&sha[2..]);
+        let (kind, data) = if loose.is_file() {
+            trace!("Reading loose object {}", sha);
+            read_loose_object(&loose)?
+        } else {
+            let id = hex::decode(&sha)
+                .map_err(|e| e.context(ErrorKind::GitObjectNotFound(sha.clone())))?;
+            let pack = self
+                .packs
+                .iter()
+                .filter_map(|x| x.find(&id).map(|offset| (x, offset)))
+                .next();
+            match pack {
+                Some((pack, offset)) => {
+                    trace!("Reading object {} from {}", sha, pack.pack_path.display());
+                    pack.read_object(offset, self, depth)?
+                }
+                None => return Err(Error::from(ErrorKind::GitObjectNotFound(sha))),
+            }
+        };
+
+        let id = object_id(kind, &data);
+        if id != sha {
+            return Err(Error::from(ErrorKind::InvalidGitRepository(format!(
+                "object {} has the content of object {}",
+                sha, id
+            ))));
+        }
+
+        Ok((kind, data))
+    }
+
+    /// Reads a loose or packed ref; symbolic refs are followed
+    fn read_ref(&self, name: &str, depth: usize) -> Result<Option<String>> {
+        if depth > 5 {
+            return Err(Error::from(ErrorKind::InvalidGitRepository(format!(
+                "too many levels of symbolic refs at {}",
+                name
+            ))));
+        }
+
+        if let Ok(content) = fs::read_to_string(self.git_dir.join(name)) {
+            let content = content.trim();
+            if content.starts_with("ref: ") {
+                let target = &content[5..];
+                if !target.starts_with("refs/") || target.split('/').any(|x| x == "..") {
+                    return Err(Error::from(ErrorKind::InvalidGitRepository(format!(
+                        "symbolic ref {} points to invalid ref {}",
+                        name, target
+                    ))));
+                }
+                return self.read_ref(target, depth + 1);
+            }
+            if is_sha(content) {
+                return Ok(Some(content.to_string()));
+            }
+        }
+
+        let packed_refs = match fs::read_to_string(self.git_dir.join("packed-refs")) {
+            Ok(packed_refs) => packed_refs,
+            Err(_) => return Ok(None),
+        };
+        let sha = packed_refs
+            .lines()
+            .filter(|x| !x.starts_with('#') && !x.starts_with('^'))
+            .filter_map(|x| {
+                let mut fields = x.splitn(2, ' ');
+                match (fields.next(), fields.next()) {
+                    (Some(sha), Some(ref_name)) if ref_name == name && is_sha(sha) => {
+                        Some(sha.to_string())
+                    }
+                    _ => None,
+                }
+            })
+            .next();
+
+        Ok(sha)
+    }
+
+    /// Follows annotated tags to the commit they point to
+    fn peel(&self, mut sha: String) -> Result<Sha> {
+        loop {
+            let (kind, data) = self.read_object(&sha)?;
+            match kind {
+                ObjectKind::Commit => return Ok(Sha::new(sha)),
+                ObjectKind::Tag => {
+                    let object = headers(&data)
+                        .find(|(name, _)| name == "object")
+                        .map(|(_, value)| value)
+                        .filter(|x| is_sha(x))
+                        .ok_or_else(|| {
+                            Error::from(ErrorKind::InvalidGitRepository(format!(
+                                "tag {} has no valid object",
+                                sha
+                            )))
+                        })?;
+                    sha = object;
+                }
+                _ => {
+                    return Err(Error::from(ErrorKind::InvalidGitRepository(format!(
+                        "object {} is a {:?}, not a commit",
+                        sha, kind
+                    ))))
+                }
+            }
+        }
+    }
+}
+
+fn is_sha(s: &str) -> bool {
+    s.len() == 40 && s.chars().all(|x| x.is_ascii_hexdigit())
+}
+
+/// SHA-1 over the object's header and content, i.e., the name git stores the object with
+fn object_id(kind: ObjectKind, data: &[u8]) -> String {
+    let mut context = digest::Context::new(&digest::SHA1);
+    context.update(format!("{} {}\0", kind.name(), data.len()).as_bytes());
+    context.update(data);
+
+    hex::encode(context.finish())
+}
+
+fn read_loose_object(path: &Path) -> Result<(ObjectKind, Vec<u8>)> {
+    let file = File::open(path).map_err(|e| {
+        e.context(ErrorKind::InvalidGitRepository(format!(
+            "failed to open {}",
+            path.display()
+        )))
+    })?;
+    let mut data = Vec::new();
+    ZlibDecoder::new(file)
+        .read_to_end(&mut data)
+        .map_err(|e| e.context(invalid_object(path)))?;
+
+    let header_end = data
+        .iter()
+        .position(|x| *x == 0)
+        .ok_or_else(|| Error::from(invalid_object(path)))?;
+    let kind = data[..header_end]
+        .split(|x| *x == b' ')
+        .next()
+        .and_then(ObjectKind::from_name)
+        .ok_or_else(|| Error::from(invalid_object(path)))?;
+
+    Ok((kind, data.split_off(header_end + 1)))
+}
+
+fn invalid_object(path: &Path) -> ErrorKind {
+    ErrorKind::InvalidGitRepository(format!("object {} is malformed", path.display()))
+}
+
+/// A pack file together with its index (version 2)
+///
+/// See https://git-scm.com/docs/pack-format
+///
+/// The pack file is kept open while the repository is, so reading many objects and their delta
+/// bases does not open it again and again.
+#[derive(Debug)]
+struct Pack {
+    pack_path: PathBuf,
+    reader: RefCell<BufReader<File>>,
+    /// Sorted object ids
+    ids: Vec<[u8; 20]>,
+    offsets: Vec<u64>,
+}
+
+/// The base of a delta object in a pack
+enum DeltaBase {
+    /// Offset of the base in the same pack
+    Offset(u64),
+    /// Id of the base, which may be stored anywhere in the repository
+    Id([u8; 20]),
+}
+
+impl Pack {
+    fn open(idx_path: &Path) -> Result<Pack> {
+        let invalid = || {
+            ErrorKind::InvalidGitRepository(format!(
+                "pack index {} is malformed",
+                idx_path.display()
+            ))
+        };
+        let idx = fs::read(idx_path).map_err(|e| e.context(invalid()))?;
+        if idx.len() < 8 + 256 * 4 || &idx[..8] != b"\xfftOc\x00\x00\x00\x02" {
+            return Err(Error::from(ErrorKind::InvalidGitRepository(format!(
+                "pack index {} is not of version 2",
+                idx_path.display()
+            ))));
+        }
+
+        let u32_at = |pos: usize| -> Result<u32> {
+            idx.get(pos..pos + 4)
+                .map(|x| {
+                    u32::from(x[0]) << 24
+                        | u32::from(x[1]) << 16
+                        | u32::from(x[2]) << 8
+                        | u32::from(x[3])
+                })
+                .ok_or_else(|| Error::from(invalid()))
+        };
+        let count = u32_at(8 + 255 * 4)? as usize;
+        let ids_start = 8 + 256 * 4;
+        // Each object has an id, a CRC32, and an offset
+        let entries_end = count
+            .checked_mul(20 + 4 + 4)
+            .and_then(|x| x.checked_add(ids_start));
+        if entries_end.map(|x| x > idx.len()).unwrap_or(true) {
+            return Err(Error::from(ErrorKind::InvalidGitRepository(format!(
+                "pack index {} is truncated, {} objects do not fit",
+                idx_path.display(),
+                count
+            ))));
+        }
+        let offsets_start = ids_start + count * 20 + count * 4;
+        let large_offsets_start = offsets_start + count * 4;
+
+        let mut ids = Vec::with_capacity(count);
+        let mut offsets = Vec::with_capacity(count);
+        for i in 0..count {
+            let mut id = [0u8; 20];
+            let pos = ids_start + i * 20;
+            id.copy_from_slice(
+                idx.get(pos..pos + 20)
+                    .ok_or_else(|| Error::from(invalid()))?,
+            );
+            ids.push(id);
+
+            let offset = u32_at(offsets_start + i * 4)?;
+            let offset = if offset & 0x8000_0000 == 0 {
+                u64::from(offset)
+            } else {
+                let pos = large_offsets_start + (offset & 0x7fff_ffff) as usize * 8;
+                u64::from(u32_at(pos)?) << 32 | u64::from(u32_at(pos + 4)?)
+            };
+            offsets.push(offset);
+        }
+
+        let pack_path = idx_path.with_extension("pack");
+        let file = File::open(&pack_path).map_err(|e| {
+            e.context(ErrorKind::InvalidGitRepository(format!(
+                "failed to open pack {}",
+                pack_path.display()
+            )))
+        })?;
+
+        Ok(Pack {
+            pack_path,
+            reader: RefCell::new(BufReader::new(file)),
+            ids,
+            offsets,
+        })
+    }
+
+    fn find(&self, id: &[u8]) -> Option<u64> {
+        self.ids
+            .binary_search_by(|x| x[..].cmp(id))
+            .ok()
+            .map(|i| self.offsets[i])
+    }
+
+    /// `depth` is the number of deltas already being resolved
+    fn read_object(
+        &self,
+        offset: u64,
+        repository: &LocalRepository,
+        depth: usize,
+    ) -> Result<(ObjectKind, Vec<u8>)> {
+        let invalid = || {
+            ErrorKind::InvalidGitRepository(format!(
+                "pack {} is malformed at offset {}",
+                self.pack_path.display(),
+                offset
+            ))
+        };
+        if depth > MAX_DELTA_DEPTH {
+            return Err(Error::from(ErrorKind::InvalidGitRepository(format!(
+                "pack {} has a delta chain longer than {} at offset {}",
+                self.pack_path.display(),
+                MAX_DELTA_DEPTH,
+                offset
+            ))));
+        }
+        // The reader is released before resolving the base, which may be stored in this pack, too
+        let (pack_type, base, data) = {
+            let mut reader = self.reader.borrow_mut();
+            reader
+                .seek(SeekFrom::Start(offset))
+                .map_err(|e| e.context(invalid()))?;
+
+            let mut byte = read_byte(&mut *reader).map_err(|e| e.context(invalid()))?;
+            let pack_type = (byte >> 4) & 0x07;
+            let mut size = u64::from(byte & 0x0f);
+            let mut shift = 4;
+            while byte & 0x80 != 0 {
+                byte = read_byte(&mut *reader).map_err(|e| e.context(invalid()))?;
+                size |= u64::from(byte & 0x7f) << shift;
+                shift += 7;
+            }
+
+            let base = match pack_type {
+                PACK_OFS_DELTA => {
+                    byte = read_byte(&mut *reader).map_err(|e| e.context(invalid()))?;
+                    let mut distance = u64::from(byte & 0x7f);
+                    while byte & 0x80 != 0 {
+                        byte = read_byte(&mut *reader).map_err(|e| e.context(invalid()))?;
+                        distance = ((distance + 1) << 7) | u64::from(byte & 0x7f);
+                    }
+                    // The base always precedes the delta
+                    let base_offset = offset
+                        .checked_sub(distance)
+                        .filter(|_| distance > 0)
+                        .ok_or_else(|| Error::from(invalid()))?;
+                    Some(DeltaBase::Offset(base_offset))
+                }
+                PACK_REF_DELTA => {
+                    let mut id = [0u8; 20];
+                    reader
+                        .read_exact(&mut id)
+                        .map_err(|e| e.context(invalid()))?;
+                    Some(DeltaBase::Id(id))
+                }
+                _ => None,
+            };
+
+            let mut data = Vec::new();
+            ZlibDecoder::new(&mut *reader)
+                .take(size)
+                .read_to_end(&mut data)
+                .map_err(|e| e.context(invalid()))?;
+
+            (pack_type, base, data)
+        };
+
+        let base = match base {
+            Some(DeltaBase::Offset(base_offset)) => {
+                Some(self.read_object(base_offset, repository, depth + 1)?)
+            }
+            Some(DeltaBase::Id(id)) => {
+                Some(repository.read_object_at_depth(&hex::encode(id), depth + 1)?)
+            }
+            None => None,
+        };
+
+        match base {
+            Some((kind, base)) => {
+                let data = apply_delta(&base, &data).ok_or_else(|| Error::from(invalid()))?;
+                Ok((kind, data))
+            }
+            None => {
+                let kind =
+                    ObjectKind::from_pack_type(pack_type).ok_or_else(|| Error::from(invalid()))?;
+                Ok((kind, data))
+            }
+        }
+    }
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> ::std::io::Result<u8> {
+    let mut byte = [0u8; 1];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Applies a git delta to `base`; `None` if the delta is malformed
+fn apply_delta(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    let mut pos = 0;
+    let read_size = |pos: &mut usize| -> Option<usize> {
+        let mut size = 0;
+        let mut shift = 0;
+        loop {
+            let byte = *delta.get(*pos)?;
+            *pos += 1;
+            size |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(size);
+            }
+        }
+    };
+    let base_size = read_size(&mut pos)?;
+    let result_size = read_size(&mut pos)?;
+    if base_size != base.len() || result_size > MAX_OBJECT_SIZE {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(result_size);
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        if op & 0x80 != 0 {
+            // Copy from base; the set bits tell which offset and size bytes are present
+            let mut offset = 0usize;
+            let mut size = 0usize;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    offset |= (*delta.get(pos)? as usize) << (i * 8);
+                    pos += 1;
+                }
+            }
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 {
+                    size |= (*delta.get(pos)? as usize) << (i * 8);
+                    pos += 1;
+                }
+            }
+            if size == 0 {
+                size = 0x10000;
+            }
+            result.extend_from_slice(base.get(offset..offset + size)?);
+        } else if op != 0 {
+            // Insert the next `op` bytes
+            let size = op as usize;
+            result.extend_from_slice(delta.get(pos..pos + size)?);
+            pos += size;
+        } else {
+            return None;
+        }
+    }
+
+    if result.len() == result_size {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+/// Headers of a commit or tag object; continuation lines are joined with new lines
+fn headers(data: &[u8]) -> impl Iterator<Item = (String, String)> {
+    let text = String::from_utf8_lossy(data);
+    let header_block = text.split("\n\n").next().unwrap_or("").to_string();
+
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in header_block.lines() {
+        if line.starts_with(' ') {
+            if let Some(last) = headers.last_mut() {
+                last.1.push('\n');
+                last.1.push_str(&line[1..]);
+            }
+        } else {
+            let mut parts = line.splitn(2, ' ');
+            let name = parts.next().unwrap_or("").to_string();
+            let value = parts.next().unwrap_or("").to_string();
+            headers.push((name, value));
+        }
+    }
+
+    headers.into_iter()
+}
+
+/// Removes the `gpgsig` header including its continuation lines; the rest is the signed payload
+fn strip_signature(data: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(data.len());
+    let mut in_headers = true;
+    let mut in_signature = false;
+    for line in data.split(|x| *x == b'\n') {
+        if in_headers {
+            if line.is_empty() {
+                in_headers = false;
+            } else if line.starts_with(b"gpgsig ") {
+                in_signature = true;
+                continue;
+            } else if in_signature && line.starts_with(b" ") {
+                continue;
+            } else {
+                in_signature = false;
+            }
+        }
+        payload.extend_from_slice(line);
+        payload.push(b'\n');
+    }
+    // `split` yields an empty last element for the trailing new line, which has been added again
+    payload.pop();
+
+    payload
+}
+
+fn parse_commit(sha: &str, data: &[u8]) -> Result<SignedCommit> {
+    let invalid = |reason: &str| {
+        Error::from(ErrorKind::InvalidGitRepository(format!(
+            "commit {} is malformed, {}",
+            sha, reason
+        )))
+    };
+
+    let mut author = None;
+    let mut committer = None;
+    let mut parents = Vec::new();
+    let mut signature = None;
+    for (name, value) in headers(data) {
+        match name.as_ref() {
+            "author" => author = Some(parse_person(&value).ok_or_else(|| invalid("author"))?),
+            "committer" => {
+                committer = Some(parse_person(&value).ok_or_else(|| invalid("committer"))?)
+            }
+            "parent" if is_sha(&value) => parents.push(Sha::new(value)),
+            "parent" => return Err(invalid("parent")),
+            "gpgsig" => signature = Some(value + "\n"),
+            _ => {}
+        }
+    }
+
+    let text = String::from_utf8_lossy(data);
+    let message = match text.find("\n\n") {
+        Some(pos) => text[pos + 2..].to_string(),
+        None => String::new(),
+    };
+    let payload = signature
+        .as_ref()
+        .map(|_| String::from_utf8_lossy(&strip_signature(data)).to_string());
+
+    Ok(SignedCommit {
+        sha: Sha::new(sha),
+        parents,
+        author: author.ok_or_else(|| invalid("no author"))?,
+        committer: committer.ok_or_else(|| invalid("no committer"))?,
+        message,
+        signature,
+        payload,
+        source_reason: None,
+    })
+}
+
+/// Parses `Name <e-mail> timestamp timezone`
+fn parse_person(value: &str) -> Option<Person> {
+    let email_start = value.find('<')?;
+    let email_end = value.rfind('>')?;
+    let name = value[..email_start].trim().to_string();
+    let email = value.get(email_start + 1..email_end)?.to_string();
+
+    let mut time = value[email_end + 1..].split_whitespace();
+    let timestamp: i64 = time.next()?.parse().ok()?;
+    let timezone = time.next()?;
+    let sign = if timezone.starts_with('-') { -1 } else { 1 };
+    let hours: i32 = timezone.get(1..3)?.parse().ok()?;
+    let minutes: i32 = timezone.get(3..5)?.parse().ok()?;
+    let offset = FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))?;
+    let date: DateTime<FixedOffset> = offset.timestamp_opt(timestamp, 0).single()?;
+
+    Some(Person { name, email, date })
+}
+
+impl CommitSource for LocalRepository {
+    /// Commits reachable from `to`, or `HEAD` if not set, which are not ancestors of `from`
+    fn commits(&self, from: Option<&Sha>, to: Option<&Sha>) -> Result<Vec<SignedCommit>> {
+        let head = match to {
+            Some(to) => to.clone(),
+            None => self.resolve("HEAD")?,
+        };
+
+        let mut excluded = HashSet::new();
+        if let Some(from) = from {
+            let mut queue: VecDeque<Sha> = self.commit(from)?.parents.into_iter().collect();
+            while let Some(sha) = queue.pop_front() {
+                if excluded.insert(sha.clone()) {
+                    queue.extend(self.commit(&sha)?.parents);
+                }
+            }
+        }
+
+        let mut commits = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(head);
+        while let Some(sha) = queue.pop_front() {
+            if excluded.contains(&sha) || !seen.insert(sha.clone()) {
+                continue;
+            }
+            let commit = self.commit(&sha)?;
+            queue.extend(commit.parents.iter().cloned());
+            commits.push(commit);
+        }
+
+        Ok(commits)
+    }
+
+    fn commit(&self, sha: &Sha) -> Result<SignedCommit> {
+        LocalRepository::commit(self, sha)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gpg::CommitVerifier, utils::test};
+
+    use spectral::prelude::*;
+    use std::env;
+
+    static REPOSITORY: &str = "tests/git-repo.git";
+
+    fn temp_git_dir(name: &str) -> PathBuf {
+        let git_dir = env::temp_dir().join(format!("github-watchtower-{}-test.git", name));
+        let _ = fs::remove_dir_all(&git_dir);
+        fs::create_dir_all(git_dir.join("objects")).expect("failed to create git dir");
+        git_dir
+    }
+
+    #[test]
+    fn resolve_refs() {
+        test::init();
+
+        let repository = LocalRepository::open(REPOSITORY).expect("failed to open repository");
+
+        asserting("HEAD is resolved via packed refs")
+            .that(&repository.resolve("HEAD"))
+            .is_ok()
+            .is_equal_to(&Sha::new("fe40012fee27cefed4c03ca8612af0e75c4e5f5c"));
+        asserting("tag is resolved")
+            .that(&repository.resolve("v0.1"))
+            .is_ok()
+            .is_equal_to(&Sha::new("aa0c6605b79294063bbbc057dc5f04cb634fe501"));
+        asserting("loose branch is resolved")
+            .that(&repository.resolve("unsigned"))
+            .is_ok()
+            .is_equal_to(&Sha::new("fe40012fee27cefed4c03ca8612af0e75c4e5f5c"));
+        asserting("unknown ref is not resolved")
+            .that(&repository.resolve("no-such-branch"))
+            .is_err();
+    }
+
+    #[test]
+    fn read_deltified_object_from_pack() {
+        test::init();
+
+        let repository = LocalRepository::open(REPOSITORY).expect("failed to open repository");
+        let expected: String = (1..=20).map(|x| format!("line {}\n", x)).collect();
+
+        let object = repository.read_object("c4352f8b46de5cdb88d0cc96958316db42dd2398");
+
+        asserting("deltified blob is read")
+            .that(&object)
+            .is_ok()
+            .is_equal_to(&(ObjectKind::Blob, expected.into_bytes()));
+    }
+
+    #[test]
+    fn swapped_object_failed() {
+        test::init();
+
+        let git_dir = temp_git_dir("swapped-object");
+        fs::create_dir_all(git_dir.join("objects/fe")).expect("failed to create object dir");
+        fs::copy(
+            Path::new(REPOSITORY).join("objects/1a/c75103710eeaaa59e71291968d01aece03cc7b"),
+            git_dir.join("objects/fe/40012fee27cefed4c03ca8612af0e75c4e5f5c"),
+        )
+        .expect("failed to copy object");
+        let repository = LocalRepository::open(&git_dir).expect("failed to open repository");
+
+        let commit = repository.commit(&Sha::new("fe40012fee27cefed4c03ca8612af0e75c4e5f5c"));
+        let _ = fs::remove_dir_all(&git_dir);
+
+        asserting("signed commit stored under another sha is rejected")
+            .that(&commit)
+            .is_err()
+            .map(|x| x.kind())
+            .is_equal_to(&ErrorKind::InvalidGitRepository(
+                "object fe40012fee27cefed4c03ca8612af0e75c4e5f5c has the content of object \
+                 1ac75103710eeaaa59e71291968d01aece03cc7b"
+                    .to_string(),
+            ));
+    }
+
+    #[test]
+    fn invalid_sha_failed() {
+        test::init();
+
+        let repository = LocalRepository::open(REPOSITORY).expect("failed to open repository");
+        let commit = parse_commit(
+            "1ac75103710eeaaa59e71291968d01aece03cc7b",
+            "parent ../../../../../../../../etc/passwd\n\
+             author A <a@example.com> 1561456980 +0200\n\
+             committer A <a@example.com> 1561456980 +0200\n\nTest\n"
+                .as_bytes(),
+        );
+
+        asserting("path is not read as object")
+            .that(&repository.read_object("../../../../../../../../../etc/passwd"))
+            .is_err();
+        asserting("multi-byte sha is rejected")
+            .that(&repository.read_object("\u{fc}c75103710eeaaa59e71291968d01aece03cc7"))
+            .is_err();
+        asserting("invalid parent is rejected")
+            .that(&commit)
+            .is_err();
+    }
+
+    #[test]
+    fn delta_loops_failed() {
+        test::init();
+
+        let git_dir = temp_git_dir("delta-loops");
+        let pack_path = git_dir.join("objects/loop.pack");
+        let id = [0x11u8; 20];
+        // Header, an OFS_DELTA at offset 12 based on itself, and a REF_DELTA at offset 14 based
+        // on itself
+        let mut pack = b"PACK\x00\x00\x00\x02\x00\x00\x00\x02\x60\x00\x70".to_vec();
+        pack.extend_from_slice(&id);
+        fs::write(&pack_path, pack).expect("failed to write pack");
+        let ofs_delta = LocalRepository {
+            git_dir: git_dir.clone(),
+            packs: vec![Pack {
+                pack_path: pack_path.clone(),
+                reader: RefCell::new(BufReader::new(
+                    File::open(&pack_path).expect("failed to open pack"),
+                )),
+                ids: vec![id],
+                offsets: vec![12],
+            }],
+        };
+        let ref_delta = LocalRepository {
+            git_dir: git_dir.clone(),
+            packs: vec![Pack {
+                reader: RefCell::new(BufReader::new(
+                    File::open(&pack_path).expect("failed to open pack"),
+                )),
+                pack_path,
+                ids: vec![id],
+                offsets: vec![14],
+            }],
+        };
+
+        let ofs_object = ofs_delta.read_object(&hex::encode(id));
+        let ref_object = ref_delta.read_object(&hex::encode(id));
+        let _ = fs::remove_dir_all(&git_dir);
+
+        asserting("zero offset is rejected")
+            .that(&ofs_object)
+            .is_err();
+        asserting("delta chain is limited")
+            .that(&ref_object)
+            .is_err()
+            .map(|x| x.kind())
+            .matches(|x| match x {
+                ErrorKind::InvalidGitRepository(reason) => reason.contains("delta chain"),
+                _ => false,
+            });
+    }
+
+    #[test]
+    fn oversized_pack_entries_failed() {
+        test::init();
+
+        let git_dir = temp_git_dir("oversized");
+        let idx_path = git_dir.join("objects/oversized.idx");
+        // Header and a fan-out table claiming 2^32 - 1 objects without any entries
+        let mut idx = b"\xfftOc\x00\x00\x00\x02".to_vec();
+        idx.extend_from_slice(&[0xff; 256 * 4]);
+        fs::write(&idx_path, idx).expect("failed to write pack index");
+
+        let pack = Pack::open(&idx_path);
+        let _ = fs::remove_dir_all(&git_dir);
+        // Delta of an empty base claiming a result of 2^32 - 1 bytes
+        let delta = apply_delta(b"", b"\x00\xff\xff\xff\xff\x0f");
+
+        asserting("object count is checked against the index size")
+            .that(&pack)
+            .is_err()
+            .map(|x| x.kind())
+            .matches(|x| match x {
+                ErrorKind::InvalidGitRepository(reason) => reason.contains("truncated"),
+                _ => false,
+            });
+        asserting("delta result size is limited")
+            .that(&delta)
+            .is_none();
+    }
+
+    #[test]
+    fn read_commits_and_verify() {
+        test::init();
+
+        let repository = LocalRepository::open(REPOSITORY).expect("failed to open repository");
+        let verifier =
+            CommitVerifier::from_key_file("tests/second.pub").expect("failed to load public key");
+        let from = repository.resolve("v0.1").expect("failed to resolve tag");
+
+        let commits = repository.commits(Some(&from), None);
+
+        asserting("commits from loose objects and pack are read")
+            .that(&commits)
+            .is_ok()
+            .has_length(3);
+        let commits = commits.unwrap();
+        let results = verifier.verify_signed_commits(&commits, Some(&repository));
+        asserting("unsigned loose commit is not verified")
+            .that(&results[0])
+            .is_err();
+        asserting("signed loose commit is verified")
+            .that(&results[1])
+            .is_ok();
+        asserting("signed packed commit is verified")
+            .that(&results[2])
+            .is_ok();
+        asserting("author is parsed")
+            .that(&commits[1].author.date.to_rfc3339().as_str())
+            .is_equal_to("2019-06-25T12:03:00+02:00");
+    }
+}
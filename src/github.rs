@@ -3,15 +3,25 @@ use crate::errors::*;
 use reqwest;
 
 pub mod commits;
+mod contents;
 pub mod endpoints;
+pub mod graphql;
 mod link;
+pub mod tags;
+pub mod users;
+pub mod webhook;
 
 pub use commits::Commit;
 pub use endpoints::Endpoints;
+pub use graphql::GraphQlClient;
+pub use tags::Tag;
+pub use users::GpgKey;
 
-use commits::Params;
+use commits::{Params, Sha};
+use tags::TagRef;
 
 static GITHUB_ACCEPT_HEADER: &[u8] = b"Accept: application/vnd.github.v3+json";
+static GITHUB_API_URL: &str = "https://api.github.com";
 static GITHUB_LINK_HEADER_NAME: &str = "Link";
 
 #[derive(Debug)]
@@ -22,6 +32,7 @@ impl Client {
         AuthorizedClient {
             oauth_token,
             http: reqwest::Client::new(),
+            base_url: GITHUB_API_URL.to_string(),
         }
     }
 }
@@ -30,6 +41,25 @@ impl Client {
 pub struct AuthorizedClient<'a> {
     oauth_token: &'a OAuthToken,
     http: reqwest::Client,
+    base_url: String,
+}
+
+impl<'a> AuthorizedClient<'a> {
+    /// Uses another API than `https://api.github.com`, e.g., `https://github.example.com/api/v3`
+    /// of GitHub Enterprise
+    pub fn with_base_url<T: Into<String>>(self, base_url: T) -> Self {
+        let base_url = base_url.into().trim_end_matches('/').to_string();
+        AuthorizedClient { base_url, ..self }
+    }
+
+    /// URL of the GraphQL API, which GitHub Enterprise serves at `/api/graphql`
+    fn graphql_url(&self) -> String {
+        if self.base_url.ends_with("/api/v3") {
+            format!("{}/graphql", self.base_url.trim_end_matches("/v3"))
+        } else {
+            format!("{}/graphql", self.base_url)
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -53,7 +83,22 @@ pub trait GitHub {
         repository: &Repository,
         params: T,
     ) -> Result<Vec<Commit>>;
+    fn commit(&self, repository: &Repository, sha: &Sha) -> Result<Commit>;
+    /// Commits reachable from `head` but not from `base`
+    fn compare(&self, repository: &Repository, base: &Sha, head: &Sha) -> Result<Vec<Commit>>;
     fn endpoints(&self) -> Result<Endpoints>;
+    /// Content of the file at `path` at `reference`, or at the default branch if `None`; `None`
+    /// if the file does not exist
+    fn file_content(
+        &self,
+        repository: &Repository,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Option<String>>;
+    fn tags(&self, repository: &Repository) -> Result<Vec<TagRef>>;
+    /// Annotated tag identified by the sha of its tag object
+    fn tag(&self, repository: &Repository, sha: &Sha) -> Result<Tag>;
+    fn user_gpg_keys(&self, login: &str) -> Result<Vec<GpgKey>>;
 }
 
 impl<'a> GitHub for AuthorizedClient<'a> {
@@ -65,7 +110,36 @@ impl<'a> GitHub for AuthorizedClient<'a> {
         commits::commits(self, repository, params)
     }
 
+    fn commit(&self, repository: &Repository, sha: &Sha) -> Result<Commit> {
+        commits::commit(self, repository, sha)
+    }
+
+    fn compare(&self, repository: &Repository, base: &Sha, head: &Sha) -> Result<Vec<Commit>> {
+        commits::compare(self, repository, base, head)
+    }
+
     fn endpoints(&self) -> Result<Endpoints> {
         endpoints::endpoints(self)
     }
+
+    fn file_content(
+        &self,
+        repository: &Repository,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Option<String>> {
+        contents::file_content(self, repository, path, reference)
+    }
+
+    fn tags(&self, repository: &Repository) -> Result<Vec<TagRef>> {
+        tags::tags(self, repository)
+    }
+
+    fn tag(&self, repository: &Repository, sha: &Sha) -> Result<Tag> {
+        tags::tag(self, repository, sha)
+    }
+
+    fn user_gpg_keys(&self, login: &str) -> Result<Vec<GpgKey>> {
+        users::user_gpg_keys(self, login)
+    }
 }
@@ -12,9 +12,9 @@ use failure::Fail;
 use log::{debug, trace};
 use reqwest::{self, header, Response, StatusCode};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, convert::TryFrom};
+use std::{collections::HashMap, convert::TryFrom, fmt};
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub struct Sha(String);
 
 impl Sha {
@@ -23,10 +23,23 @@ impl Sha {
     }
 }
 
+impl fmt::Display for Sha {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Commit {
     pub sha: Sha,
     pub commit: CommitDetail,
+    #[serde(default)]
+    pub parents: Vec<Parent>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Parent {
+    pub sha: Sha,
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,7 +57,7 @@ pub struct PersonDetails {
     pub date: DateTime<FixedOffset>,
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Reason {
     ExpiredKey,
@@ -76,12 +89,12 @@ pub struct Verification {
 /// Attention: from means "all commits until this"; cf. GitHub documentation, Parameters, "sha"
 #[derive(Debug, Default, Serialize)]
 pub struct Params {
-    from: Option<Sha>,
-    to: Option<Sha>,
-    path: Option<String>,
-    author: Option<String>,
-    since: Option<DateTime<FixedOffset>>,
-    until: Option<DateTime<FixedOffset>>,
+    pub(super) from: Option<Sha>,
+    pub(super) to: Option<Sha>,
+    pub(super) path: Option<String>,
+    pub(super) author: Option<String>,
+    pub(super) since: Option<DateTime<FixedOffset>>,
+    pub(super) until: Option<DateTime<FixedOffset>>,
 }
 
 impl Params {
@@ -172,7 +185,7 @@ pub(crate) fn commits<T: Into<Option<Params>>>(
 }
 
 /// Filter commits if `to` is set else just return passed commits
-fn filter_to(commits: Vec<Commit>, params_opt: Option<Params>) -> Vec<Commit> {
+pub(super) fn filter_to(commits: Vec<Commit>, params_opt: Option<Params>) -> Vec<Commit> {
     if let Some(p) = params_opt {
         if let Some(to) = p.to {
             let mut v = Vec::new();
@@ -199,7 +212,8 @@ fn do_commits(
     let OAuthToken(ref token) = client.oauth_token;
 
     let url = format!(
-        "https://api.github.com/repos/{owner}/{repository}/commits",
+        "{base_url}/repos/{owner}/{repository}/commits",
+        base_url = client.base_url,
         owner = repository.owner,
         repository = repository.name
     );
@@ -227,6 +241,106 @@ fn do_commits(
     Ok(commits)
 }
 
+/// Get a single commit
+pub(crate) fn commit(
+    client: &AuthorizedClient,
+    repository: &Repository,
+    sha: &Sha,
+) -> Result<Commit> {
+    let OAuthToken(ref token) = client.oauth_token;
+
+    let url = format!(
+        "{base_url}/repos/{owner}/{repository}/commits/{sha}",
+        base_url = client.base_url,
+        owner = repository.owner,
+        repository = repository.name,
+        sha = sha,
+    );
+
+    let mut response = get_commits(&client, &url, None, &token)?;
+    let commit = response.json().map_err(|e| {
+        e.context(ErrorKind::FailedToProcessHttpResponse(
+            response.status(),
+            "reading body".to_string(),
+        ))
+    })?;
+
+    Ok(commit)
+}
+
+/// Comparison of two commits as returned by GitHub
+///
+/// See https://developer.github.com/v3/repos/commits/#compare-two-commits
+#[derive(Debug, Deserialize)]
+struct Comparison {
+    /// Number of commits between base and head; may exceed the number of returned commits
+    total_commits: usize,
+    commits: Vec<Commit>,
+}
+
+/// Get the commits reachable from `head` but not from `base`, oldest first
+///
+/// GitHub returns at most 250 commits for a comparison. Larger comparisons fail instead of
+/// returning a truncated range.
+pub(crate) fn compare(
+    client: &AuthorizedClient,
+    repository: &Repository,
+    base: &Sha,
+    head: &Sha,
+) -> Result<Vec<Commit>> {
+    let OAuthToken(ref token) = client.oauth_token;
+
+    let url = format!(
+        "{base_url}/repos/{owner}/{repository}/compare/{base}...{head}",
+        base_url = client.base_url,
+        owner = repository.owner,
+        repository = repository.name,
+        base = base,
+        head = head,
+    );
+
+    let mut commits: Vec<Commit> = Vec::new();
+    let mut total_commits = 0;
+
+    let mut response = get_commits(&client, &url, None, &token)?;
+    loop {
+        let result: Comparison = response.json().map_err(|e| {
+            e.context(ErrorKind::FailedToProcessHttpResponse(
+                response.status(),
+                "reading body".to_string(),
+            ))
+        })?;
+        total_commits = total_commits.max(result.total_commits);
+        commits.extend(result.commits);
+
+        if let Some(next_link) = next_link(&response)? {
+            trace!("Following next header: '{}'", next_link);
+            response = get_commits(&client, next_link, None, &token)?;
+        } else {
+            break;
+        }
+    }
+
+    check_complete(total_commits, commits)
+        .map_err(|e| Error::from(ErrorKind::FailedToProcessHttpResponse(response.status(), e)))
+}
+
+/// Fails if fewer than `total_commits` commits have been received
+fn check_complete(
+    total_commits: usize,
+    commits: Vec<Commit>,
+) -> ::std::result::Result<Vec<Commit>, String> {
+    if commits.len() < total_commits {
+        return Err(format!(
+            "comparison is truncated, received {} of {} commits",
+            commits.len(),
+            total_commits
+        ));
+    }
+
+    Ok(commits)
+}
+
 fn get_commits(
     client: &AuthorizedClient,
     url: &str,
@@ -250,7 +364,7 @@ fn get_commits(
     Ok(response)
 }
 
-fn next_link(response: &Response) -> Result<Option<&str>> {
+pub(super) fn next_link(response: &Response) -> Result<Option<&str>> {
     if let Some(link_header_value) = response.headers().get(GITHUB_LINK_HEADER_NAME) {
         let value_str = link_header_value.to_str().map_err(|e| {
             e.context(ErrorKind::FailedToProcessHttpResponse(
@@ -366,6 +480,50 @@ mod tests {
         assert_that(&endpoints).is_ok().has_length(1);
     }
 
+    #[test]
+    fn truncated_comparison_failed() {
+        test::init();
+
+        let comparison_json = r#"
+            {
+                "total_commits": 251,
+                "commits": [
+                    {
+                        "sha": "6dcb09b5b57875f334f61aebed695e2e4193db5e",
+                        "commit": {
+                            "author": {
+                                "name": "Monalisa Octocat",
+                                "email": "support@github.com",
+                                "date": "2011-04-14T16:00:49Z"
+                            },
+                            "committer": {
+                                "name": "Monalisa Octocat",
+                                "email": "support@github.com",
+                                "date": "2011-04-14T16:00:49Z"
+                            },
+                            "message": "Fix all the bugs",
+                            "verification": {
+                                "verified": false,
+                                "reason": "unsigned",
+                                "signature": null,
+                                "payload": null
+                            }
+                        }
+                    }
+                ]
+            }
+        "#;
+
+        let comparison: Comparison =
+            serde_json::from_str(comparison_json).expect("failed to deserialize comparison");
+        let res = check_complete(comparison.total_commits, comparison.commits);
+
+        asserting("truncated comparison is rejected")
+            .that(&res)
+            .is_err()
+            .is_equal_to(&"comparison is truncated, received 1 of 251 commits".to_string());
+    }
+
     #[test]
     fn deserialize_commits_with_verification_object() {
         test::init();
@@ -0,0 +1,139 @@
+use crate::{
+    errors::*,
+    github::{AuthorizedClient, OAuthToken, Repository, GITHUB_ACCEPT_HEADER},
+    utils::http::GeneralErrHandler,
+};
+
+use base64;
+use failure::Fail;
+use log::debug;
+use reqwest::{self, header, Response, StatusCode};
+use serde::Deserialize;
+
+/// File as returned by the Contents API
+///
+/// See https://developer.github.com/v3/repos/contents/#get-contents
+#[derive(Debug, Deserialize)]
+struct FileContent {
+    #[serde(rename = "type")]
+    content_type: String,
+    encoding: String,
+    content: String,
+}
+
+impl FileContent {
+    fn decode(&self) -> Result<String> {
+        if self.content_type != "file" || self.encoding != "base64" {
+            return Err(Error::from(ErrorKind::FailedToProcessHttpResponse(
+                StatusCode::OK,
+                format!(
+                    "expected base64 encoded file, but got {} encoded {}",
+                    self.encoding, self.content_type
+                ),
+            )));
+        }
+
+        // GitHub breaks the encoded content into lines
+        let encoded: String = self.content.split_whitespace().collect();
+        let bytes = base64::decode(&encoded).map_err(|e| {
+            e.context(ErrorKind::FailedToProcessHttpResponse(
+                StatusCode::OK,
+                "decoding content".to_string(),
+            ))
+        })?;
+
+        String::from_utf8(bytes).map_err(|e| {
+            e.context(ErrorKind::FailedToProcessHttpResponse(
+                StatusCode::OK,
+                "content is not UTF-8".to_string(),
+            ))
+            .into()
+        })
+    }
+}
+
+/// Get the content of a file at `reference` -- a sha, branch, or tag -- or at the default branch
+///
+/// Returns `None` if the file does not exist.
+pub(crate) fn file_content(
+    client: &AuthorizedClient,
+    repository: &Repository,
+    path: &str,
+    reference: Option<&str>,
+) -> Result<Option<String>> {
+    let OAuthToken(ref token) = client.oauth_token;
+
+    let url = format!(
+        "{base_url}/repos/{owner}/{repository}/contents/{path}",
+        base_url = client.base_url,
+        owner = repository.owner,
+        repository = repository.name,
+        path = path.trim_start_matches('/'),
+    );
+
+    let mut request = client
+        .http
+        .get(&url)
+        .header(header::ACCEPT, GITHUB_ACCEPT_HEADER)
+        .bearer_auth(token);
+    if let Some(reference) = reference {
+        request = request.query(&[("ref", reference)]);
+    }
+    debug!("Request: '{:#?}'", request);
+
+    let response: Response = request
+        .send()
+        .map_err(|e| e.context(ErrorKind::HttpRequestFailed))?;
+    if response.status() == StatusCode::NOT_FOUND {
+        debug!("File '{}' does not exist", path);
+        return Ok(None);
+    }
+    let mut response = response.general_err_handler(StatusCode::OK)?;
+    debug!("Response: '{:#?}'", response);
+
+    let result: FileContent = response.json().map_err(|e| {
+        e.context(ErrorKind::FailedToProcessHttpResponse(
+            response.status(),
+            "reading body".to_string(),
+        ))
+    })?;
+
+    result.decode().map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test;
+
+    use serde_json;
+    use spectral::prelude::*;
+
+    #[test]
+    fn deserialize_and_decode_file_content() {
+        test::init();
+
+        let content_json = r#"
+            {
+                "type": "file",
+                "encoding": "base64",
+                "size": 32,
+                "name": "watchtower.toml",
+                "path": ".github/watchtower.toml",
+                "content": "W3BvbGljeV0Kc2lnbmF0dXJlX21vZGUgPSAi\nYWxsIgo=\n",
+                "sha": "3d21ec53a331a6f037a91c368710b99387d012c1",
+                "url": "https://api.github.com/repos/octocat/Hello-World/contents/.github/watchtower.toml"
+            }
+        "#;
+
+        let content: ::std::result::Result<FileContent, _> = serde_json::from_str(content_json);
+
+        asserting("file content is deserialized")
+            .that(&content)
+            .is_ok();
+        asserting("file content is decoded")
+            .that(&content.unwrap().decode())
+            .is_ok()
+            .is_equal_to("[policy]\nsignature_mode = \"all\"\n".to_string());
+    }
+}
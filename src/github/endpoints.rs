@@ -15,7 +15,7 @@ pub(crate) fn endpoints(client: &AuthorizedClient) -> Result<Endpoints> {
     let OAuthToken(ref token) = client.oauth_token;
     let request = client
         .http
-        .get("https://api.github.com/")
+        .get(&format!("{}/", client.base_url))
         .header(header::ACCEPT, GITHUB_ACCEPT_HEADER)
         .bearer_auth(token);
     debug!("Request: '{:#?}'", request);
@@ -0,0 +1,524 @@
+//! Commits retrieved via GitHub's GraphQL API
+//!
+//! The REST API returns 30 commits per page. The `history` connection returns 100 commits
+//! together with their signatures per request, so long histories take far fewer requests.
+
+use crate::{
+    errors::*,
+    github::{
+        commits::{
+            filter_to, Commit, CommitDetail, Params, Parent, PersonDetails, Reason, Sha,
+            Verification,
+        },
+        contents, endpoints,
+        tags::{self, TagRef},
+        users, AuthorizedClient, Endpoints, GitHub, GpgKey, OAuthToken, Repository, Tag,
+    },
+    utils::http::GeneralErrHandler,
+};
+
+use chrono::{DateTime, FixedOffset};
+use failure::Fail;
+use log::{debug, trace};
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json, Value};
+
+/// Maximum page size of GraphQL connections
+const PAGE_SIZE: usize = 100;
+
+static HISTORY_QUERY: &str = r#"
+query($owner: String!, $name: String!, $expression: String!, $first: Int!, $after: String,
+      $path: String, $author: CommitAuthor, $since: GitTimestamp, $until: GitTimestamp) {
+  repository(owner: $owner, name: $name) {
+    object(expression: $expression) {
+      ... on Commit {
+        history(first: $first, after: $after, path: $path, author: $author, since: $since,
+                until: $until) {
+          pageInfo { hasNextPage endCursor }
+          nodes {
+            oid
+            message
+            author { name email date }
+            committer { name email date }
+            parents(first: 100) { nodes { oid } }
+            signature {
+              isValid
+              state
+              wasSignedByGitHub
+              signer { login }
+              signature
+              payload
+            }
+          }
+        }
+      }
+    }
+  }
+}
+"#;
+
+/// GitHub client retrieving commits via GraphQL; all other calls use the REST API
+#[derive(Debug)]
+pub struct GraphQlClient<'a> {
+    client: &'a AuthorizedClient<'a>,
+}
+
+impl<'a> AuthorizedClient<'a> {
+    pub fn graphql(&'a self) -> GraphQlClient<'a> {
+        GraphQlClient { client: self }
+    }
+}
+
+impl<'a> GitHub for GraphQlClient<'a> {
+    fn commits<T: Into<Option<Params>>>(
+        &self,
+        repository: &Repository,
+        params: T,
+    ) -> Result<Vec<Commit>> {
+        commits(self.client, repository, params)
+    }
+
+    fn commit(&self, repository: &Repository, sha: &Sha) -> Result<Commit> {
+        self.client.commit(repository, sha)
+    }
+
+    fn compare(&self, repository: &Repository, base: &Sha, head: &Sha) -> Result<Vec<Commit>> {
+        self.client.compare(repository, base, head)
+    }
+
+    fn endpoints(&self) -> Result<Endpoints> {
+        endpoints::endpoints(self.client)
+    }
+
+    fn file_content(
+        &self,
+        repository: &Repository,
+        path: &str,
+        reference: Option<&str>,
+    ) -> Result<Option<String>> {
+        contents::file_content(self.client, repository, path, reference)
+    }
+
+    fn tags(&self, repository: &Repository) -> Result<Vec<TagRef>> {
+        tags::tags(self.client, repository)
+    }
+
+    fn tag(&self, repository: &Repository, sha: &Sha) -> Result<Tag> {
+        tags::tag(self.client, repository, sha)
+    }
+
+    fn user_gpg_keys(&self, login: &str) -> Result<Vec<GpgKey>> {
+        users::user_gpg_keys(self.client, login)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Request<'a> {
+    query: &'a str,
+    variables: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlResponse {
+    data: Option<Value>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQlError {
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct History {
+    page_info: PageInfo,
+    nodes: Vec<HistoryCommit>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryCommit {
+    oid: String,
+    message: String,
+    author: GitActor,
+    committer: GitActor,
+    parents: Parents,
+    signature: Option<GitSignature>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitActor {
+    name: String,
+    email: String,
+    date: DateTime<FixedOffset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Parents {
+    nodes: Vec<ParentNode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ParentNode {
+    oid: String,
+}
+
+/// See https://developer.github.com/v4/object/gpgsignature/
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GitSignature {
+    is_valid: bool,
+    state: String,
+    was_signed_by_git_hub: bool,
+    signer: Option<Signer>,
+    signature: String,
+    payload: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Signer {
+    login: String,
+}
+
+impl From<GitActor> for PersonDetails {
+    fn from(actor: GitActor) -> PersonDetails {
+        PersonDetails {
+            name: actor.name,
+            email: actor.email,
+            date: actor.date,
+        }
+    }
+}
+
+impl From<HistoryCommit> for Commit {
+    fn from(commit: HistoryCommit) -> Commit {
+        let verification = match commit.signature {
+            Some(signature) => {
+                trace!(
+                    "Commit {} signed by {:?}, by GitHub: {}",
+                    commit.oid,
+                    signature.signer.map(|x| x.login),
+                    signature.was_signed_by_git_hub
+                );
+                Verification {
+                    verified: signature.is_valid,
+                    reason: reason_from_state(&signature.state),
+                    signature: Some(signature.signature),
+                    payload: Some(signature.payload),
+                }
+            }
+            None => Verification {
+                verified: false,
+                reason: Reason::Unsigned,
+                signature: None,
+                payload: None,
+            },
+        };
+
+        Commit {
+            sha: Sha::new(commit.oid),
+            commit: CommitDetail {
+                author: commit.author.into(),
+                committer: commit.committer.into(),
+                message: commit.message,
+                verification,
+            },
+            parents: commit
+                .parents
+                .nodes
+                .into_iter()
+                .map(|x| Parent {
+                    sha: Sha::new(x.oid),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Maps `GitSignatureState` to the REST API's verification reason
+///
+/// See https://developer.github.com/v4/enum/gitsignaturestate/
+fn reason_from_state(state: &str) -> Reason {
+    match state {
+        "VALID" => Reason::Valid,
+        "EXPIRED_KEY" => Reason::ExpiredKey,
+        "NOT_SIGNING_KEY" => Reason::NotSigningKey,
+        "GPGVERIFY_ERROR" => Reason::GpgverifyError,
+        "GPGVERIFY_UNAVAILABLE" => Reason::GpgverifyUnavailable,
+        "UNSIGNED" => Reason::Unsigned,
+        "UNKNOWN_SIG_TYPE" => Reason::UnknownSignatureType,
+        "NO_USER" => Reason::NoUser,
+        "UNVERIFIED_EMAIL" => Reason::UnverifiedEmail,
+        "BAD_EMAIL" => Reason::BadEmail,
+        "UNKNOWN_KEY" => Reason::UnknownKey,
+        "MALFORMED_SIG" => Reason::MalformedSignature,
+        _ => Reason::Invalid,
+    }
+}
+
+/// Get commits via the `history` connection following all pages
+///
+/// In contrast to the REST API, the `author` parameter has to be an e-mail address.
+pub(crate) fn commits<T: Into<Option<Params>>>(
+    client: &AuthorizedClient,
+    repository: &Repository,
+    params: T,
+) -> Result<Vec<Commit>> {
+    let params_opt: Option<Params> = params.into();
+    let mut variables = variables(repository, params_opt.as_ref())?;
+    let to = params_opt.as_ref().and_then(|x| x.to.clone());
+
+    let mut commits: Vec<Commit> = Vec::new();
+    loop {
+        let history = query_history(client, &variables)?;
+        commits.extend(history.nodes.into_iter().map(Commit::from));
+
+        // There is no need to fetch further pages once the oldest requested commit has been seen
+        let found_to = to
+            .as_ref()
+            .map(|to| commits.iter().any(|x| &x.sha == to))
+            .unwrap_or(false);
+        match history.page_info.end_cursor {
+            Some(cursor) if history.page_info.has_next_page && !found_to => {
+                trace!("Following cursor '{}'", cursor);
+                variables["after"] = Value::String(cursor);
+            }
+            _ => break,
+        }
+    }
+
+    Ok(filter_to(commits, params_opt))
+}
+
+fn variables(repository: &Repository, params: Option<&Params>) -> Result<Value> {
+    let mut variables = json!({
+        "owner": repository.owner,
+        "name": repository.name,
+        "expression": "HEAD",
+        "first": PAGE_SIZE,
+    });
+
+    if let Some(params) = params {
+        if let Some(ref from) = params.from {
+            variables["expression"] = Value::String(from.to_string());
+        }
+        if let Some(ref path) = params.path {
+            variables["path"] = Value::String(path.clone());
+        }
+        if let Some(ref author) = params.author {
+            if !author.contains('@') {
+                return Err(Error::from(ErrorKind::FailedToPrepareHttpRequest(format!(
+                    "author '{}' is not an e-mail address",
+                    author
+                ))));
+            }
+            variables["author"] = json!({ "emails": [author] });
+        }
+        if let Some(ref since) = params.since {
+            variables["since"] = Value::String(since.to_rfc3339());
+        }
+        if let Some(ref until) = params.until {
+            variables["until"] = Value::String(until.to_rfc3339());
+        }
+    }
+
+    Ok(variables)
+}
+
+fn query_history(client: &AuthorizedClient, variables: &Value) -> Result<History> {
+    let OAuthToken(ref token) = client.oauth_token;
+    let request = client
+        .http
+        .post(&client.graphql_url())
+        .json(&Request {
+            query: HISTORY_QUERY,
+            variables: variables.clone(),
+        })
+        .bearer_auth(token);
+    debug!("Request: '{:#?}'", request);
+
+    let mut response: Response = request
+        .send()
+        .map_err(|e| e.context(ErrorKind::HttpRequestFailed))?
+        .general_err_handler(StatusCode::OK)?;
+    debug!("Response: '{:#?}'", response);
+
+    let result: GraphQlResponse = response.json().map_err(|e| {
+        e.context(ErrorKind::FailedToProcessHttpResponse(
+            response.status(),
+            "reading body".to_string(),
+        ))
+    })?;
+
+    history_from_response(result)
+}
+
+/// GraphQL reports errors with status code 200, so they have to be checked explicitly
+fn history_from_response(response: GraphQlResponse) -> Result<History> {
+    if !response.errors.is_empty() {
+        let messages: Vec<_> = response.errors.into_iter().map(|x| x.message).collect();
+        return Err(Error::from(ErrorKind::ApiCallFailed(
+            StatusCode::OK,
+            messages.join("; "),
+        )));
+    }
+
+    let history = response
+        .data
+        .as_ref()
+        .and_then(|x| x.pointer("/repository/object/history"))
+        .cloned()
+        .ok_or_else(|| {
+            Error::from(ErrorKind::FailedToProcessHttpResponse(
+                StatusCode::OK,
+                "object is not a commit".to_string(),
+            ))
+        })?;
+
+    serde_json::from_value(history).map_err(|e| {
+        e.context(ErrorKind::FailedToProcessHttpResponse(
+            StatusCode::OK,
+            "reading history".to_string(),
+        ))
+        .into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{github::Client, utils::test};
+
+    use spectral::prelude::*;
+
+    #[test]
+    fn graphql_url_follows_base_url() {
+        test::init();
+
+        let token = OAuthToken("token".to_string());
+        let github = Client::with_oauth_token(&token);
+        let enterprise =
+            Client::with_oauth_token(&token).with_base_url("https://github.example.com/api/v3/");
+
+        asserting("GraphQL API of GitHub")
+            .that(&github.graphql_url())
+            .is_equal_to("https://api.github.com/graphql".to_string());
+        asserting("GraphQL API of GitHub Enterprise")
+            .that(&enterprise.graphql_url())
+            .is_equal_to("https://github.example.com/api/graphql".to_string());
+    }
+
+    #[test]
+    fn history_from_response_okay() {
+        test::init();
+
+        let response: GraphQlResponse = serde_json::from_str(
+            r#"{
+                "data": { "repository": { "object": { "history": {
+                    "pageInfo": { "hasNextPage": true, "endCursor": "abc 99" },
+                    "nodes": [
+                        {
+                            "oid": "c255ad2347d00cae3dd2d7a21e1357e50413fc4f",
+                            "message": "Test commit",
+                            "author": { "name": "Second Test", "email": "second@example.com", "date": "2019-06-25T12:00:00+02:00" },
+                            "committer": { "name": "Second Test", "email": "second@example.com", "date": "2019-06-25T12:00:00+02:00" },
+                            "parents": { "nodes": [ { "oid": "5b1c1fa6dd0f4f3a9ab3a5cb9ef4c1f1e1ee9ff4" } ] },
+                            "signature": {
+                                "isValid": false,
+                                "state": "UNKNOWN_SIG_TYPE",
+                                "wasSignedByGitHub": false,
+                                "signer": null,
+                                "signature": "-----BEGIN SSH SIGNATURE-----",
+                                "payload": "tree 8b1a..."
+                            }
+                        },
+                        {
+                            "oid": "5b1c1fa6dd0f4f3a9ab3a5cb9ef4c1f1e1ee9ff4",
+                            "message": "Initial commit",
+                            "author": { "name": "Second Test", "email": "second@example.com", "date": "2019-06-25T11:00:00+02:00" },
+                            "committer": { "name": "Second Test", "email": "second@example.com", "date": "2019-06-25T11:00:00+02:00" },
+                            "parents": { "nodes": [] },
+                            "signature": null
+                        }
+                    ]
+                } } } }
+            }"#,
+        )
+        .expect("failed to deserialize response");
+
+        let history = history_from_response(response).expect("failed to read history");
+        let commits: Vec<_> = history.nodes.into_iter().map(Commit::from).collect();
+
+        asserting("cursor is read")
+            .that(&history.page_info.end_cursor)
+            .is_equal_to(&Some("abc 99".to_string()));
+        asserting("state is mapped to reason")
+            .that(&commits[0].commit.verification.reason)
+            .is_equal_to(&Reason::UnknownSignatureType);
+        asserting("signature is kept")
+            .that(&commits[0].commit.verification.signature.is_some())
+            .is_true();
+        asserting("parents are read")
+            .that(&commits[0].parents.len())
+            .is_equal_to(1);
+        asserting("commit without signature is unsigned")
+            .that(&commits[1].commit.verification.reason)
+            .is_equal_to(&Reason::Unsigned);
+    }
+
+    #[test]
+    fn history_from_error_response_failed() {
+        test::init();
+
+        let response: GraphQlResponse = serde_json::from_str(
+            r#"{ "data": null, "errors": [ { "message": "Could not resolve to a Repository" } ] }"#,
+        )
+        .expect("failed to deserialize response");
+
+        asserting("errors are reported")
+            .that(&history_from_response(response))
+            .is_err()
+            .map(|x| x.kind())
+            .is_equal_to(&ErrorKind::ApiCallFailed(
+                StatusCode::OK,
+                "Could not resolve to a Repository".to_string(),
+            ));
+    }
+
+    #[test]
+    fn variables_author_must_be_email() {
+        test::init();
+
+        let repository = Repository::new("lukaspustina", "github-watchtower");
+
+        asserting("login is rejected")
+            .that(&variables(
+                &repository,
+                Some(&Params::new().author("lukaspustina".to_string())),
+            ))
+            .is_err();
+        asserting("e-mail and from are set")
+            .that(&variables(
+                &repository,
+                Some(
+                    &Params::new()
+                        .from(Sha::new("c255ad2347d00cae3dd2d7a21e1357e50413fc4f"))
+                        .author("second@example.com".to_string()),
+                ),
+            ))
+            .is_ok()
+            .matches(|x| {
+                x["expression"] == "c255ad2347d00cae3dd2d7a21e1357e50413fc4f"
+                    && x["author"]["emails"][0] == "second@example.com"
+            });
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    errors::*,
+    github::{
+        commits::{next_link, PersonDetails, Sha, Verification},
+        AuthorizedClient, OAuthToken, Repository, GITHUB_ACCEPT_HEADER,
+    },
+    utils::http::GeneralErrHandler,
+};
+
+use failure::Fail;
+use log::{debug, trace};
+use reqwest::{self, header, Response, StatusCode};
+use serde::Deserialize;
+
+/// Reference to a tag as returned by the Git Data API
+///
+/// See https://developer.github.com/v3/git/refs/
+#[derive(Debug, Deserialize)]
+pub struct TagRef {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub object: GitObject,
+}
+
+impl TagRef {
+    /// Name of the tag without the `refs/tags/` prefix
+    pub fn name(&self) -> &str {
+        self.reference.trim_start_matches("refs/tags/")
+    }
+
+    /// Only annotated tags point to tag objects and may carry a signature
+    pub fn is_annotated(&self) -> bool {
+        self.object.object_type == ObjectType::Tag
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitObject {
+    #[serde(rename = "type")]
+    pub object_type: ObjectType,
+    pub sha: Sha,
+}
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectType {
+    Commit,
+    Tree,
+    Blob,
+    Tag,
+}
+
+/// Annotated tag as returned by the Git Data API
+///
+/// See https://developer.github.com/v3/git/tags/
+#[derive(Debug, Deserialize)]
+pub struct Tag {
+    pub sha: Sha,
+    pub tag: String,
+    pub message: String,
+    pub tagger: PersonDetails,
+    pub object: GitObject,
+    pub verification: Verification,
+}
+
+/// Get all tag references of a repository
+pub(crate) fn tags(client: &AuthorizedClient, repository: &Repository) -> Result<Vec<TagRef>> {
+    let OAuthToken(ref token) = client.oauth_token;
+
+    let url = format!(
+        "{base_url}/repos/{owner}/{repository}/git/refs/tags",
+        base_url = client.base_url,
+        owner = repository.owner,
+        repository = repository.name
+    );
+
+    let mut tags: Vec<TagRef> = Vec::new();
+
+    let mut response = get(&client, &url, &token)?;
+    loop {
+        let result: Vec<TagRef> = response.json().map_err(|e| {
+            e.context(ErrorKind::FailedToProcessHttpResponse(
+                response.status(),
+                "reading body".to_string(),
+            ))
+        })?;
+        tags.extend(result);
+
+        if let Some(next_link) = next_link(&response)? {
+            trace!("Following next header: '{}'", next_link);
+            response = get(&client, next_link, &token)?;
+        } else {
+            break;
+        }
+    }
+
+    Ok(tags)
+}
+
+/// Get an annotated tag by the sha of its tag object
+pub(crate) fn tag(client: &AuthorizedClient, repository: &Repository, sha: &Sha) -> Result<Tag> {
+    let OAuthToken(ref token) = client.oauth_token;
+
+    let url = format!(
+        "{base_url}/repos/{owner}/{repository}/git/tags/{sha}",
+        base_url = client.base_url,
+        owner = repository.owner,
+        repository = repository.name,
+        sha = sha,
+    );
+
+    let mut response = get(&client, &url, &token)?;
+    let result = response.json().map_err(|e| {
+        e.context(ErrorKind::FailedToProcessHttpResponse(
+            response.status(),
+            "reading body".to_string(),
+        ))
+    })?;
+
+    Ok(result)
+}
+
+fn get(client: &AuthorizedClient, url: &str, token: &str) -> Result<Response> {
+    let request = client
+        .http
+        .get(url)
+        .header(header::ACCEPT, GITHUB_ACCEPT_HEADER)
+        .bearer_auth(token);
+    debug!("Request: '{:#?}'", request);
+
+    let response: Response = request
+        .send()
+        .map_err(|e| e.context(ErrorKind::HttpRequestFailed))?
+        .general_err_handler(StatusCode::OK)?;
+    debug!("Response: '{:#?}'", response);
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test;
+
+    use serde_json;
+    use spectral::prelude::*;
+
+    #[test]
+    fn deserialize_tag_refs() {
+        test::init();
+
+        let tags_json = r#"
+            [
+                {
+                    "ref": "refs/tags/v0.0.1",
+                    "node_id": "MDM6UmVmcmVmcy90YWdzL3YwLjAuMQ==",
+                    "url": "https://api.github.com/repos/octocat/Hello-World/git/refs/tags/v0.0.1",
+                    "object": {
+                        "type": "tag",
+                        "sha": "940bd336248efae0f9ee5bc7b2d5c985887b16ac",
+                        "url": "https://api.github.com/repos/octocat/Hello-World/git/tags/940bd336248efae0f9ee5bc7b2d5c985887b16ac"
+                    }
+                },
+                {
+                    "ref": "refs/tags/lightweight",
+                    "node_id": "MDM6UmVmcmVmcy90YWdzL2xpZ2h0d2VpZ2h0",
+                    "url": "https://api.github.com/repos/octocat/Hello-World/git/refs/tags/lightweight",
+                    "object": {
+                        "type": "commit",
+                        "sha": "c3d0be41ecbe669545ee3e94d31ed9a4bc91ee3c",
+                        "url": "https://api.github.com/repos/octocat/Hello-World/git/commits/c3d0be41ecbe669545ee3e94d31ed9a4bc91ee3c"
+                    }
+                }
+            ]
+        "#;
+
+        let tags: ::std::result::Result<Vec<TagRef>, _> = serde_json::from_str(tags_json);
+
+        asserting("tag refs are deserialized")
+            .that(&tags)
+            .is_ok()
+            .has_length(2);
+        let tags = tags.unwrap();
+        asserting("tag name is stripped")
+            .that(&tags[0].name())
+            .is_equal_to("v0.0.1");
+        asserting("annotated tag is detected")
+            .that(&tags[0].is_annotated())
+            .is_true();
+        asserting("lightweight tag is detected")
+            .that(&tags[1].is_annotated())
+            .is_false();
+    }
+
+    #[test]
+    fn deserialize_tag_with_verification_object() {
+        test::init();
+
+        let tag_json = r#"
+            {
+                "node_id": "MDM6VGFnOTQwYmQzMzYyNDhlZmFlMGY5ZWU1YmM3YjJkNWM5ODU4ODdiMTZhYw==",
+                "tag": "v0.0.1",
+                "sha": "940bd336248efae0f9ee5bc7b2d5c985887b16ac",
+                "url": "https://api.github.com/repos/octocat/Hello-World/git/tags/940bd336248efae0f9ee5bc7b2d5c985887b16ac",
+                "message": "Release v0.0.1\n",
+                "tagger": {
+                    "name": "Second Test",
+                    "email": "second@example.com",
+                    "date": "2019-06-25T10:53:20Z"
+                },
+                "object": {
+                    "type": "commit",
+                    "sha": "72cf6df73dbd1a13ac096319e00cb63e0f2846c7",
+                    "url": "https://api.github.com/repos/octocat/Hello-World/git/commits/72cf6df73dbd1a13ac096319e00cb63e0f2846c7"
+                },
+                "verification": {
+                    "verified": true,
+                    "reason": "valid",
+                    "signature": "-----BEGIN PGP SIGNATURE-----\n...\n-----END PGP SIGNATURE-----\n",
+                    "payload": "object 72cf6df73dbd1a13ac096319e00cb63e0f2846c7\ntype commit\ntag v0.0.1\ntagger Second Test <second@example.com> 1561460000 +0200\n\nRelease v0.0.1\n"
+                }
+            }
+        "#;
+
+        let tag: ::std::result::Result<Tag, _> = serde_json::from_str(tag_json);
+
+        asserting("tag is deserialized")
+            .that(&tag)
+            .is_ok()
+            .map(|x| &x.tag)
+            .is_equal_to(&"v0.0.1".to_string());
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{
+    errors::*,
+    github::{commits::next_link, AuthorizedClient, OAuthToken, GITHUB_ACCEPT_HEADER},
+    utils::http::GeneralErrHandler,
+};
+
+use chrono::{DateTime, FixedOffset};
+use failure::Fail;
+use log::{debug, trace};
+use reqwest::{self, header, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+
+/// GPG key of a GitHub user
+///
+/// See https://developer.github.com/v3/users/gpg_keys/
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GpgKey {
+    pub id: u64,
+    pub key_id: String,
+    /// Armored public key; only available for keys uploaded after GitHub started to store them
+    pub raw_key: Option<String>,
+    pub emails: Vec<GpgKeyEmail>,
+    pub can_sign: bool,
+    pub expires_at: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GpgKeyEmail {
+    pub email: String,
+    pub verified: bool,
+}
+
+/// Get the public GPG keys of a user
+pub(crate) fn user_gpg_keys(client: &AuthorizedClient, login: &str) -> Result<Vec<GpgKey>> {
+    let OAuthToken(ref token) = client.oauth_token;
+
+    let url = format!(
+        "{base_url}/users/{login}/gpg_keys",
+        base_url = client.base_url,
+        login = login
+    );
+
+    let mut keys: Vec<GpgKey> = Vec::new();
+
+    let mut response = get(&client, &url, &token)?;
+    loop {
+        let result: Vec<GpgKey> = response.json().map_err(|e| {
+            e.context(ErrorKind::FailedToProcessHttpResponse(
+                response.status(),
+                "reading body".to_string(),
+            ))
+        })?;
+        keys.extend(result);
+
+        if let Some(next_link) = next_link(&response)? {
+            trace!("Following next header: '{}'", next_link);
+            response = get(&client, next_link, &token)?;
+        } else {
+            break;
+        }
+    }
+
+    Ok(keys)
+}
+
+fn get(client: &AuthorizedClient, url: &str, token: &str) -> Result<Response> {
+    let request = client
+        .http
+        .get(url)
+        .header(header::ACCEPT, GITHUB_ACCEPT_HEADER)
+        .bearer_auth(token);
+    debug!("Request: '{:#?}'", request);
+
+    let response: Response = request
+        .send()
+        .map_err(|e| e.context(ErrorKind::HttpRequestFailed))?
+        .general_err_handler(StatusCode::OK)?;
+    debug!("Response: '{:#?}'", response);
+
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test;
+
+    use serde_json;
+    use spectral::prelude::*;
+
+    #[test]
+    fn deserialize_gpg_keys() {
+        test::init();
+
+        let keys_json = r#"
+            [
+                {
+                    "id": 3,
+                    "primary_key_id": null,
+                    "key_id": "3262EFF25BA0D270",
+                    "public_key": "xsBNBFayYZ...",
+                    "emails": [
+                        {
+                            "email": "mastahyeti@users.noreply.github.com",
+                            "verified": true
+                        }
+                    ],
+                    "subkeys": [
+                        {
+                            "id": 4,
+                            "primary_key_id": 3,
+                            "key_id": "4A595D4C72EE49C7",
+                            "public_key": "zsBNBFayYZ...",
+                            "emails": [],
+                            "subkeys": [],
+                            "can_sign": false,
+                            "can_encrypt_comms": true,
+                            "can_encrypt_storage": true,
+                            "can_certify": false,
+                            "created_at": "2016-03-24T11:31:04-06:00",
+                            "expires_at": null
+                        }
+                    ],
+                    "can_sign": true,
+                    "can_encrypt_comms": false,
+                    "can_encrypt_storage": false,
+                    "can_certify": true,
+                    "created_at": "2016-03-24T11:31:04-06:00",
+                    "expires_at": null,
+                    "raw_key": "-----BEGIN PGP PUBLIC KEY BLOCK-----\n...\n-----END PGP PUBLIC KEY BLOCK-----"
+                }
+            ]
+        "#;
+
+        let keys: ::std::result::Result<Vec<GpgKey>, _> = serde_json::from_str(keys_json);
+
+        asserting("GPG keys are deserialized")
+            .that(&keys)
+            .is_ok()
+            .has_length(1);
+    }
+}
@@ -0,0 +1,84 @@
+//! Authentication of webhook deliveries
+//!
+//! GitHub signs each payload with an HMAC keyed by the webhook secret and sends it in the
+//! `X-Hub-Signature` (SHA-1) and `X-Hub-Signature-256` (SHA-256) headers.
+
+use crate::errors::*;
+
+use hex;
+use ring::{digest, hmac};
+
+pub static SIGNATURE_HEADER: &str = "X-Hub-Signature";
+pub static SIGNATURE_256_HEADER: &str = "X-Hub-Signature-256";
+
+/// Verifies `signature`, e.g., `sha1=<hex>`, of a webhook `payload`
+pub fn verify_signature(secret: &str, signature: &str, payload: &[u8]) -> Result<()> {
+    let (algorithm, mac) = if signature.starts_with("sha256=") {
+        (&digest::SHA256, &signature[7..])
+    } else if signature.starts_with("sha1=") {
+        (&digest::SHA1, &signature[5..])
+    } else {
+        return Err(invalid("unsupported algorithm"));
+    };
+    let mac = hex::decode(mac).map_err(|_| invalid("not hex encoded"))?;
+
+    let key = hmac::VerificationKey::new(algorithm, secret.as_bytes());
+    hmac::verify(&key, payload, &mac).map_err(|_| invalid("payload does not match"))
+}
+
+fn invalid(reason: &str) -> Error {
+    Error::from(ErrorKind::InvalidWebhookSignature(reason.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test;
+
+    use spectral::prelude::*;
+
+    // Example from https://developer.github.com/webhooks/securing/
+    static PAYLOAD: &[u8] = b"Hello, World!";
+    static SECRET: &str = "It's a Secret to Everybody";
+
+    #[test]
+    fn verify_signature_okay() {
+        test::init();
+
+        let sha256 = verify_signature(
+            SECRET,
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+            PAYLOAD,
+        );
+
+        let sha1 = verify_signature(
+            SECRET,
+            "sha1=01dc10d0c83e72ed246219cdd91669667fe2ca59",
+            PAYLOAD,
+        );
+
+        asserting("SHA-256 signature is valid")
+            .that(&sha256)
+            .is_ok();
+        asserting("SHA-1 signature is valid").that(&sha1).is_ok();
+    }
+
+    #[test]
+    fn verify_signature_failed() {
+        test::init();
+
+        let res = verify_signature(
+            "other secret",
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17",
+            PAYLOAD,
+        );
+
+        asserting("signature with another secret is invalid")
+            .that(&res)
+            .is_err()
+            .map(|x| x.kind())
+            .is_equal_to(&ErrorKind::InvalidWebhookSignature(
+                "payload does not match".to_string(),
+            ));
+    }
+}
@@ -1,31 +1,266 @@
 use crate::{
-    config::GitHubWatchTowerConfig,
+    config::{GitHubWatchTowerConfig, MergeMode, PubKey, SignatureMode},
     errors::*,
-    github::commits::{Commit, Reason, Verification},
+    github::{
+        commits::{Commit, Reason, Sha, Verification},
+        GitHub, Repository, Tag,
+    },
+    source::{CommitSource, GitHubSource, SignedCommit},
+    ssh::{SshSigner, SshVerifier, SSH_SIGNATURE_ARMOR_BEGIN},
+    utils::sha256,
+    x509::{X509Verifier, X509_SIGNATURE_ARMOR_BEGIN},
 };
 
+use chrono::{DateTime, TimeZone, Utc};
 use log::{debug, trace, warn};
 use openpgp::{
+    armor,
+    crypto::{self, KeyPair},
+    packet::{key::SecretKey, Key, Signature},
     parse::{stream::*, Parse},
-    TPK,
+    serialize::stream::{Message, Signer},
+    RevocationStatus, TPK,
 };
 use sequoia_openpgp as openpgp;
-use std::{convert::TryFrom, path::Path};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    convert::TryFrom,
+    fmt,
+    io::Write,
+    iter,
+    path::Path,
+};
+
+/// Fingerprints of the keys GitHub uses to sign commits created in its web UI, cf.
+/// https://github.com/web-flow.gpg
+pub static GITHUB_WEB_FLOW_FINGERPRINTS: &[&str] = &[
+    "5DE3E0509C47EA3CF04A42D34AEE18F83AFDEB23",
+    "968479A1AFF927E37D1A566BB5690EEEBB952194",
+];
+
+/// Maximum number of parents of web-flow merges retrieved from outside of a verified batch
+pub const MAX_RESOLVED_PARENTS: usize = 100;
 
 #[derive(Debug)]
 pub struct CommitVerifier {
     pub_keys: Vec<TPK>,
+    subkey_policies: HashMap<String, SubkeyPolicy>,
+    owners: HashMap<String, KeyOwner>,
+    require_pusher_owns_key: bool,
+    signature_mode: SignatureMode,
+    ssh_verifier: SshVerifier,
+    x509_verifier: Option<X509Verifier>,
+    web_flow_verifier: Option<Box<CommitVerifier>>,
+    merge_mode: MergeMode,
+}
+
+/// Verifies signatures of annotated tags against the keys of release managers
+#[derive(Debug)]
+pub struct TagVerifier {
+    verifier: CommitVerifier,
+}
+
+impl TryFrom<&GitHubWatchTowerConfig> for TagVerifier {
+    type Error = Error;
+    fn try_from(config: &GitHubWatchTowerConfig) -> Result<TagVerifier> {
+        let verifier = CommitVerifier::from_armored_keys(
+            config
+                .release_pub_keys
+                .iter()
+                .map(|x| x.armored_key.as_ref()),
+        )?
+        .with_signature_mode(config.policy.signature_mode);
+        check_fingerprints(&config.release_pub_keys, &verifier.pub_keys)?;
+
+        Ok(TagVerifier { verifier })
+    }
+}
+
+impl TagVerifier {
+    pub fn from_keys(pub_keys: Vec<TPK>) -> TagVerifier {
+        TagVerifier {
+            verifier: CommitVerifier::from_keys(pub_keys),
+        }
+    }
+
+    pub fn from_key_files<P: AsRef<Path>>(file_paths: &[P]) -> Result<TagVerifier> {
+        let verifier = CommitVerifier::from_key_files(file_paths)?;
+
+        Ok(TagVerifier { verifier })
+    }
+
+    /// Verifies a tag and returns the keys of all its trusted signatures
+    pub fn verify(&self, tag: &Tag) -> Result<Vec<VerificationKey>> {
+        self.verifier.verify_verification(&tag.verification)
+    }
+}
+
+/// Creates detached, ASCII armored signatures, e.g., for checkpoints of the audit log
+#[derive(Debug)]
+pub struct DetachedSigner {
+    tsk: TPK,
+}
+
+impl DetachedSigner {
+    /// The key file has to contain an unencrypted secret signing key
+    pub fn from_key_file<P: AsRef<Path>>(file_path: P) -> Result<DetachedSigner> {
+        let tsk = TPK::from_file(file_path).map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
+
+        Ok(DetachedSigner { tsk })
+    }
+
+    /// Fingerprint of the primary key
+    pub fn finger_print(&self) -> String {
+        self.tsk.fingerprint().to_hex()
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Result<String> {
+        let key = self
+            .tsk
+            .keys_valid()
+            .signing_capable()
+            .map(|x| x.2)
+            .next()
+            .ok_or_else(|| {
+                Error::from(ErrorKind::FailedToSign(format!(
+                    "key {} has no valid signing key",
+                    self.finger_print()
+                )))
+            })?;
+        let mut keypair = match key.secret() {
+            Some(SecretKey::Unencrypted { ref mpis }) => KeyPair::new(key.clone(), mpis.clone())
+                .map_err(|e| {
+                    e.context(ErrorKind::FailedToSign("invalid secret key".to_string()))
+                })?,
+            _ => {
+                return Err(Error::from(ErrorKind::FailedToSign(format!(
+                    "signing key {} has no unencrypted secret key",
+                    key.fingerprint().to_hex()
+                ))))
+            }
+        };
+
+        let mut signature = Vec::new();
+        {
+            let sink = armor::Writer::new(&mut signature, armor::Kind::Signature, &[])
+                .map_err(|e| e.context(ErrorKind::FailedToSign("armor failed".to_string())))?;
+            let signers: Vec<&mut dyn crypto::Signer> = vec![&mut keypair];
+            let mut signer = Signer::detached(Message::new(sink), signers)
+                .map_err(|e| e.context(ErrorKind::FailedToSign("signing failed".to_string())))?;
+            signer
+                .write_all(message)
+                .map_err(|e| e.context(ErrorKind::FailedToSign("signing failed".to_string())))?;
+            signer
+                .finalize()
+                .map_err(|e| e.context(ErrorKind::FailedToSign("signing failed".to_string())))?;
+        }
+
+        String::from_utf8(signature).map_err(|e| {
+            e.context(ErrorKind::FailedToSign(
+                "signature is not ASCII".to_string(),
+            ))
+            .into()
+        })
+    }
+}
+
+/// Result of verifying a merge commit together with the commits it introduces
+#[derive(Debug)]
+pub struct MergeVerification {
+    pub sha: Sha,
+    pub keys: Result<Vec<VerificationKey>>,
+    /// Commits reachable from the non-first parents but not from the first parent; empty for
+    /// `MergeMode::CommitOnly` and commits that are not merges
+    pub introduced: Vec<(Sha, Result<Vec<VerificationKey>>)>,
+}
+
+impl MergeVerification {
+    /// True if the merge commit and all commits it introduces are verified
+    pub fn is_verified(&self) -> bool {
+        self.keys.is_ok() && self.introduced.iter().all(|(_, res)| res.is_ok())
+    }
+}
+
+/// Restricts which (sub)keys of a public key may sign commits
+///
+/// If `pinned` is not empty, only the listed (sub)keys are allowed to sign. Any (sub)key listed in
+/// `blocked` is never allowed to sign. Fingerprints may contain white space and are case
+/// insensitive.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SubkeyPolicy {
+    pub pinned: Vec<String>,
+    pub blocked: Vec<String>,
+}
+
+impl SubkeyPolicy {
+    fn allows(&self, finger_print: &str) -> bool {
+        let is_listed = |list: &[String]| {
+            list.iter()
+                .any(|x| normalize_fingerprint(x) == finger_print)
+        };
+
+        (self.pinned.is_empty() || is_listed(&self.pinned)) && !is_listed(&self.blocked)
+    }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+/// The person owning a public key as configured, cf. `PubKey`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct KeyOwner {
+    pub name: String,
+    pub github_logins: Vec<String>,
+    pub e_mails: Vec<String>,
+    pub teams: Vec<String>,
+}
+
+impl KeyOwner {
+    pub fn has_github_login(&self, login: &str) -> bool {
+        self.github_logins
+            .iter()
+            .any(|x| x.eq_ignore_ascii_case(login))
+    }
+}
+
+/// Formats an owner as `@alice (team: platform)` or, without GitHub login, `Alice`
+impl fmt::Display for KeyOwner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.github_logins.first() {
+            Some(login) => write!(f, "@{}", login)?,
+            None => f.write_str(&self.name)?,
+        }
+        if !self.teams.is_empty() {
+            write!(f, " (team: {})", self.teams.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The kind of key a signature has been verified with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyKind {
+    OpenPgp,
+    /// SSH keys have neither key IDs nor creation times; the key ID is the fingerprint and the
+    /// e-mail addresses are the principals of the allowed signer.
+    Ssh,
+    /// X.509 certificates have no key IDs; the key ID is the subject of the certificate and the
+    /// e-mail addresses are taken from the subject and its alternative names.
+    X509,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct VerificationKey {
+    kind: KeyKind,
     finger_print: String,
     key_id: String,
     e_mails: Vec<String>,
+    signing_key: SigningKey,
+    owner: Option<KeyOwner>,
 }
 
-impl From<TPK> for VerificationKey {
-    fn from(tpk: TPK) -> VerificationKey {
+impl VerificationKey {
+    fn new(tpk: &TPK, key: &Key, binding: Option<&Signature>) -> VerificationKey {
         let finger_print = tpk.fingerprint().to_string();
         let key_id = tpk.fingerprint().to_keyid().to_string();
         let e_mails = tpk
@@ -37,11 +272,153 @@ impl From<TPK> for VerificationKey {
             })
             .flatten()
             .collect();
+        let signing_key = SigningKey::new(key, binding);
 
         VerificationKey {
+            kind: KeyKind::OpenPgp,
             finger_print,
             key_id,
             e_mails,
+            signing_key,
+            owner: None,
+        }
+    }
+
+    pub(crate) fn from_ssh_key(finger_print: String, principals: Vec<String>) -> VerificationKey {
+        let signing_key = SigningKey {
+            finger_print: finger_print.clone(),
+            key_id: finger_print.clone(),
+            capabilities: KeyCapabilities {
+                sign: true,
+                ..Default::default()
+            },
+            creation_time: None,
+        };
+
+        VerificationKey {
+            kind: KeyKind::Ssh,
+            key_id: finger_print.clone(),
+            finger_print,
+            e_mails: principals,
+            signing_key,
+            owner: None,
+        }
+    }
+
+    pub(crate) fn from_x509_certificate(
+        finger_print: String,
+        subject: String,
+        e_mails: Vec<String>,
+    ) -> VerificationKey {
+        let signing_key = SigningKey {
+            finger_print: finger_print.clone(),
+            key_id: subject.clone(),
+            capabilities: KeyCapabilities {
+                sign: true,
+                ..Default::default()
+            },
+            creation_time: None,
+        };
+
+        VerificationKey {
+            kind: KeyKind::X509,
+            finger_print,
+            key_id: subject,
+            e_mails,
+            signing_key,
+            owner: None,
+        }
+    }
+
+    pub fn kind(&self) -> KeyKind {
+        self.kind
+    }
+
+    /// Fingerprint of the primary key
+    pub fn finger_print(&self) -> &str {
+        &self.finger_print
+    }
+
+    /// Key ID of the primary key
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn e_mails(&self) -> &[String] {
+        &self.e_mails
+    }
+
+    /// The (sub)key that actually created the signature
+    pub fn signing_key(&self) -> &SigningKey {
+        &self.signing_key
+    }
+
+    /// The owner of the key if configured
+    pub fn owner(&self) -> Option<&KeyOwner> {
+        self.owner.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SigningKey {
+    finger_print: String,
+    key_id: String,
+    capabilities: KeyCapabilities,
+    creation_time: Option<DateTime<Utc>>,
+}
+
+impl SigningKey {
+    fn new(key: &Key, binding: Option<&Signature>) -> SigningKey {
+        let finger_print = key.fingerprint().to_string();
+        let key_id = key.fingerprint().to_keyid().to_string();
+        let capabilities = binding.map(KeyCapabilities::from).unwrap_or_default();
+        let creation_time = Some(Utc.timestamp(key.creation_time().to_timespec().sec, 0));
+
+        SigningKey {
+            finger_print,
+            key_id,
+            capabilities,
+            creation_time,
+        }
+    }
+
+    pub fn finger_print(&self) -> &str {
+        &self.finger_print
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn capabilities(&self) -> &KeyCapabilities {
+        &self.capabilities
+    }
+
+    pub fn creation_time(&self) -> Option<&DateTime<Utc>> {
+        self.creation_time.as_ref()
+    }
+}
+
+/// Capabilities of a (sub)key as declared by its binding signature
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct KeyCapabilities {
+    pub certify: bool,
+    pub sign: bool,
+    pub encrypt_for_transport: bool,
+    pub encrypt_at_rest: bool,
+    pub authenticate: bool,
+}
+
+impl From<&Signature> for KeyCapabilities {
+    fn from(binding: &Signature) -> KeyCapabilities {
+        let flags = binding.key_flags();
+
+        KeyCapabilities {
+            certify: flags.can_certify(),
+            sign: flags.can_sign(),
+            encrypt_for_transport: flags.can_encrypt_for_transport(),
+            encrypt_at_rest: flags.can_encrypt_at_rest(),
+            authenticate: flags.can_authenticate(),
         }
     }
 }
@@ -49,13 +426,93 @@ impl From<TPK> for VerificationKey {
 impl TryFrom<&GitHubWatchTowerConfig> for CommitVerifier {
     type Error = Error;
     fn try_from(config: &GitHubWatchTowerConfig) -> Result<CommitVerifier> {
-        CommitVerifier::from_armored_keys(config.pub_keys.iter().map(|x| x.armored_key.as_ref()))
+        let ssh_signers: Result<Vec<_>> = config
+            .allowed_signers
+            .iter()
+            .map(SshSigner::try_from)
+            .collect();
+        let mut cv = CommitVerifier::from_armored_keys(
+            config.pub_keys.iter().map(|x| x.armored_key.as_ref()),
+        )?
+        .with_signature_mode(config.policy.signature_mode)
+        .with_merge_mode(config.policy.merge_mode)
+        .with_ssh_verifier(SshVerifier::new(ssh_signers?));
+        check_fingerprints(&config.pub_keys, &cv.pub_keys)?;
+        if !config.x509_root_cas.is_empty() {
+            cv = cv.with_x509_verifier(X509Verifier::from_pem_files(&config.x509_root_cas)?);
+        }
+        if config.policy.trust_web_flow {
+            let armored_key = config.web_flow_key.as_ref().ok_or_else(|| {
+                Error::from(ErrorKind::InvalidWebFlowKey(
+                    "trusting web-flow requires a web-flow key".to_string(),
+                ))
+            })?;
+            let tpk = TPK::from_bytes(armored_key.as_bytes())
+                .map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
+            cv = cv.with_web_flow_key(tpk)?;
+        }
+
+        let policies: Vec<_> = cv
+            .pub_keys
+            .iter()
+            .zip(config.pub_keys.iter())
+            .filter(|(_, x)| !x.pinned_subkeys.is_empty() || !x.blocked_subkeys.is_empty())
+            .map(|(tpk, x)| {
+                let policy = SubkeyPolicy {
+                    pinned: x.pinned_subkeys.clone(),
+                    blocked: x.blocked_subkeys.clone(),
+                };
+                (tpk.fingerprint().to_hex(), policy)
+            })
+            .collect();
+        cv.subkey_policies.extend(policies);
+
+        let mut owners: Vec<_> = cv
+            .pub_keys
+            .iter()
+            .zip(config.pub_keys.iter())
+            .map(|(tpk, x)| {
+                let owner = KeyOwner {
+                    name: x.name.clone(),
+                    github_logins: x.github_logins.clone(),
+                    e_mails: x.emails.clone(),
+                    teams: Vec::new(),
+                };
+                (tpk.fingerprint().to_hex(), owner)
+            })
+            .collect();
+        for team in &config.teams {
+            for member in &team.members {
+                let mut found = false;
+                for (_, owner) in owners.iter_mut().filter(|(_, x)| &x.name == member) {
+                    owner.teams.push(team.name.clone());
+                    found = true;
+                }
+                if !found && !config.allowed_signers.iter().any(|x| &x.name == member) {
+                    warn!("Team '{}' member '{}' has no public key", team.name, member);
+                }
+            }
+        }
+        cv.owners.extend(owners);
+        cv.require_pusher_owns_key = config.policy.require_pusher_owns_key;
+
+        Ok(cv)
     }
 }
 
 impl CommitVerifier {
     pub fn from_keys(pub_keys: Vec<TPK>) -> CommitVerifier {
-        CommitVerifier { pub_keys }
+        CommitVerifier {
+            pub_keys,
+            subkey_policies: HashMap::new(),
+            owners: HashMap::new(),
+            require_pusher_owns_key: false,
+            signature_mode: SignatureMode::default(),
+            ssh_verifier: SshVerifier::default(),
+            x509_verifier: None,
+            web_flow_verifier: None,
+            merge_mode: MergeMode::default(),
+        }
     }
 
     pub fn from_armored_keys<'a, T: IntoIterator<Item = &'a str>>(
@@ -67,7 +524,7 @@ impl CommitVerifier {
             .collect();
         let pub_keys = pub_keys.map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
 
-        Ok(CommitVerifier { pub_keys })
+        Ok(CommitVerifier::from_keys(pub_keys))
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<CommitVerifier> {
@@ -93,121 +550,758 @@ impl CommitVerifier {
 
         Ok(CommitVerifier::from_keys(keys))
     }
+
+    /// Adds public keys, e.g., from additional keyrings
+    pub fn with_keys(mut self, pub_keys: Vec<TPK>) -> Self {
+        self.pub_keys.extend(pub_keys);
+        self
+    }
+
+    /// Sets the subkey policy for the public key identified by the fingerprint of its primary key
+    pub fn with_subkey_policy(mut self, primary_finger_print: &str, policy: SubkeyPolicy) -> Self {
+        self.subkey_policies
+            .insert(normalize_fingerprint(primary_finger_print), policy);
+        self
+    }
+
+    /// Sets the owner of the public key identified by the fingerprint of its primary key
+    pub fn with_key_owner(mut self, primary_finger_print: &str, owner: KeyOwner) -> Self {
+        self.owners
+            .insert(normalize_fingerprint(primary_finger_print), owner);
+        self
+    }
+
+    /// Requires the pushing user to own one of the signing keys, cf. `verify_push`
+    pub fn with_require_pusher_owns_key(self, require_pusher_owns_key: bool) -> Self {
+        CommitVerifier {
+            require_pusher_owns_key,
+            ..self
+        }
+    }
+
+    /// Sets the verifier used for SSH signatures
+    pub fn with_ssh_verifier(self, ssh_verifier: SshVerifier) -> Self {
+        CommitVerifier {
+            ssh_verifier,
+            ..self
+        }
+    }
+
+    /// Sets the verifier used for X.509 / S/MIME signatures; these are rejected if unset
+    pub fn with_x509_verifier(self, x509_verifier: X509Verifier) -> Self {
+        CommitVerifier {
+            x509_verifier: Some(x509_verifier),
+            ..self
+        }
+    }
+
+    /// Trusts GitHub's web-flow key for commits whose parents are verified by our keys
+    ///
+    /// The key is only accepted if its fingerprint is one of `GITHUB_WEB_FLOW_FINGERPRINTS`.
+    pub fn with_web_flow_key(self, web_flow_key: TPK) -> Result<Self> {
+        let finger_print = web_flow_key.fingerprint().to_hex();
+        if !GITHUB_WEB_FLOW_FINGERPRINTS.contains(&finger_print.as_str()) {
+            return Err(Error::from(ErrorKind::InvalidWebFlowKey(format!(
+                "unknown fingerprint {}",
+                finger_print
+            ))));
+        }
+
+        Ok(self.with_web_flow_verifier(CommitVerifier::from_keys(vec![web_flow_key])))
+    }
+
+    fn with_web_flow_verifier(self, web_flow_verifier: CommitVerifier) -> Self {
+        CommitVerifier {
+            web_flow_verifier: Some(Box::new(web_flow_verifier)),
+            ..self
+        }
+    }
+
+    /// Sets which commits are verified when a merge commit lands
+    pub fn with_merge_mode(self, merge_mode: MergeMode) -> Self {
+        CommitVerifier { merge_mode, ..self }
+    }
+
+    /// Sets whether any or all signatures of a commit have to be made by trusted keys
+    pub fn with_signature_mode(self, signature_mode: SignatureMode) -> Self {
+        CommitVerifier {
+            signature_mode,
+            ..self
+        }
+    }
+
+    /// Fingerprints of all trusted keys and root CAs including those of the web-flow verifier,
+    /// sorted
+    pub fn finger_prints(&self) -> Vec<String> {
+        let mut finger_prints: Vec<_> = self
+            .pub_keys
+            .iter()
+            .map(|x| x.fingerprint().to_hex())
+            .collect();
+        finger_prints.extend(self.ssh_verifier.finger_prints());
+        if let Some(ref x509_verifier) = self.x509_verifier {
+            finger_prints.extend(x509_verifier.finger_prints().iter().cloned());
+        }
+        if let Some(ref web_flow_verifier) = self.web_flow_verifier {
+            finger_prints.extend(web_flow_verifier.finger_prints());
+        }
+        finger_prints.sort();
+        finger_prints.dedup();
+
+        finger_prints
+    }
+
+    /// SHA-256 over the fingerprints of all trusted keys; changes whenever a key is added or
+    /// removed
+    pub fn keyring_version(&self) -> String {
+        sha256::from_bytes_as_str(self.finger_prints().join("\n").as_bytes())
+    }
+
+    /// SHA-256 over everything verification results depend on, i.e., the trusted keys including
+    /// their revocations, the allowed SSH signers, the root CAs, subkey policies, key owners, and
+    /// the signature mode; cf. `keyring_version`
+    pub fn verification_version(&self) -> Result<String> {
+        let mut pub_keys = self
+            .pub_keys
+            .iter()
+            .map(|x| {
+                let mut bytes = Vec::new();
+                openpgp::serialize::Serialize::serialize(x, &mut bytes)
+                    .map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
+                Ok(sha256::from_bytes_as_str(&bytes))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        pub_keys.sort();
+        let web_flow_version = match self.web_flow_verifier {
+            Some(ref x) => Some(x.verification_version()?),
+            None => None,
+        };
+        let subkey_policies: BTreeMap<_, _> = self.subkey_policies.iter().collect();
+        let owners: BTreeMap<_, _> = self.owners.iter().collect();
+        let state = format!(
+            "{:?}\n{}\n{:?}\n{:?}\n{:?}\n{:?}\n{:?}",
+            pub_keys,
+            self.ssh_verifier.version(),
+            self.x509_verifier.as_ref().map(|x| x.finger_prints()),
+            web_flow_version,
+            subkey_policies,
+            owners,
+            self.signature_mode
+        );
+
+        Ok(sha256::from_bytes_as_str(state.as_bytes()))
+    }
 }
 
 // I really don't like this side effect way to do things, but there doesn't seem to be another way
 // to get the signature key out of `DetachedVerifier`. At least, the side effect is locally isolated
 // in this method only.
 impl CommitVerifier {
+    /// Verifies a commit and returns the key of its first trusted signature
     pub fn verify(&self, commit: &Commit) -> Result<VerificationKey> {
-        match commit.commit.verification {
+        let mut keys = self.verify_signers(commit)?;
+
+        Ok(keys.remove(0))
+    }
+
+    /// Verifies a commit and returns the keys of all its trusted signatures
+    ///
+    /// Depending on the `SignatureMode`, signatures by untrusted or invalid keys are either skipped
+    /// or fail the verification.
+    pub fn verify_signers(&self, commit: &Commit) -> Result<Vec<VerificationKey>> {
+        self.verify_signed_commit(&SignedCommit::from(commit))
+    }
+
+    /// Verifies a commit of any source, cf. `verify_signers`
+    ///
+    /// If the source verifies signatures itself, commits it has not verified are rejected.
+    pub fn verify_signed_commit(&self, commit: &SignedCommit) -> Result<Vec<VerificationKey>> {
+        match *commit {
+            SignedCommit {
+                source_reason: None,
+                signature: Some(ref signature),
+                payload: Some(ref message),
+                ..
+            }
+            | SignedCommit {
+                source_reason: Some(Reason::Valid),
+                signature: Some(ref signature),
+                payload: Some(ref message),
+                ..
+            } => self.verify_message(message.as_ref(), signature.as_ref()),
+            _ => Err(Error::from(ErrorKind::FailedToVerify(
+                "verification object is invalid".to_string(),
+            ))),
+        }
+    }
+
+    fn verify_verification(&self, verification: &Verification) -> Result<Vec<VerificationKey>> {
+        match *verification {
             Verification {
                 verified: true,
                 reason: Reason::Valid,
                 signature: Some(ref signature),
                 payload: Some(ref message),
-            } => verify_message(&self.pub_keys, message.as_ref(), signature.as_ref()),
+            } => self.verify_message(message.as_ref(), signature.as_ref()),
             _ => Err(Error::from(ErrorKind::FailedToVerify(
-                "commit verification object is invalid".to_string(),
+                "verification object is invalid".to_string(),
             ))),
         }
     }
-}
 
-fn verify_message(pub_keys: &[TPK], message: &[u8], signature: &[u8]) -> Result<VerificationKey> {
-    let mut result_key: Vec<TPK> = Vec::new();
-    let vc = VerificationContext::new(pub_keys, &mut result_key);
-    let _ = DetachedVerifier::from_bytes(signature, message, vc, None).map_err(|e| {
-        e.context(ErrorKind::FailedToVerify(
-            "signature could not be verified".to_string(),
-        ))
-    })?;
+    /// Verifies a commit pushed by the GitHub user `pusher` and returns the keys of all its trusted
+    /// signatures
+    ///
+    /// If required by configuration, one of the signing keys has to be owned by the pusher.
+    pub fn verify_push(&self, commit: &Commit, pusher: &str) -> Result<Vec<VerificationKey>> {
+        let keys = self.verify_signers(commit)?;
+        if self.require_pusher_owns_key
+            && !keys
+                .iter()
+                .filter_map(VerificationKey::owner)
+                .any(|x| x.has_github_login(pusher))
+        {
+            return Err(Error::from(ErrorKind::PusherDoesNotOwnKey(
+                pusher.to_string(),
+            )));
+        }
+
+        Ok(keys)
+    }
+
+    /// Verifies a batch of commits and returns the keys of all trusted signatures per commit
+    ///
+    /// In contrast to `verify_signers`, merge commits signed by GitHub's web-flow key are accepted
+    /// if web-flow is trusted and all their parents are verified by our keys or are accepted
+    /// web-flow merges themselves. Thus, a merge made in the web UI cannot introduce unverified
+    /// content. Other web-flow signed commits, e.g., file edits or squash merges made in the web
+    /// UI, are rejected. Parents outside of the batch cannot be verified, so these merges are
+    /// rejected as well; cf. `verify_signed_commits` to resolve them.
+    pub fn verify_commits(&self, commits: &[Commit]) -> Vec<Result<Vec<VerificationKey>>> {
+        let commits: Vec<_> = commits.iter().map(SignedCommit::from).collect();
+
+        self.verify_signed_commits(&commits, None)
+    }
+
+    /// Verifies a batch of commits of any source, cf. `verify_commits`
+    ///
+    /// Parents of web-flow merges outside of the batch are retrieved from `source`, if set.
+    pub fn verify_signed_commits(
+        &self,
+        commits: &[SignedCommit],
+        source: Option<&dyn CommitSource>,
+    ) -> Vec<Result<Vec<VerificationKey>>> {
+        self.verify_signed_commits_with(commits, source, |x| self.verify_signed_commit(x))
+    }
+
+    /// Verifies a batch of commits like `verify_signed_commits`, but single commits with `verify`
+    ///
+    /// This allows to look up verification results elsewhere first, e.g., in a cache. Commits
+    /// signed by GitHub's web-flow key are still verified by this verifier.
+    pub fn verify_signed_commits_with<F>(
+        &self,
+        commits: &[SignedCommit],
+        source: Option<&dyn CommitSource>,
+        mut verify: F,
+    ) -> Vec<Result<Vec<VerificationKey>>>
+    where
+        F: FnMut(&SignedCommit) -> Result<Vec<VerificationKey>>,
+    {
+        let mut results: Vec<_> = commits.iter().map(&mut verify).collect();
+
+        let web_flow_verifier = match self.web_flow_verifier {
+            Some(ref x) => x,
+            None => return results,
+        };
+        let mut verified: HashSet<Sha> = commits
+            .iter()
+            .zip(results.iter())
+            .filter(|(_, res)| res.is_ok())
+            .map(|(commit, _)| commit.sha.clone())
+            .collect();
+        let mut known: HashSet<Sha> = commits.iter().map(|x| x.sha.clone()).collect();
+
+        let mut merges = Vec::new();
+        for (index, (commit, res)) in commits.iter().zip(results.iter_mut()).enumerate() {
+            if res.is_err() {
+                if let Ok(keys) = web_flow_verifier.verify_signed_commit(commit) {
+                    match check_web_flow_merge(commit) {
+                        Ok(()) => merges.push(WebFlowMerge::new(Some(index), commit, keys)),
+                        Err(e) => *res = Err(e),
+                    }
+                }
+            }
+        }
+
+        // Merges are accepted once all their parents are, so chains of merges need several
+        // passes. Parents outside of the batch are resolved on the way.
+        let mut resolved = 0;
+        loop {
+            let mut missing: Vec<Sha> = Vec::new();
+            for parent in merges.iter().flat_map(|x| x.parents.iter()) {
+                if !known.contains(parent) && !missing.contains(parent) {
+                    missing.push(parent.clone());
+                }
+            }
+            for sha in &missing {
+                known.insert(sha.clone());
+                let source = match source {
+                    Some(x) if resolved < MAX_RESOLVED_PARENTS => x,
+                    _ => continue,
+                };
+                resolved += 1;
+                let commit = match source.commit(sha) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        warn!("Failed to resolve parent {} of web-flow merge: {}", sha, e);
+                        continue;
+                    }
+                };
+                if verify(&commit).is_ok() {
+                    verified.insert(commit.sha.clone());
+                } else if let Ok(keys) = web_flow_verifier.verify_signed_commit(&commit) {
+                    if check_web_flow_merge(&commit).is_ok() {
+                        merges.push(WebFlowMerge::new(None, &commit, keys));
+                    }
+                }
+            }
+
+            let pending = merges.len();
+            merges.retain(|merge| {
+                if merge.parents.iter().any(|x| !verified.contains(x)) {
+                    return true;
+                }
+                verified.insert(merge.sha.clone());
+                if let Some(index) = merge.index {
+                    results[index] = Ok(merge.keys.clone());
+                }
+                false
+            });
+            if missing.is_empty() && merges.len() == pending {
+                break;
+            }
+        }
+
+        for merge in merges {
+            if let (Some(index), Some(parent)) = (
+                merge.index,
+                merge.parents.iter().find(|x| !verified.contains(x)),
+            ) {
+                results[index] = Err(Error::from(ErrorKind::FailedToVerify(format!(
+                    "web-flow signed commit has unverified parent {}",
+                    parent
+                ))));
+            }
+        }
+
+        results
+    }
+
+    /// Verifies a commit and, depending on the `MergeMode`, the commits it introduces if it is a
+    /// merge commit
+    ///
+    /// The introduced commits are retrieved from GitHub and verified as a batch together with the
+    /// merge commit, cf. `verify_signed_commits`. Thus, merges made in the web UI are accepted like
+    /// in any other batch; parents outside of the batch are retrieved from GitHub.
+    pub fn verify_merge<G: GitHub>(
+        &self,
+        github: &G,
+        repository: &Repository,
+        commit: &Commit,
+    ) -> Result<MergeVerification> {
+        let mut introduced: Vec<Commit> = Vec::new();
+        if self.merge_mode == MergeMode::IntroducedCommits && commit.parents.len() > 1 {
+            let first_parent = &commit.parents[0].sha;
+            for parent in &commit.parents[1..] {
+                let commits = github.compare(repository, first_parent, &parent.sha)?;
+                for c in commits {
+                    if !introduced.iter().any(|x| x.sha == c.sha) {
+                        introduced.push(c);
+                    }
+                }
+            }
+        }
+        debug!(
+            "Merge commit {} introduces {} commits",
+            commit.sha,
+            introduced.len()
+        );
+
+        let source = GitHubSource::new(github, repository);
+        let commits: Vec<_> = iter::once(commit)
+            .chain(introduced.iter())
+            .map(SignedCommit::from)
+            .collect();
+        let mut results = self.verify_signed_commits(&commits, Some(&source));
+        let keys = results.remove(0);
+        let introduced = introduced
+            .into_iter()
+            .map(|x| x.sha)
+            .zip(results.into_iter())
+            .collect();
+
+        Ok(MergeVerification {
+            sha: commit.sha.clone(),
+            keys,
+            introduced,
+        })
+    }
+
+    pub(crate) fn verify_message(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<Vec<VerificationKey>> {
+        // SSH signatures always carry exactly one signature, so there is no need to apply the
+        // signature mode.
+        if signature.starts_with(SSH_SIGNATURE_ARMOR_BEGIN.as_bytes()) {
+            let signature = String::from_utf8_lossy(signature);
+            return self
+                .ssh_verifier
+                .verify(message, &signature)
+                .map(|x| vec![x]);
+        }
+        if signature.starts_with(X509_SIGNATURE_ARMOR_BEGIN.as_bytes()) {
+            let x509_verifier = self.x509_verifier.as_ref().ok_or_else(|| {
+                Error::from(ErrorKind::FailedToVerify(
+                    "X.509 signatures are not trusted; no root CAs configured".to_string(),
+                ))
+            })?;
+            let signature = String::from_utf8_lossy(signature);
+            return x509_verifier.verify(message, &signature).map(|x| vec![x]);
+        }
 
-    if result_key.len() > 1 {
-        warn!("Found multiple signing keys. That's not inherently bad, but unexpected and odd.")
+        let mut result_keys: Vec<VerificationKey> = Vec::new();
+        let vc = VerificationContext::new(self, &mut result_keys);
+        let _ = DetachedVerifier::from_bytes(signature, message, vc, None)
+            .map_err(verification_error)?;
+
+        if result_keys.is_empty() {
+            return Err(Error::from(ErrorKind::FailedToVerify(
+                "no key found; this should not happen".to_string(),
+            )));
+        }
+        debug!("Message successfully verified with keys {:?}", result_keys);
+
+        Ok(result_keys)
+    }
+}
+
+/// Web-flow signed commits with a single parent may introduce content written in the web UI
+fn check_web_flow_merge(commit: &SignedCommit) -> Result<()> {
+    if commit.parents.len() < 2 {
+        return Err(Error::from(ErrorKind::FailedToVerify(format!(
+            "web-flow signed commit has {} parents, so it may introduce unverified content",
+            commit.parents.len()
+        ))));
     }
 
-    let tpk = result_key.pop().ok_or_else(|| {
-        Error::from(ErrorKind::FailedToVerify(
-            "no key found; this should not happen".to_string(),
-        ))
-    })?;
+    Ok(())
+}
+
+/// Web-flow signed merge commit accepted as soon as all its parents are
+struct WebFlowMerge {
+    /// Position in the verified batch; `None` for parents resolved outside of the batch
+    index: Option<usize>,
+    sha: Sha,
+    parents: Vec<Sha>,
+    keys: Vec<VerificationKey>,
+}
 
-    let key = tpk.into();
-    debug!("Message successfully verified with key {:?}", key);
+impl WebFlowMerge {
+    fn new(
+        index: Option<usize>,
+        commit: &SignedCommit,
+        keys: Vec<VerificationKey>,
+    ) -> WebFlowMerge {
+        WebFlowMerge {
+            index,
+            sha: commit.sha.clone(),
+            parents: commit.parents.clone(),
+            keys,
+        }
+    }
+}
 
-    Ok(key)
+/// Key validity failures are raised as `ErrorKind` by our policy in `VerificationContext::check`;
+/// these are passed through as is so callers can distinguish them from other failures.
+fn verification_error(e: failure::Error) -> Error {
+    match e.downcast::<ErrorKind>() {
+        Ok(kind) => Error::from(kind),
+        Err(e) => e
+            .context(ErrorKind::FailedToVerify(
+                "signature could not be verified".to_string(),
+            ))
+            .into(),
+    }
 }
 
 struct VerificationContext<'a> {
-    pub_keys: &'a [TPK],
-    result_key: &'a mut Vec<TPK>,
+    verifier: &'a CommitVerifier,
+    result_keys: &'a mut Vec<VerificationKey>,
 }
 
 impl<'a> VerificationContext<'a> {
-    pub fn new(pub_keys: &'a [TPK], result_key: &'a mut Vec<TPK>) -> VerificationContext<'a> {
+    pub fn new(
+        verifier: &'a CommitVerifier,
+        result_keys: &'a mut Vec<VerificationKey>,
+    ) -> VerificationContext<'a> {
         VerificationContext {
-            pub_keys,
-            result_key,
+            verifier,
+            result_keys,
+        }
+    }
+
+    fn check_result(&self, result: &VerificationResult) -> openpgp::Result<VerificationKey> {
+        // Given a VerificationResult, which only says whether the signature checks out
+        // mathematically, we apply our policy.
+        match result {
+            VerificationResult::GoodChecksum(sig, tpk, key, binding, revocation) => {
+                check_key_validity(sig, tpk, key, *binding, revocation)?;
+                check_subkey_policy(&self.verifier.subkey_policies, tpk, key)?;
+                trace!("Verfified with key: {:#?}", tpk);
+
+                let mut verification_key = VerificationKey::new(tpk, key, *binding);
+                verification_key.owner = self
+                    .verifier
+                    .owners
+                    .get(&tpk.fingerprint().to_hex())
+                    .cloned();
+
+                Ok(verification_key)
+            }
+            VerificationResult::MissingKey(_) => {
+                Err(failure::err_msg("Missing key to verify signature"))
+            }
+            VerificationResult::BadChecksum(_) => Err(failure::err_msg("Bad signature")),
         }
     }
 }
 
 impl<'a> VerificationHelper for VerificationContext<'a> {
     fn get_public_keys(&mut self, _ids: &[openpgp::KeyID]) -> openpgp::Result<Vec<openpgp::TPK>> {
-        Ok(self.pub_keys.to_vec())
+        Ok(self.verifier.pub_keys.to_vec())
     }
 
     fn check(&mut self, structure: &MessageStructure) -> openpgp::Result<()> {
         // In this function, we implement our signature verification policy.
-        // It might seem odd that there is a mutable state variable `good` instread of an early
-        // return in case of GoodChecksum, but there might be multiple signatures, so we need to
-        // continue.
-        let mut good = false;
-        for (i, layer) in structure.iter().enumerate() {
-            match (i, layer) {
-                // First, we are interested in signatures over the
-                // data, i.e. level 0 signatures.
-                (0, MessageLayer::SignatureGroup { ref results }) => {
-                    // Finally, given a VerificationResult, which only says
-                    // whether the signature checks out mathematically, we apply our policy.
-                    match results.get(0) {
-                        Some(VerificationResult::GoodChecksum(_, tpk, _, _, _)) => {
-                            trace!("Verfified with key: {:#?}", tpk);
-                            self.result_key.push((*tpk).clone());
-                            good = true;
-                        }
-                        Some(VerificationResult::MissingKey(_)) => {
-                            return Err(failure::err_msg("Missing key to verify signature"))
-                        }
-                        Some(VerificationResult::BadChecksum(_)) => {
-                            return Err(failure::err_msg("Bad signature"))
+        // Every signature is checked, so all signers can be reported; the signature mode decides
+        // afterwards whether one or all of them have to be good.
+        let mut failures = Vec::new();
+        for layer in structure.iter() {
+            match layer {
+                MessageLayer::SignatureGroup { ref results } => {
+                    if results.is_empty() {
+                        failures.push(failure::err_msg("No signature"));
+                    }
+                    for result in results {
+                        match self.check_result(result) {
+                            Ok(key) => self.result_keys.push(key),
+                            Err(e) => {
+                                warn!("Signature rejected: {}", e);
+                                failures.push(e);
+                            }
                         }
-                        None => return Err(failure::err_msg("No signature")),
                     }
                 }
+                // Compression does not change what has been signed.
+                MessageLayer::Compression { .. } => {}
                 _ => return Err(failure::err_msg("Unexpected message structure")),
             }
         }
 
-        if good {
-            Ok(())
-        } else {
-            Err(failure::err_msg("Signature verification failed"))
+        let good = !self.result_keys.is_empty();
+        match self.verifier.signature_mode {
+            SignatureMode::Any if good => Ok(()),
+            SignatureMode::All if good && failures.is_empty() => Ok(()),
+            _ => Err(failures
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| failure::err_msg("Signature verification failed"))),
         }
     }
 }
 
-#[cfg(test)]
-mod tests {
+/// Checks that the signing (sub)key has been valid when the signature has been created and that
+/// neither the key nor its primary key has been revoked since.
+fn check_key_validity(
+    sig: &Signature,
+    tpk: &TPK,
+    key: &Key,
+    binding: Option<&Signature>,
+    revocation: &RevocationStatus,
+) -> openpgp::Result<()> {
+    let signature_creation_time = sig
+        .signature_creation_time()
+        .ok_or_else(|| failure::err_msg("Signature has no creation time"))?;
+
+    if signature_creation_time < *key.creation_time() {
+        return Err(ErrorKind::SignatureBeforeKeyCreation(key.fingerprint().to_string()).into());
+    }
+
+    if let RevocationStatus::Revoked(_) = tpk.revoked(None) {
+        return Err(ErrorKind::KeyRevoked(tpk.fingerprint().to_string()).into());
+    }
+    if let RevocationStatus::Revoked(_) = revocation {
+        return Err(ErrorKind::KeyRevoked(key.fingerprint().to_string()).into());
+    }
+
+    if let Some(selfsig) = tpk.primary_key_signature() {
+        if selfsig.key_expired_at(tpk.primary(), signature_creation_time) {
+            return Err(ErrorKind::KeyExpired(tpk.fingerprint().to_string()).into());
+        }
+    }
+    if let Some(binding) = binding {
+        if binding.key_expired_at(key, signature_creation_time) {
+            return Err(ErrorKind::KeyExpired(key.fingerprint().to_string()).into());
+        }
+    }
+
+    Ok(())
+}
+
+fn check_subkey_policy(
+    subkey_policies: &HashMap<String, SubkeyPolicy>,
+    tpk: &TPK,
+    key: &Key,
+) -> openpgp::Result<()> {
+    let policy = match subkey_policies.get(&tpk.fingerprint().to_hex()) {
+        Some(policy) => policy,
+        None => return Ok(()),
+    };
+
+    if policy.allows(&key.fingerprint().to_hex()) {
+        Ok(())
+    } else {
+        Err(ErrorKind::SubkeyNotAllowed(key.fingerprint().to_string()).into())
+    }
+}
+
+/// Checks that the key of a `pub_key` entry has the expected fingerprint if one is configured
+pub(crate) fn check_fingerprint(pub_key: &PubKey, tpk: &TPK) -> Result<()> {
+    let expected = match pub_key.fingerprint {
+        Some(ref x) => normalize_fingerprint(x),
+        None => return Ok(()),
+    };
+    let actual = tpk.fingerprint().to_hex();
+    if expected != actual {
+        return Err(Error::from(ErrorKind::FingerprintMismatch(
+            pub_key.name.clone(),
+            expected,
+            actual,
+        )));
+    }
+
+    Ok(())
+}
+
+fn check_fingerprints(pub_keys: &[PubKey], tpks: &[TPK]) -> Result<()> {
+    pub_keys
+        .iter()
+        .zip(tpks.iter())
+        .map(|(pub_key, tpk)| check_fingerprint(pub_key, tpk))
+        .collect()
+}
+
+/// Strips white space and upper cases a fingerprint so it can be compared to `Fingerprint::to_hex`
+pub(crate) fn normalize_fingerprint(finger_print: &str) -> String {
+    finger_print
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
-    use crate::{github::commits::*, utils::test};
+    use crate::{
+        github::{commits::*, tags::*},
+        source::FixtureSource,
+        utils::test::{self, FakeGitHub},
+    };
 
     use clams::config::Config;
     use log::debug;
     use spectral::prelude::*;
     use std::convert::TryInto;
 
+    fn lukas_pustina_signing_key() -> SigningKey {
+        SigningKey {
+            finger_print: "4161 0C26 6853 C6D5 7FE1  9760 ECFB 5D03 2D82 9112".to_string(),
+            key_id: "ECFB 5D03 2D82 9112".to_string(),
+            capabilities: KeyCapabilities {
+                certify: true,
+                sign: true,
+                ..Default::default()
+            },
+            creation_time: Some(Utc.timestamp(1_525_434_554, 0)),
+        }
+    }
+
+    fn lukas_pustina_commit() -> Commit {
+        Commit {
+            sha: Sha::new("72cf6df73dbd1a13ac096319e00cb63e0f2846c7".to_string()),
+            commit: CommitDetail {
+                author: PersonDetails {
+                    name: "Lukas Pustina".to_string(),
+                    email: "lukas@pustina.de".to_string(),
+                    date: "2019-06-25T08:37:21+00:00".parse().unwrap(),
+                },
+                committer: PersonDetails {
+                    name: "Lukas Pustina".to_string(),
+                    email: "lukas@pustina.de".to_string(),
+                    date: "2019-06-25T10:27:51+00:00".parse().unwrap(),
+                },
+                message: "Add travis config".to_string(),
+                verification: Verification {
+                    verified: true,
+                    reason: Reason::Valid,
+                    signature: Some(
+                        "-----BEGIN PGP SIGNATURE-----\nComment: GPGTools - http://gpgtools.org\n\niQIzBAABCAAdFiEEQWEMJmhTxtV/4Zdg7PtdAy2CkRIFAl0R9ysACgkQ7PtdAy2C\nkRKdzQ//cDyI9JX93+c/893g8TDLAIYyoLqbBL700wSjXEMO7WLkXYOJtFMO8jlA\nKjecVo+v2b0Eq7t8xAWrGPXGYyCdrbqIJg6eQRWaSkrS9PwIwrWcraPcduvWPHk2\n7bxCykiuXe+R01+00zMICZY0P0WnvuaoZo4kL7s6etgGY3sQff+fXUI8sGg8KN1Y\nav+t+bGKJnONa+BomLuIMNUuh29DaDytB2N/xuvhE3Pj/WEiYDDlhh3Wka7nTmsM\nxMhaK8+Jjjsv9rhzW63yPKrc4tHLUHLjvs3f8bPZbSgZqvS6YpY2/Nm7l20N4HBy\nxwUQ1Ee6YaE6GS6InXUEcoLZu0DxvOP476r1VZ/l6t2YTkcvYp7yi1zHIF3AuVQs\nA9gb4gK0aI7uyKrbT86XJCKAeu1CuOIpp6fGwD39maD1LgB6tYoIiFj8kOHxM0cp\nlCRdM+rF5Sgmr5UYaaEpFM6uWvQ7O7SJWn4j1FwQN6Ul++1CUQjoq8XczXQhZ9e0\n7bzOF+KlahNUWElxCiatiBsKGAhZEVzHp4LALJQE5s7X/Ea1fqkF+c87+0FQXGUT\nV5YwhHK6LTutfgxVqyCUlK3pshFxyEkHb2zKQsoIr02KWbZH8uTzs56xNHCJ6mI/\nANFLOdKLkRWNBARGMAuiM2hTyEUUOL0F9uSQMMzRQTlrkL3lWRA=\n=ivRW\n-----END PGP SIGNATURE-----".to_string(),
+                    ),
+                    payload: Some(
+                        "tree ea7435f6d72196332c436474a42aea8ce030d424\nparent c255ad2347d00cae3dd2d7a21e1357e50413fc4f\nauthor Lukas Pustina <lukas@pustina.de> 1561451841 +0200\ncommitter Lukas Pustina <lukas@pustina.de> 1561458471 +0200\n\nAdd travis config\n".to_string(),
+                    ),
+                },
+            },
+            parents: vec![Parent {
+                sha: Sha::new("c255ad2347d00cae3dd2d7a21e1357e50413fc4f"),
+            }],
+        }
+    }
+
+    /// A commit signed by the subkey of `tests/subkey.pub` which stands in for GitHub's web-flow
+    /// key in tests
+    fn web_flow_commit(parents: &[&str]) -> Commit {
+        Commit {
+            sha: Sha::new("5b1c1fa6dd0f4f3a9ab3a5cb9ef4c1f1e1ee9ff4"),
+            commit: CommitDetail {
+                author: PersonDetails {
+                    name: "Lukas Pustina".to_string(),
+                    email: "lukas@pustina.de".to_string(),
+                    date: "2019-06-26T08:00:00+00:00".parse().unwrap(),
+                },
+                committer: PersonDetails {
+                    name: "GitHub".to_string(),
+                    email: "noreply@github.com".to_string(),
+                    date: "2019-06-26T08:00:00+00:00".parse().unwrap(),
+                },
+                message: "Merge pull request #1".to_string(),
+                verification: Verification {
+                    verified: true,
+                    reason: Reason::Valid,
+                    signature: Some(include_str!("../tests/subkey.sig").to_string()),
+                    payload: Some(include_str!("../tests/test-commit.txt").to_string()),
+                },
+            },
+            parents: parents
+                .iter()
+                .map(|x| Parent { sha: Sha::new(*x) })
+                .collect(),
+        }
+    }
+
     #[test]
     fn load_key_from_file() {
         test::init();
@@ -219,6 +1313,77 @@ mod tests {
             .is_ok();
     }
 
+    #[test]
+    fn keyring_version_depends_on_keys_only() {
+        test::init();
+
+        let cv = CommitVerifier::from_key_files(&["tests/second.pub", "tests/subkey.pub"])
+            .expect("failed to load keys");
+        let reordered = CommitVerifier::from_key_files(&["tests/subkey.pub", "tests/second.pub"])
+            .expect("failed to load keys");
+        let fewer = CommitVerifier::from_key_file("tests/second.pub").expect("failed to load key");
+
+        asserting("fingerprints are sorted")
+            .that(&cv.finger_prints())
+            .is_equal_to(&vec![
+                "78B3C7EC5A090700D587B4917715E9F9942C03D0".to_string(),
+                "81E95AD23D9F4C60599BD3D161B5D0C9A727049E".to_string(),
+            ]);
+        asserting("order of keys does not matter")
+            .that(&cv.keyring_version())
+            .is_equal_to(&reordered.keyring_version());
+        asserting("removing a key changes the version")
+            .that(&cv.keyring_version() != fewer.keyring_version())
+            .is_true();
+    }
+
+    #[test]
+    fn sign_and_verify_detached() {
+        test::init();
+
+        let signer =
+            DetachedSigner::from_key_file("tests/checkpoint.sec").expect("failed to load key");
+        let verifier =
+            CommitVerifier::from_key_file("tests/checkpoint.pub").expect("failed to load key");
+
+        let signature = signer.sign(b"checkpoint").expect("failed to sign");
+        let keys = verifier.verify_message(b"checkpoint", signature.as_bytes());
+
+        asserting("signature is verified with the public key")
+            .that(&keys)
+            .is_ok()
+            .map(|x| &x[0].finger_print)
+            .is_equal_to(&"B4DB09760D0E5468D6AC15093F2B285486570F06".to_string());
+        asserting("other message is not verified")
+            .that(&verifier.verify_message(b"tampered", signature.as_bytes()))
+            .is_err();
+    }
+
+    #[test]
+    fn verification_version_depends_on_policies() {
+        test::init();
+
+        let cv = CommitVerifier::from_key_file("tests/subkey.pub").expect("failed to load key");
+        let version = cv
+            .verification_version()
+            .expect("failed to compute version");
+        let keyring_version = cv.keyring_version();
+        let cv = cv.with_subkey_policy(
+            "78B3C7EC5A090700D587B4917715E9F9942C03D0",
+            SubkeyPolicy {
+                pinned: Vec::new(),
+                blocked: vec!["78B3C7EC5A090700D587B4917715E9F9942C03D0".to_string()],
+            },
+        );
+
+        asserting("keyring version is unchanged")
+            .that(&cv.keyring_version())
+            .is_equal_to(&keyring_version);
+        asserting("blocking a key changes the version")
+            .that(&cv.verification_version().ok() != Some(version))
+            .is_true();
+    }
+
     #[test]
     fn load_key_from_bytes() {
         test::init();
@@ -245,6 +1410,44 @@ mod tests {
             .is_ok();
     }
 
+    #[test]
+    fn from_config_with_pinned_fingerprint() {
+        test::init();
+
+        let mut config = GitHubWatchTowerConfig::from_file("tests/config.toml")
+            .expect("failed to load config file");
+        config.pub_keys[0].fingerprint =
+            Some("4161 0C26 6853 C6D5 7FE1  9760 ECFB 5D03 2D82 9112".to_string());
+
+        let cv: Result<CommitVerifier> = (&config).try_into();
+
+        asserting("Created CommitVerifier from Config with matching fingerprint")
+            .that(&cv)
+            .is_ok();
+    }
+
+    #[test]
+    fn from_config_with_wrong_fingerprint_failed() {
+        test::init();
+
+        let mut config = GitHubWatchTowerConfig::from_file("tests/config.toml")
+            .expect("failed to load config file");
+        config.pub_keys[0].fingerprint =
+            Some("81E9 5AD2 3D9F 4C60 599B  D3D1 61B5 D0C9 A727 049E".to_string());
+
+        let cv: Result<CommitVerifier> = (&config).try_into();
+
+        asserting("Refused to create CommitVerifier from Config with wrong fingerprint")
+            .that(&cv)
+            .is_err()
+            .map(|x| x.kind())
+            .is_equal_to(&ErrorKind::FingerprintMismatch(
+                "Lukas Pustina".to_string(),
+                "81E95AD23D9F4C60599BD3D161B5D0C9A727049E".to_string(),
+                "41610C266853C6D57FE19760ECFB5D032D829112".to_string(),
+            ));
+    }
+
     mod internal {
         use super::*;
 
@@ -277,6 +1480,7 @@ committer Lukas Pustina <lukas@pustina.de> 1561466095 +0200
 Github: add list endpoints
 "#;
             let expected_key = VerificationKey {
+                kind: KeyKind::OpenPgp,
                 finger_print: "4161 0C26 6853 C6D5 7FE1  9760 ECFB 5D03 2D82 9112".to_string(),
                 key_id: "ECFB 5D03 2D82 9112".to_string(),
                 e_mails: vec![
@@ -284,17 +1488,19 @@ Github: add list endpoints
                     "lukas.pustina@codecentric.de".to_string(),
                     "lukas@pustina.de".to_string(),
                 ],
+                signing_key: lukas_pustina_signing_key(),
+                owner: None,
             };
 
             let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
                 .expect("failed to load public key");
-            let res = super::verify_message(&cv.pub_keys, message.as_ref(), signature.as_ref());
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
 
             debug!("Res: {:#?}", res);
             asserting("Signature is valid")
                 .that(&res)
                 .is_ok()
-                .is_equal_to(&expected_key);
+                .is_equal_to(&vec![expected_key]);
         }
 
         #[test]
@@ -326,6 +1532,7 @@ committer Lukas Pustina <lukas@pustina.de> 1561466095 +0200
 Github: add list endpoints
 "#;
             let expected_key = VerificationKey {
+                kind: KeyKind::OpenPgp,
                 finger_print: "4161 0C26 6853 C6D5 7FE1  9760 ECFB 5D03 2D82 9112".to_string(),
                 key_id: "ECFB 5D03 2D82 9112".to_string(),
                 e_mails: vec![
@@ -333,6 +1540,8 @@ Github: add list endpoints
                     "lukas.pustina@codecentric.de".to_string(),
                     "lukas@pustina.de".to_string(),
                 ],
+                signing_key: lukas_pustina_signing_key(),
+                owner: None,
             };
 
             let cv = CommitVerifier::from_key_files(&[
@@ -340,13 +1549,13 @@ Github: add list endpoints
                 "tests/lukas.pustina-invalid.pub",
             ])
             .expect("failed to load public key");
-            let res = super::verify_message(&cv.pub_keys, message.as_ref(), signature.as_ref());
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
 
             debug!("Res: {:#?}", res);
             asserting("Signature is valid")
                 .that(&res)
                 .is_ok()
-                .is_equal_to(&expected_key);
+                .is_equal_to(&vec![expected_key]);
         }
 
         #[test]
@@ -365,7 +1574,7 @@ Github: add list endpoints
             let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
                 .expect("failed to load public key");
 
-            let res = super::verify_message(&cv.pub_keys, message.as_ref(), signature.as_ref());
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
 
             debug!("Res: {:#?}", res);
             asserting("Signature is valid").that(&res).is_ok();
@@ -405,7 +1614,7 @@ Github: add list endpoints
             let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
                 .expect("failed to load public key");
 
-            let res = super::verify_message(&cv.pub_keys, message.as_ref(), signature.as_ref());
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
 
             debug!("Res: {:#?}", res);
             asserting("Signature is not verified").that(&res).is_err();
@@ -444,45 +1653,302 @@ Github: add list endpoints
             let cv = CommitVerifier::from_key_file("tests/lukas.pustina-invalid.pub")
                 .expect("failed to load public key");
 
-            let res = super::verify_message(&cv.pub_keys, message.as_ref(), signature.as_ref());
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
 
             debug!("Res: {:#?}", res);
             asserting("Signature is not verified").that(&res).is_err();
             res.unwrap();
         }
+
+        #[test]
+        fn verify_message_failed_key_expired() {
+            test::init();
+
+            let signature = include_str!("../tests/expired.sig");
+            let message = include_str!("../tests/test-commit.txt");
+            let cv = CommitVerifier::from_key_file("tests/expired.pub")
+                .expect("failed to load public key");
+
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            debug!("Res: {:#?}", res);
+            asserting("Signature by expired key is not verified")
+                .that(&res.map_err(|e| e.kind().clone()))
+                .is_err()
+                .is_equal_to(ErrorKind::KeyExpired(
+                    "EF1A BB05 ADAD BA3F 77AB  C2B2 D373 AB84 0368 E973".to_string(),
+                ));
+        }
+
+        #[test]
+        fn verify_message_failed_key_revoked() {
+            test::init();
+
+            let signature = include_str!("../tests/revoked.sig");
+            let message = include_str!("../tests/test-commit.txt");
+            let cv = CommitVerifier::from_key_file("tests/revoked.pub")
+                .expect("failed to load public key");
+
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            debug!("Res: {:#?}", res);
+            asserting("Signature by revoked key is not verified")
+                .that(&res.map_err(|e| e.kind().clone()))
+                .is_err()
+                .is_equal_to(ErrorKind::KeyRevoked(
+                    "C8B8 235A FC89 29E4 187E  62D2 2FB6 FB4F B5A4 4693".to_string(),
+                ));
+        }
+
+        #[test]
+        fn verify_message_failed_signature_before_key_creation() {
+            test::init();
+
+            let signature = include_str!("../tests/future.sig");
+            let message = include_str!("../tests/test-commit.txt");
+            let cv = CommitVerifier::from_key_file("tests/future.pub")
+                .expect("failed to load public key");
+
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            debug!("Res: {:#?}", res);
+            asserting("Signature older than its key is not verified")
+                .that(&res.map_err(|e| e.kind().clone()))
+                .is_err()
+                .is_equal_to(ErrorKind::SignatureBeforeKeyCreation(
+                    "59F6 D75C 1813 2FF7 6124  5858 E996 AB24 D54B 6C15".to_string(),
+                ));
+        }
+
+        #[test]
+        fn verify_message_with_subkey_okay() {
+            test::init();
+
+            let signature = include_str!("../tests/subkey.sig");
+            let message = include_str!("../tests/test-commit.txt");
+            let expected_signing_key = SigningKey {
+                finger_print: "77FF 72E5 9B33 9D77 BA43  A541 F115 9818 6D81 E3CF".to_string(),
+                key_id: "F115 9818 6D81 E3CF".to_string(),
+                capabilities: KeyCapabilities {
+                    sign: true,
+                    ..Default::default()
+                },
+                creation_time: Some(Utc.timestamp(1_546_387_200, 0)),
+            };
+
+            let cv = CommitVerifier::from_key_file("tests/subkey.pub")
+                .expect("failed to load public key");
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            debug!("Res: {:#?}", res);
+            asserting("Signature is valid and reports signing subkey")
+                .that(&res)
+                .is_ok()
+                .map(|x| &x[0].signing_key)
+                .is_equal_to(&expected_signing_key);
+            asserting("Signature reports primary key")
+                .that(&res)
+                .is_ok()
+                .map(|x| &x[0].finger_print)
+                .is_equal_to(&"78B3 C7EC 5A09 0700 D587  B491 7715 E9F9 942C 03D0".to_string());
+        }
+
+        #[test]
+        fn verify_message_with_pinned_subkey_okay() {
+            test::init();
+
+            let signature = include_str!("../tests/subkey.sig");
+            let message = include_str!("../tests/test-commit.txt");
+            let policy = SubkeyPolicy {
+                pinned: vec!["77ff72e59b339d77ba43a541f11598186d81e3cf".to_string()],
+                blocked: Vec::new(),
+            };
+
+            let cv = CommitVerifier::from_key_file("tests/subkey.pub")
+                .expect("failed to load public key")
+                .with_subkey_policy("78B3 C7EC 5A09 0700 D587  B491 7715 E9F9 942C 03D0", policy);
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            debug!("Res: {:#?}", res);
+            asserting("Signature by pinned subkey is valid")
+                .that(&res)
+                .is_ok();
+        }
+
+        #[test]
+        fn verify_message_failed_blocked_subkey() {
+            test::init();
+
+            let signature = include_str!("../tests/subkey.sig");
+            let message = include_str!("../tests/test-commit.txt");
+            let policy = SubkeyPolicy {
+                pinned: Vec::new(),
+                blocked: vec!["77FF 72E5 9B33 9D77 BA43  A541 F115 9818 6D81 E3CF".to_string()],
+            };
+
+            let cv = CommitVerifier::from_key_file("tests/subkey.pub")
+                .expect("failed to load public key")
+                .with_subkey_policy("78B3C7EC5A090700D587B4917715E9F9942C03D0", policy);
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            debug!("Res: {:#?}", res);
+            asserting("Signature by blocked subkey is not verified")
+                .that(&res.map_err(|e| e.kind().clone()))
+                .is_err()
+                .is_equal_to(ErrorKind::SubkeyNotAllowed(
+                    "77FF 72E5 9B33 9D77 BA43  A541 F115 9818 6D81 E3CF".to_string(),
+                ));
+        }
+
+        #[test]
+        fn verify_message_failed_subkey_not_pinned() {
+            test::init();
+
+            let signature = include_str!("../tests/subkey.sig");
+            let message = include_str!("../tests/test-commit.txt");
+            let policy = SubkeyPolicy {
+                pinned: vec!["78B3C7EC5A090700D587B4917715E9F9942C03D0".to_string()],
+                blocked: Vec::new(),
+            };
+
+            let cv = CommitVerifier::from_key_file("tests/subkey.pub")
+                .expect("failed to load public key")
+                .with_subkey_policy("78B3C7EC5A090700D587B4917715E9F9942C03D0", policy);
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            debug!("Res: {:#?}", res);
+            asserting("Signature by subkey that is not pinned is not verified")
+                .that(&res)
+                .is_err();
+        }
+
+        #[test]
+        fn verify_message_with_multiple_signatures_okay() {
+            test::init();
+
+            let signature = include_str!("../tests/multiple.sig");
+            let message = include_str!("../tests/test-commit.txt");
+
+            let cv = CommitVerifier::from_key_files(&["tests/subkey.pub", "tests/second.pub"])
+                .expect("failed to load public key")
+                .with_signature_mode(SignatureMode::All);
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            debug!("Res: {:#?}", res);
+            asserting("All signers are reported")
+                .that(&res)
+                .is_ok()
+                .has_length(2);
+        }
+
+        #[test]
+        fn verify_message_with_multiple_signatures_any_okay() {
+            test::init();
+
+            let signature = include_str!("../tests/multiple.sig");
+            let message = include_str!("../tests/test-commit.txt");
+
+            let cv = CommitVerifier::from_key_file("tests/subkey.pub")
+                .expect("failed to load public key")
+                .with_signature_mode(SignatureMode::Any);
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            debug!("Res: {:#?}", res);
+            asserting("Only trusted signer is reported")
+                .that(&res)
+                .is_ok()
+                .has_length(1);
+        }
+
+        #[test]
+        #[should_panic(expected = r#"Missing key to verify signature"#)]
+        fn verify_message_with_multiple_signatures_all_failed() {
+            test::init();
+
+            let signature = include_str!("../tests/multiple.sig");
+            let message = include_str!("../tests/test-commit.txt");
+
+            let cv = CommitVerifier::from_key_file("tests/subkey.pub")
+                .expect("failed to load public key")
+                .with_signature_mode(SignatureMode::All);
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            debug!("Res: {:#?}", res);
+            asserting("Signature by untrusted key fails verification")
+                .that(&res)
+                .is_err();
+            res.unwrap();
+        }
+
+        #[test]
+        fn verify_message_with_ssh_signature_okay() {
+            test::init();
+
+            let signature = include_str!("../tests/ssh-ed25519.sig");
+            let message = include_str!("../tests/test-commit.txt");
+            let ssh_verifier =
+                SshVerifier::from_allowed_signers(include_str!("../tests/allowed_signers"))
+                    .expect("failed to load allowed signers");
+
+            let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
+                .expect("failed to load public key")
+                .with_ssh_verifier(ssh_verifier);
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            debug!("Res: {:#?}", res);
+            asserting("SSH signature is dispatched to SSH verifier")
+                .that(&res)
+                .is_ok()
+                .map(|x| &x[0].kind)
+                .is_equal_to(&KeyKind::Ssh);
+        }
+
+        #[test]
+        fn verify_message_with_x509_signature_okay() {
+            test::init();
+
+            let signature = include_str!("../tests/x509.sig");
+            let message = include_str!("../tests/test-commit.txt");
+            let x509_verifier = X509Verifier::from_pem_files(&["tests/x509-root.pem"])
+                .expect("failed to load root CA");
+
+            let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
+                .expect("failed to load public key")
+                .with_x509_verifier(x509_verifier);
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            debug!("Res: {:#?}", res);
+            asserting("X.509 signature is dispatched to X.509 verifier")
+                .that(&res)
+                .is_ok()
+                .map(|x| &x[0].kind)
+                .is_equal_to(&KeyKind::X509);
+        }
+
+        #[test]
+        fn verify_message_with_x509_signature_without_root_cas_failed() {
+            test::init();
+
+            let signature = include_str!("../tests/x509.sig");
+            let message = include_str!("../tests/test-commit.txt");
+
+            let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
+                .expect("failed to load public key");
+            let res = cv.verify_message(message.as_ref(), signature.as_ref());
+
+            asserting("X.509 signature is rejected without root CAs")
+                .that(&res)
+                .is_err();
+        }
     }
 
     #[test]
     fn verify_commit() {
         test::init();
 
-        let commit = Commit {
-            sha: Sha::new("72cf6df73dbd1a13ac096319e00cb63e0f2846c7".to_string()),
-            commit: CommitDetail {
-                author: PersonDetails {
-                    name: "Lukas Pustina".to_string(),
-                    email: "lukas@pustina.de".to_string(),
-                    date: "2019-06-25T08:37:21+00:00".parse().unwrap(),
-                },
-                committer: PersonDetails {
-                    name: "Lukas Pustina".to_string(),
-                    email: "lukas@pustina.de".to_string(),
-                    date: "2019-06-25T10:27:51+00:00".parse().unwrap(),
-                },
-                message: "Add travis config".to_string(),
-                verification: Verification {
-                    verified: true,
-                    reason: Reason::Valid,
-                    signature: Some(
-                        "-----BEGIN PGP SIGNATURE-----\nComment: GPGTools - http://gpgtools.org\n\niQIzBAABCAAdFiEEQWEMJmhTxtV/4Zdg7PtdAy2CkRIFAl0R9ysACgkQ7PtdAy2C\nkRKdzQ//cDyI9JX93+c/893g8TDLAIYyoLqbBL700wSjXEMO7WLkXYOJtFMO8jlA\nKjecVo+v2b0Eq7t8xAWrGPXGYyCdrbqIJg6eQRWaSkrS9PwIwrWcraPcduvWPHk2\n7bxCykiuXe+R01+00zMICZY0P0WnvuaoZo4kL7s6etgGY3sQff+fXUI8sGg8KN1Y\nav+t+bGKJnONa+BomLuIMNUuh29DaDytB2N/xuvhE3Pj/WEiYDDlhh3Wka7nTmsM\nxMhaK8+Jjjsv9rhzW63yPKrc4tHLUHLjvs3f8bPZbSgZqvS6YpY2/Nm7l20N4HBy\nxwUQ1Ee6YaE6GS6InXUEcoLZu0DxvOP476r1VZ/l6t2YTkcvYp7yi1zHIF3AuVQs\nA9gb4gK0aI7uyKrbT86XJCKAeu1CuOIpp6fGwD39maD1LgB6tYoIiFj8kOHxM0cp\nlCRdM+rF5Sgmr5UYaaEpFM6uWvQ7O7SJWn4j1FwQN6Ul++1CUQjoq8XczXQhZ9e0\n7bzOF+KlahNUWElxCiatiBsKGAhZEVzHp4LALJQE5s7X/Ea1fqkF+c87+0FQXGUT\nV5YwhHK6LTutfgxVqyCUlK3pshFxyEkHb2zKQsoIr02KWbZH8uTzs56xNHCJ6mI/\nANFLOdKLkRWNBARGMAuiM2hTyEUUOL0F9uSQMMzRQTlrkL3lWRA=\n=ivRW\n-----END PGP SIGNATURE-----".to_string(),
-                    ),
-                    payload: Some(
-                        "tree ea7435f6d72196332c436474a42aea8ce030d424\nparent c255ad2347d00cae3dd2d7a21e1357e50413fc4f\nauthor Lukas Pustina <lukas@pustina.de> 1561451841 +0200\ncommitter Lukas Pustina <lukas@pustina.de> 1561458471 +0200\n\nAdd travis config\n".to_string(),
-                    ),
-                },
-            },
-        };
+        let commit = lukas_pustina_commit();
         let expected_key = VerificationKey {
+            kind: KeyKind::OpenPgp,
             finger_print: "4161 0C26 6853 C6D5 7FE1  9760 ECFB 5D03 2D82 9112".to_string(),
             key_id: "ECFB 5D03 2D82 9112".to_string(),
             e_mails: vec![
@@ -490,6 +1956,8 @@ Github: add list endpoints
                 "lukas.pustina@codecentric.de".to_string(),
                 "lukas@pustina.de".to_string(),
             ],
+            signing_key: lukas_pustina_signing_key(),
+            owner: None,
         };
 
         let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
@@ -502,4 +1970,398 @@ Github: add list endpoints
             .is_ok()
             .is_equal_to(&expected_key);
     }
+
+    /// A commit like `lukas_pustina_commit`, signed by the same key, but with another sha
+    fn lukas_pustina_commit_with_sha(sha: &str) -> Commit {
+        let mut commit = lukas_pustina_commit();
+        commit.sha = Sha::new(sha);
+        commit
+    }
+
+    fn web_flow_commit_verifier() -> CommitVerifier {
+        let web_flow_verifier =
+            CommitVerifier::from_key_file("tests/subkey.pub").expect("failed to load public key");
+
+        CommitVerifier::from_key_file("tests/lukas.pustina.pub")
+            .expect("failed to load public key")
+            .with_web_flow_verifier(web_flow_verifier)
+    }
+
+    #[test]
+    fn verify_commits_with_web_flow_okay() {
+        test::init();
+
+        let commits = vec![
+            web_flow_commit(&[
+                "72cf6df73dbd1a13ac096319e00cb63e0f2846c7",
+                "c255ad2347d00cae3dd2d7a21e1357e50413fc4f",
+            ]),
+            lukas_pustina_commit(),
+            lukas_pustina_commit_with_sha("c255ad2347d00cae3dd2d7a21e1357e50413fc4f"),
+        ];
+        let res = web_flow_commit_verifier().verify_commits(&commits);
+
+        debug!("Res: {:#?}", res);
+        asserting("Web-flow signed merge with verified parents is valid")
+            .that(&res[0])
+            .is_ok();
+        asserting("Parent is valid").that(&res[1]).is_ok();
+    }
+
+    #[test]
+    fn verify_commits_with_web_flow_single_parent_failed() {
+        test::init();
+
+        let commits = vec![
+            web_flow_commit(&["72cf6df73dbd1a13ac096319e00cb63e0f2846c7"]),
+            lukas_pustina_commit(),
+        ];
+        let res = web_flow_commit_verifier().verify_commits(&commits);
+
+        debug!("Res: {:#?}", res);
+        asserting("Web-flow signed commit with a single parent may introduce unverified content")
+            .that(&res[0])
+            .is_err();
+    }
+
+    #[test]
+    fn verify_commits_with_web_flow_merge_of_merge_okay() {
+        test::init();
+
+        let mut merge_of_merge = web_flow_commit(&[
+            "5b1c1fa6dd0f4f3a9ab3a5cb9ef4c1f1e1ee9ff4",
+            "c255ad2347d00cae3dd2d7a21e1357e50413fc4f",
+        ]);
+        merge_of_merge.sha = Sha::new("e4d6e1c0d8a9b1d0a1a1e2b5c4b3f6e7d8c9b0a1");
+        let commits = vec![
+            merge_of_merge,
+            web_flow_commit(&[
+                "72cf6df73dbd1a13ac096319e00cb63e0f2846c7",
+                "c255ad2347d00cae3dd2d7a21e1357e50413fc4f",
+            ]),
+            lukas_pustina_commit(),
+            lukas_pustina_commit_with_sha("c255ad2347d00cae3dd2d7a21e1357e50413fc4f"),
+        ];
+        let res = web_flow_commit_verifier().verify_commits(&commits);
+
+        debug!("Res: {:#?}", res);
+        asserting("Web-flow signed merge of a web-flow signed merge is valid")
+            .that(&res[0])
+            .is_ok();
+        asserting("Web-flow signed merge is valid")
+            .that(&res[1])
+            .is_ok();
+    }
+
+    #[test]
+    fn verify_signed_commits_with_web_flow_resolves_parents() {
+        test::init();
+
+        let commits: Vec<_> = vec![
+            web_flow_commit(&[
+                "72cf6df73dbd1a13ac096319e00cb63e0f2846c7",
+                "c255ad2347d00cae3dd2d7a21e1357e50413fc4f",
+            ]),
+            lukas_pustina_commit(),
+        ]
+        .iter()
+        .map(SignedCommit::from)
+        .collect();
+        let source = FixtureSource::new(vec![SignedCommit::from(&lukas_pustina_commit_with_sha(
+            "c255ad2347d00cae3dd2d7a21e1357e50413fc4f",
+        ))]);
+        let cv = web_flow_commit_verifier();
+
+        asserting("Parent outside of the batch is rejected without source")
+            .that(&cv.verify_signed_commits(&commits, None)[0])
+            .is_err();
+        asserting("Parent outside of the batch is resolved through source")
+            .that(&cv.verify_signed_commits(&commits, Some(&source))[0])
+            .is_ok();
+    }
+
+    #[test]
+    fn verify_commits_with_web_flow_unverified_parent_failed() {
+        test::init();
+
+        let commits = vec![
+            web_flow_commit(&[
+                "72cf6df73dbd1a13ac096319e00cb63e0f2846c7",
+                "0000000000000000000000000000000000000000",
+            ]),
+            lukas_pustina_commit(),
+        ];
+        let res = web_flow_commit_verifier().verify_commits(&commits);
+
+        debug!("Res: {:#?}", res);
+        asserting("Web-flow signed commit with unverified parent is invalid")
+            .that(&res[0])
+            .is_err();
+    }
+
+    #[test]
+    fn verify_commits_without_web_flow_failed() {
+        test::init();
+
+        let commits = vec![
+            web_flow_commit(&["72cf6df73dbd1a13ac096319e00cb63e0f2846c7"]),
+            lukas_pustina_commit(),
+        ];
+
+        let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
+            .expect("failed to load public key");
+        let res = cv.verify_commits(&commits);
+
+        asserting("Web-flow signed commit is invalid if web-flow is not trusted")
+            .that(&res[0])
+            .is_err();
+    }
+
+    #[test]
+    fn with_web_flow_key_unknown_finger_print_failed() {
+        test::init();
+
+        let tpk = TPK::from_file("tests/subkey.pub").expect("failed to load public key");
+        let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
+            .expect("failed to load public key")
+            .with_web_flow_key(tpk);
+
+        asserting("Key with unknown fingerprint is not accepted as web-flow key")
+            .that(&cv)
+            .is_err();
+    }
+
+    fn merge_commit() -> Commit {
+        let mut commit = lukas_pustina_commit();
+        commit.parents = vec![
+            Parent {
+                sha: Sha::new("c255ad2347d00cae3dd2d7a21e1357e50413fc4f"),
+            },
+            Parent {
+                sha: Sha::new("5b1c1fa6dd0f4f3a9ab3a5cb9ef4c1f1e1ee9ff4"),
+            },
+        ];
+        commit
+    }
+
+    #[test]
+    fn verify_merge_introduced_commits_okay() {
+        test::init();
+
+        let github = FakeGitHub::new().with_comparison(|| vec![lukas_pustina_commit()]);
+        let repository = Repository::new("lukaspustina", "github-watchtower");
+
+        let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
+            .expect("failed to load public key")
+            .with_merge_mode(MergeMode::IntroducedCommits);
+        let res = cv.verify_merge(&github, &repository, &merge_commit());
+
+        debug!("Res: {:#?}", res);
+        asserting("Merge commit and introduced commits are valid")
+            .that(&res)
+            .is_ok()
+            .matches(|x| x.is_verified() && x.introduced.len() == 1);
+    }
+
+    #[test]
+    fn verify_merge_introduced_commits_failed() {
+        test::init();
+
+        let github = FakeGitHub::new().with_comparison(|| {
+            vec![
+                lukas_pustina_commit(),
+                web_flow_commit(&["72cf6df73dbd1a13ac096319e00cb63e0f2846c7"]),
+            ]
+        });
+        let repository = Repository::new("lukaspustina", "github-watchtower");
+
+        let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
+            .expect("failed to load public key")
+            .with_merge_mode(MergeMode::IntroducedCommits);
+        let res = cv.verify_merge(&github, &repository, &merge_commit());
+
+        debug!("Res: {:#?}", res);
+        asserting("Merge commit introducing an unverified commit is invalid")
+            .that(&res)
+            .is_ok()
+            .matches(|x| x.keys.is_ok() && !x.is_verified());
+    }
+
+    #[test]
+    fn verify_merge_web_flow_introduced_commits_okay() {
+        test::init();
+
+        let github = FakeGitHub::new()
+            .with_comparison(|| vec![lukas_pustina_commit()])
+            .with_commits(|| {
+                vec![lukas_pustina_commit_with_sha(
+                    "c255ad2347d00cae3dd2d7a21e1357e50413fc4f",
+                )]
+            });
+        let repository = Repository::new("lukaspustina", "github-watchtower");
+        let merge = web_flow_commit(&[
+            "c255ad2347d00cae3dd2d7a21e1357e50413fc4f",
+            "72cf6df73dbd1a13ac096319e00cb63e0f2846c7",
+        ]);
+
+        let cv = web_flow_commit_verifier().with_merge_mode(MergeMode::IntroducedCommits);
+        let res = cv.verify_merge(&github, &repository, &merge);
+
+        debug!("Res: {:#?}", res);
+        asserting("Web-flow signed merge and introduced commits are valid")
+            .that(&res)
+            .is_ok()
+            .matches(|x| x.is_verified() && x.introduced.len() == 1);
+    }
+
+    #[test]
+    fn verify_merge_commit_only() {
+        test::init();
+
+        let github = FakeGitHub::new().with_comparison(|| vec![web_flow_commit(&[])]);
+        let repository = Repository::new("lukaspustina", "github-watchtower");
+
+        let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
+            .expect("failed to load public key");
+        let res = cv.verify_merge(&github, &repository, &merge_commit());
+
+        asserting("Only merge commit is verified")
+            .that(&res)
+            .is_ok()
+            .matches(|x| x.is_verified() && x.introduced.is_empty());
+    }
+
+    fn tag(signature: &str) -> Tag {
+        Tag {
+            sha: Sha::new("940bd336248efae0f9ee5bc7b2d5c985887b16ac"),
+            tag: "v0.0.1".to_string(),
+            message: "Release v0.0.1\n".to_string(),
+            tagger: PersonDetails {
+                name: "Second Test".to_string(),
+                email: "second@example.com".to_string(),
+                date: "2019-06-25T12:53:20+02:00".parse().unwrap(),
+            },
+            object: GitObject {
+                object_type: ObjectType::Commit,
+                sha: Sha::new("72cf6df73dbd1a13ac096319e00cb63e0f2846c7"),
+            },
+            verification: Verification {
+                verified: true,
+                reason: Reason::Valid,
+                signature: Some(signature.to_string()),
+                payload: Some(include_str!("../tests/test-tag.txt").to_string()),
+            },
+        }
+    }
+
+    #[test]
+    fn verify_tag() {
+        test::init();
+
+        let tv =
+            TagVerifier::from_key_files(&["tests/second.pub"]).expect("failed to load public key");
+        let res = tv.verify(&tag(include_str!("../tests/tag.sig")));
+
+        debug!("Res: {:#?}", res);
+        asserting("Tag signature by release manager is valid")
+            .that(&res)
+            .is_ok()
+            .has_length(1);
+    }
+
+    #[test]
+    fn verify_tag_by_committer_failed() {
+        test::init();
+
+        let tv = TagVerifier::from_key_files(&["tests/lukas.pustina.pub"])
+            .expect("failed to load public key");
+        let res = tv.verify(&tag(include_str!("../tests/tag.sig")));
+
+        asserting("Tag signature by key other than release manager's is invalid")
+            .that(&res)
+            .is_err();
+    }
+
+    fn lukas_pustina_owner() -> KeyOwner {
+        KeyOwner {
+            name: "Lukas Pustina".to_string(),
+            github_logins: vec!["lukaspustina".to_string()],
+            e_mails: vec!["lukas@pustina.de".to_string()],
+            teams: vec!["platform".to_string()],
+        }
+    }
+
+    #[test]
+    fn key_owner_display() {
+        let owner = lukas_pustina_owner();
+        let without_login = KeyOwner {
+            github_logins: Vec::new(),
+            teams: Vec::new(),
+            ..lukas_pustina_owner()
+        };
+
+        asserting("Owner is displayed with GitHub login and team")
+            .that(&owner.to_string())
+            .is_equal_to("@lukaspustina (team: platform)".to_string());
+        asserting("Owner without GitHub login is displayed with name")
+            .that(&without_login.to_string())
+            .is_equal_to("Lukas Pustina".to_string());
+    }
+
+    #[test]
+    fn verify_commit_with_key_owner() {
+        test::init();
+
+        let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
+            .expect("failed to load public key")
+            .with_key_owner(
+                "4161 0C26 6853 C6D5 7FE1  9760 ECFB 5D03 2D82 9112",
+                lukas_pustina_owner(),
+            );
+        let res = cv.verify(&lukas_pustina_commit());
+
+        asserting("Key owner is reported")
+            .that(&res)
+            .is_ok()
+            .map(|x| &x.owner)
+            .is_equal_to(&Some(lukas_pustina_owner()));
+    }
+
+    #[test]
+    fn verify_push_by_key_owner() {
+        test::init();
+
+        let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
+            .expect("failed to load public key")
+            .with_key_owner(
+                "4161 0C26 6853 C6D5 7FE1  9760 ECFB 5D03 2D82 9112",
+                lukas_pustina_owner(),
+            )
+            .with_require_pusher_owns_key(true);
+        let res = cv.verify_push(&lukas_pustina_commit(), "LukasPustina");
+
+        asserting("Commit pushed by key owner is valid")
+            .that(&res)
+            .is_ok();
+    }
+
+    #[test]
+    fn verify_push_by_other_user_failed() {
+        test::init();
+
+        let cv = CommitVerifier::from_key_file("tests/lukas.pustina.pub")
+            .expect("failed to load public key")
+            .with_key_owner(
+                "4161 0C26 6853 C6D5 7FE1  9760 ECFB 5D03 2D82 9112",
+                lukas_pustina_owner(),
+            )
+            .with_require_pusher_owns_key(true);
+        let res = cv.verify_push(&lukas_pustina_commit(), "mallory");
+
+        asserting("Commit pushed by other user is invalid")
+            .that(&res)
+            .is_err()
+            .map(|x| x.kind())
+            .is_equal_to(&ErrorKind::PusherDoesNotOwnKey("mallory".to_string()));
+    }
 }
@@ -0,0 +1,1005 @@
+//! Sources of public keys beyond the armored keys of the configuration
+
+use crate::{
+    config::{GitHubKeyringConfig, GitHubMember, GitHubWatchTowerConfig, PubKey},
+    errors::*,
+    github::{GitHub, GpgKey},
+    gpg::{check_fingerprint, normalize_fingerprint, CommitVerifier, KeyOwner},
+    utils::http::GeneralErrHandler,
+};
+
+use failure::Fail;
+use log::{debug, info, warn};
+use openpgp::{
+    parse::{PacketParser, Parse},
+    tpk::TPKParser,
+    TPK,
+};
+use reqwest::{self, StatusCode};
+use sequoia_openpgp as openpgp;
+use serde_json;
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError, RwLock},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+static KEYBOX_MAGIC: &[u8] = b"KBXf";
+const KEYBOX_BLOB_TYPE_HEADER: u8 = 1;
+const KEYBOX_BLOB_TYPE_OPENPGP: u8 = 2;
+
+/// Where a public key has been loaded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Provenance {
+    /// `pub_key` entry of the configuration with its name
+    Config(String),
+    File(PathBuf),
+    Keybox(PathBuf),
+    Url(String),
+    /// GitHub user the key has been fetched from
+    GitHub(String),
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Provenance::Config(name) => write!(f, "config entry '{}'", name),
+            Provenance::File(path) => write!(f, "file {}", path.display()),
+            Provenance::Keybox(path) => write!(f, "keybox {}", path.display()),
+            Provenance::Url(url) => write!(f, "URL {}", url),
+            Provenance::GitHub(login) => write!(f, "GitHub user @{}", login),
+        }
+    }
+}
+
+/// A public key together with the sources it has been loaded from
+#[derive(Debug)]
+pub struct KeyringEntry {
+    pub tpk: TPK,
+    pub provenance: Vec<Provenance>,
+}
+
+impl KeyringEntry {
+    fn new(tpk: TPK, provenance: Provenance) -> KeyringEntry {
+        KeyringEntry {
+            tpk,
+            provenance: vec![provenance],
+        }
+    }
+}
+
+/// A source of public keys
+pub trait KeyringSource {
+    fn load(&self) -> Result<Vec<KeyringEntry>>;
+}
+
+/// Keys of the configuration's `pub_key` entries
+#[derive(Debug)]
+pub struct ConfigSource<'a> {
+    pub_keys: &'a [PubKey],
+}
+
+impl<'a> ConfigSource<'a> {
+    pub fn new(pub_keys: &'a [PubKey]) -> ConfigSource<'a> {
+        ConfigSource { pub_keys }
+    }
+}
+
+impl<'a> KeyringSource for ConfigSource<'a> {
+    fn load(&self) -> Result<Vec<KeyringEntry>> {
+        let mut entries = Vec::new();
+        for pub_key in self.pub_keys {
+            let tpk = TPK::from_bytes(pub_key.armored_key.as_bytes())
+                .map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
+            check_fingerprint(pub_key, &tpk)?;
+            entries.push(KeyringEntry::new(
+                tpk,
+                Provenance::Config(pub_key.name.clone()),
+            ));
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Keys of all `.asc` files in a directory; each file may contain multiple keys
+#[derive(Debug)]
+pub struct DirectorySource {
+    path: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new<P: Into<PathBuf>>(path: P) -> DirectorySource {
+        DirectorySource { path: path.into() }
+    }
+
+    fn key_files(&self) -> Result<Vec<PathBuf>> {
+        let mut paths: Vec<_> = fs::read_dir(&self.path)
+            .map_err(|e| e.context(ErrorKind::FailedToLoadKey))?
+            .filter_map(|x| x.ok().map(|x| x.path()))
+            .filter(|x| x.is_file() && x.extension().map(|x| x == "asc").unwrap_or(false))
+            .collect();
+        // Sort for a deterministic order of keys independent of the file system
+        paths.sort();
+
+        Ok(paths)
+    }
+
+    /// Names, sizes, and modification times of all key files to detect changes
+    fn snapshot(&self) -> Result<Vec<(PathBuf, u64, Option<SystemTime>)>> {
+        let mut snapshot = Vec::new();
+        for path in self.key_files()? {
+            let metadata =
+                fs::metadata(&path).map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
+            snapshot.push((path, metadata.len(), metadata.modified().ok()));
+        }
+
+        Ok(snapshot)
+    }
+}
+
+impl KeyringSource for DirectorySource {
+    fn load(&self) -> Result<Vec<KeyringEntry>> {
+        let mut entries = Vec::new();
+        for path in self.key_files()? {
+            let bytes = read_file(&path)?;
+            for tpk in parse_keys(&bytes)? {
+                entries.push(KeyringEntry::new(tpk, Provenance::File(path.clone())));
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+/// Keys of a GnuPG keybox, e.g., `pubring.kbx`, or of a file exported by `gpg --export`
+#[derive(Debug)]
+pub struct KeyboxSource {
+    path: PathBuf,
+}
+
+impl KeyboxSource {
+    pub fn new<P: Into<PathBuf>>(path: P) -> KeyboxSource {
+        KeyboxSource { path: path.into() }
+    }
+}
+
+impl KeyringSource for KeyboxSource {
+    fn load(&self) -> Result<Vec<KeyringEntry>> {
+        let bytes = read_file(&self.path)?;
+
+        let tpks = if is_keybox(&bytes) {
+            let mut tpks = Vec::new();
+            for keyblock in keybox_keyblocks(&bytes)? {
+                tpks.extend(parse_keys(keyblock)?);
+            }
+            tpks
+        } else {
+            parse_keys(&bytes)?
+        };
+
+        let entries = tpks
+            .into_iter()
+            .map(|x| KeyringEntry::new(x, Provenance::Keybox(self.path.clone())))
+            .collect();
+
+        Ok(entries)
+    }
+}
+
+/// Keys served by an HTTP URL, e.g., of an internal key server
+#[derive(Debug)]
+pub struct UrlSource {
+    url: String,
+}
+
+impl UrlSource {
+    pub fn new<T: Into<String>>(url: T) -> UrlSource {
+        UrlSource { url: url.into() }
+    }
+}
+
+impl KeyringSource for UrlSource {
+    fn load(&self) -> Result<Vec<KeyringEntry>> {
+        let mut response = reqwest::Client::new()
+            .get(&self.url)
+            .send()
+            .map_err(|e| e.context(ErrorKind::HttpRequestFailed))?
+            .general_err_handler(StatusCode::OK)?;
+        let mut bytes = Vec::new();
+        response.read_to_end(&mut bytes).map_err(|e| {
+            e.context(ErrorKind::FailedToProcessHttpResponse(
+                response.status(),
+                "reading body".to_string(),
+            ))
+        })?;
+
+        let entries = parse_keys(&bytes)?
+            .into_iter()
+            .map(|x| KeyringEntry::new(x, Provenance::Url(self.url.clone())))
+            .collect();
+
+        Ok(entries)
+    }
+}
+
+/// Keys of GitHub users, cf. `GitHubKeyring`
+#[derive(Debug)]
+pub struct GitHubUserKeysSource<'a, G: GitHub> {
+    keyring: &'a GitHubKeyring,
+    github: &'a G,
+}
+
+impl<'a, G: GitHub> GitHubUserKeysSource<'a, G> {
+    pub fn new(keyring: &'a GitHubKeyring, github: &'a G) -> GitHubUserKeysSource<'a, G> {
+        GitHubUserKeysSource { keyring, github }
+    }
+}
+
+impl<'a, G: GitHub> KeyringSource for GitHubUserKeysSource<'a, G> {
+    fn load(&self) -> Result<Vec<KeyringEntry>> {
+        let entries = self
+            .keyring
+            .load(self.github)?
+            .into_iter()
+            .map(|(owner, tpk)| KeyringEntry::new(tpk, Provenance::GitHub(owner.name)))
+            .collect();
+
+        Ok(entries)
+    }
+}
+
+/// Keys of multiple sources merged into one keyring
+///
+/// Keys with the same fingerprint are merged, e.g., to combine new signatures, and keep the
+/// provenance of all sources.
+#[derive(Debug, Default)]
+pub struct Keyring {
+    entries: Vec<KeyringEntry>,
+}
+
+impl Keyring {
+    pub fn from_sources(sources: &[&dyn KeyringSource]) -> Result<Keyring> {
+        let mut keyring = Keyring::default();
+        for source in sources {
+            for entry in source.load()? {
+                keyring.add(entry)?;
+            }
+        }
+
+        Ok(keyring)
+    }
+
+    fn add(&mut self, entry: KeyringEntry) -> Result<()> {
+        let finger_print = entry.tpk.fingerprint();
+        let position = self
+            .entries
+            .iter()
+            .position(|x| x.tpk.fingerprint() == finger_print);
+
+        match position {
+            Some(i) => {
+                let existing = self.entries.remove(i);
+                let tpk = existing
+                    .tpk
+                    .merge(entry.tpk)
+                    .map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
+                let mut provenance = existing.provenance;
+                provenance.extend(entry.provenance);
+                self.entries.insert(i, KeyringEntry { tpk, provenance });
+            }
+            None => self.entries.push(entry),
+        }
+
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[KeyringEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Sources of the key with the fingerprint of its primary key
+    pub fn provenance(&self, primary_finger_print: &str) -> Option<&[Provenance]> {
+        let finger_print = normalize_fingerprint(primary_finger_print);
+        self.entries
+            .iter()
+            .find(|x| x.tpk.fingerprint().to_hex() == finger_print)
+            .map(|x| x.provenance.as_slice())
+    }
+
+    pub fn verifier(&self) -> CommitVerifier {
+        CommitVerifier::from_keys(self.entries.iter().map(|x| x.tpk.clone()).collect())
+    }
+}
+
+type Configuration = Box<dyn Fn(CommitVerifier) -> CommitVerifier + Send + Sync>;
+
+/// `CommitVerifier` over a directory of armored keys that is reloaded when the directory changes
+///
+/// The directory is polled, cf. `watch`. On changes, a new `CommitVerifier` is created and swapped
+/// atomically; verifications in progress keep using the previous one. If the directory cannot be
+/// loaded, the previous `CommitVerifier` stays in place.
+pub struct WatchedKeyring {
+    source: DirectorySource,
+    configuration: Configuration,
+    verifier: RwLock<Arc<CommitVerifier>>,
+    state: Mutex<WatchState>,
+}
+
+#[derive(Debug, Default)]
+struct WatchState {
+    snapshot: Option<Vec<(PathBuf, u64, Option<SystemTime>)>>,
+    finger_prints: HashSet<String>,
+}
+
+impl fmt::Debug for WatchedKeyring {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WatchedKeyring")
+            .field("source", &self.source)
+            .field("verifier", &self.verifier)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl WatchedKeyring {
+    /// Loads the keys of a directory; `configuration` is applied to every `CommitVerifier` created
+    /// from the keys, e.g., to set the signature mode
+    pub fn new<P, F>(directory: P, configuration: F) -> Result<WatchedKeyring>
+    where
+        P: Into<PathBuf>,
+        F: Fn(CommitVerifier) -> CommitVerifier + Send + Sync + 'static,
+    {
+        let watched = WatchedKeyring {
+            source: DirectorySource::new(directory),
+            configuration: Box::new(configuration),
+            verifier: RwLock::new(Arc::new(CommitVerifier::from_keys(Vec::new()))),
+            state: Mutex::new(WatchState::default()),
+        };
+        watched.reload_if_changed()?;
+
+        Ok(watched)
+    }
+
+    /// The current `CommitVerifier`
+    pub fn verifier(&self) -> Arc<CommitVerifier> {
+        let verifier = self.verifier.read().unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(&verifier)
+    }
+
+    /// Reloads the keys if any key file has been added, removed, or modified
+    ///
+    /// Returns whether the keys have been reloaded.
+    pub fn reload_if_changed(&self) -> Result<bool> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let snapshot = self.source.snapshot()?;
+        if state.snapshot.as_ref() == Some(&snapshot) {
+            return Ok(false);
+        }
+
+        let keyring = Keyring::from_sources(&[&self.source])?;
+        let finger_prints: HashSet<_> = keyring
+            .entries()
+            .iter()
+            .map(|x| x.tpk.fingerprint().to_hex())
+            .collect();
+        for added in finger_prints.difference(&state.finger_prints) {
+            info!("Added key {} from {:?}", added, self.source.path);
+        }
+        for removed in state.finger_prints.difference(&finger_prints) {
+            info!("Removed key {} from {:?}", removed, self.source.path);
+        }
+
+        let verifier = Arc::new((self.configuration)(keyring.verifier()));
+        *self
+            .verifier
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = verifier;
+        state.snapshot = Some(snapshot);
+        state.finger_prints = finger_prints;
+
+        Ok(true)
+    }
+
+    /// Polls the directory for changes in a background thread
+    ///
+    /// The thread stops as soon as the `WatchedKeyring` has been dropped.
+    pub fn watch(watched: &Arc<WatchedKeyring>, interval: Duration) -> thread::JoinHandle<()> {
+        let watched = Arc::downgrade(watched);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            let watched = match watched.upgrade() {
+                Some(x) => x,
+                None => break,
+            };
+            if let Err(e) = watched.reload_if_changed() {
+                warn!(
+                    "Failed to reload keys from {:?}: {}",
+                    watched.source.path, e
+                );
+            }
+        })
+    }
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|e| e.context(ErrorKind::FailedToLoadKey).into())
+}
+
+/// Parses all keys of armored or binary OpenPGP data
+fn parse_keys(bytes: &[u8]) -> Result<Vec<TPK>> {
+    let ppr = PacketParser::from_bytes(bytes).map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
+    let tpks: ::std::result::Result<Vec<_>, _> = TPKParser::from_packet_parser(ppr).collect();
+
+    tpks.map_err(|e| e.context(ErrorKind::FailedToLoadKey).into())
+}
+
+fn is_keybox(bytes: &[u8]) -> bool {
+    bytes.len() >= 12 && bytes[4] == KEYBOX_BLOB_TYPE_HEADER && &bytes[8..12] == KEYBOX_MAGIC
+}
+
+/// Extracts the OpenPGP keyblocks of a keybox, cf. GnuPG's `kbx/keybox-blob.c`
+///
+/// A keybox is a sequence of blobs. Each blob starts with its length (4 bytes, big endian) and its
+/// type (1 byte); OpenPGP blobs carry the offset and length of their keyblock at bytes 8 and 12.
+fn keybox_keyblocks(bytes: &[u8]) -> Result<Vec<&[u8]>> {
+    let invalid = || Error::from(ErrorKind::FailedToLoadKey);
+    let read_u32 = |b: &[u8], pos: usize| -> Result<usize> {
+        b.get(pos..pos + 4)
+            .map(|x| u32::from_be_bytes([x[0], x[1], x[2], x[3]]) as usize)
+            .ok_or_else(invalid)
+    };
+
+    let mut keyblocks = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let len = read_u32(bytes, pos)?;
+        let blob = bytes.get(pos..pos + len).ok_or_else(invalid)?;
+        if len < 5 {
+            return Err(invalid());
+        }
+        if blob[4] == KEYBOX_BLOB_TYPE_OPENPGP {
+            let offset = read_u32(blob, 8)?;
+            let length = read_u32(blob, 12)?;
+            keyblocks.push(blob.get(offset..offset + length).ok_or_else(invalid)?);
+        }
+        pos += len;
+    }
+
+    Ok(keyblocks)
+}
+
+/// Fetches the signing keys of GitHub users from their GitHub accounts
+///
+/// If fingerprints are pinned for a user, any other key of this user fails loading; this detects
+/// keys added to a user's account unexpectedly. Fetched keys of users with pinned fingerprints are
+/// stored in an optional cache file which is used if their keys cannot be fetched, e.g., because
+/// GitHub is unavailable. The cache file is not authenticated, so it is not used for users without
+/// pinned fingerprints; otherwise, anyone able to write it could add trusted keys.
+#[derive(Debug)]
+pub struct GitHubKeyring {
+    members: Vec<GitHubMember>,
+    cache_file: Option<PathBuf>,
+}
+
+type Cache = HashMap<String, Vec<GpgKey>>;
+
+impl From<&GitHubKeyringConfig> for GitHubKeyring {
+    fn from(config: &GitHubKeyringConfig) -> GitHubKeyring {
+        GitHubKeyring {
+            members: config.members.clone(),
+            cache_file: config.cache_file.clone(),
+        }
+    }
+}
+
+impl GitHubKeyring {
+    pub fn new(members: Vec<GitHubMember>) -> GitHubKeyring {
+        GitHubKeyring {
+            members,
+            cache_file: None,
+        }
+    }
+
+    pub fn with_cache_file<P: Into<PathBuf>>(self, cache_file: P) -> Self {
+        GitHubKeyring {
+            cache_file: Some(cache_file.into()),
+            ..self
+        }
+    }
+
+    /// Loads the signing keys of all members together with their owners
+    pub fn load<G: GitHub>(&self, github: &G) -> Result<Vec<(KeyOwner, TPK)>> {
+        let mut cache = self.read_cache();
+        let mut keys = Vec::new();
+
+        for member in &self.members {
+            let gpg_keys = match github.user_gpg_keys(&member.login) {
+                Ok(gpg_keys) => {
+                    if member.pinned_fingerprints.is_empty() {
+                        cache.remove(&member.login);
+                    } else {
+                        cache.insert(member.login.clone(), gpg_keys.clone());
+                    }
+                    gpg_keys
+                }
+                Err(e) => match cache.get(&member.login) {
+                    // Cached keys are checked against the pinned fingerprints by `load_key`
+                    Some(gpg_keys) if !member.pinned_fingerprints.is_empty() => {
+                        warn!(
+                            "Failed to fetch keys of GitHub user {}, using cache: {}",
+                            member.login, e
+                        );
+                        gpg_keys.clone()
+                    }
+                    _ => return Err(e),
+                },
+            };
+            for gpg_key in gpg_keys {
+                if let Some(tpk) = load_key(member, &gpg_key)? {
+                    keys.push((owner(member, &gpg_key), tpk));
+                }
+            }
+        }
+        self.write_cache(&cache)?;
+
+        Ok(keys)
+    }
+
+    /// Adds the signing keys of all members and their owners to a `CommitVerifier`
+    pub fn extend<G: GitHub>(
+        &self,
+        github: &G,
+        verifier: CommitVerifier,
+    ) -> Result<CommitVerifier> {
+        let keys = self.load(github)?;
+
+        let verifier = keys.into_iter().fold(verifier, |verifier, (owner, tpk)| {
+            verifier
+                .with_key_owner(&tpk.fingerprint().to_hex(), owner)
+                .with_keys(vec![tpk])
+        });
+
+        Ok(verifier)
+    }
+
+    fn read_cache(&self) -> Cache {
+        let cache_file = match self.cache_file {
+            Some(ref x) if x.exists() => x,
+            _ => return Cache::new(),
+        };
+
+        File::open(cache_file)
+            .map_err(|e| e.to_string())
+            .and_then(|f| serde_json::from_reader(f).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                warn!("Failed to read key cache {:?}: {}", cache_file, e);
+                Cache::new()
+            })
+    }
+
+    fn write_cache(&self, cache: &Cache) -> Result<()> {
+        let cache_file = match self.cache_file {
+            Some(ref x) => x,
+            None => return Ok(()),
+        };
+
+        // Write to a temporary file first, so a failed write doesn't destroy the existing cache.
+        let tmp_file = cache_file.with_extension("tmp");
+        let file = File::create(&tmp_file).map_err(|e| e.context(ErrorKind::GeneralError))?;
+        serde_json::to_writer(file, cache).map_err(|e| e.context(ErrorKind::GeneralError))?;
+        fs::rename(&tmp_file, cache_file).map_err(|e| e.context(ErrorKind::GeneralError))?;
+        debug!("Wrote key cache {:?}", cache_file);
+
+        Ok(())
+    }
+}
+
+/// Creates the verifier of a configuration including the keys of its `github_keyring` members
+pub fn verifier<G: GitHub>(config: &GitHubWatchTowerConfig, github: &G) -> Result<CommitVerifier> {
+    let verifier = CommitVerifier::try_from(config)?;
+
+    match config.github_keyring {
+        Some(ref github_keyring) => GitHubKeyring::from(github_keyring).extend(github, verifier),
+        None => Ok(verifier),
+    }
+}
+
+fn load_key(member: &GitHubMember, gpg_key: &GpgKey) -> Result<Option<TPK>> {
+    let raw_key = match gpg_key.raw_key {
+        Some(ref x) => x,
+        None => {
+            warn!(
+                "Key {} of GitHub user {} is not available in armored form; skipping",
+                gpg_key.key_id, member.login
+            );
+            return Ok(None);
+        }
+    };
+    let tpk =
+        TPK::from_bytes(raw_key.as_bytes()).map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
+
+    let finger_print = tpk.fingerprint().to_hex();
+    if !member.pinned_fingerprints.is_empty()
+        && !member
+            .pinned_fingerprints
+            .iter()
+            .any(|x| normalize_fingerprint(x) == finger_print)
+    {
+        return Err(Error::from(ErrorKind::UnexpectedKey(
+            member.login.clone(),
+            finger_print,
+        )));
+    }
+    // Pinning is checked first, so unexpected keys are detected even if they cannot sign.
+    if !gpg_key.can_sign {
+        debug!(
+            "Key {} of GitHub user {} cannot sign; skipping",
+            finger_print, member.login
+        );
+        return Ok(None);
+    }
+
+    Ok(Some(tpk))
+}
+
+fn owner(member: &GitHubMember, gpg_key: &GpgKey) -> KeyOwner {
+    KeyOwner {
+        name: member.login.clone(),
+        github_logins: vec![member.login.clone()],
+        e_mails: gpg_key
+            .emails
+            .iter()
+            .filter(|x| x.verified)
+            .map(|x| x.email.clone())
+            .collect(),
+        teams: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        github::users::GpgKeyEmail,
+        utils::test::{self, FakeGitHub},
+    };
+
+    use clams::config::Config;
+    use spectral::prelude::*;
+    use std::{env, io::Write, net::TcpListener, thread};
+
+    static SECOND_FINGERPRINT: &str = "81E95AD23D9F4C60599BD3D161B5D0C9A727049E";
+
+    fn github() -> FakeGitHub {
+        FakeGitHub::new().with_user_gpg_keys(
+            "second",
+            vec![GpgKey {
+                id: 1,
+                key_id: "61B5D0C9A727049E".to_string(),
+                raw_key: Some(include_str!("../tests/second.pub").to_string()),
+                emails: vec![GpgKeyEmail {
+                    email: "second@example.com".to_string(),
+                    verified: true,
+                }],
+                can_sign: true,
+                expires_at: None,
+            }],
+        )
+    }
+
+    fn member(pinned_fingerprints: &[&str]) -> GitHubMember {
+        GitHubMember {
+            login: "second".to_string(),
+            pinned_fingerprints: pinned_fingerprints.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn load_keys_okay() {
+        test::init();
+
+        let keyring = GitHubKeyring::new(vec![member(&[])]);
+        let keys = keyring.load(&github());
+
+        asserting("Keys of GitHub user are loaded")
+            .that(&keys)
+            .is_ok()
+            .has_length(1);
+        let keys = keys.unwrap();
+        asserting("Owner is GitHub user")
+            .that(&keys[0].0.github_logins)
+            .is_equal_to(&vec!["second".to_string()]);
+    }
+
+    #[test]
+    fn load_pinned_keys_okay() {
+        test::init();
+
+        let keyring = GitHubKeyring::new(vec![member(&[SECOND_FINGERPRINT])]);
+        let keys = keyring.load(&github());
+
+        asserting("Pinned keys of GitHub user are loaded")
+            .that(&keys)
+            .is_ok()
+            .has_length(1);
+    }
+
+    #[test]
+    fn load_unexpected_key_failed() {
+        test::init();
+
+        let keyring = GitHubKeyring::new(vec![member(&[
+            "4161 0C26 6853 C6D5 7FE1  9760 ECFB 5D03 2D82 9112",
+        ])]);
+        let keys = keyring.load(&github());
+
+        asserting("Key not pinned is detected")
+            .that(&keys)
+            .is_err()
+            .map(|x| x.kind())
+            .is_equal_to(&ErrorKind::UnexpectedKey(
+                "second".to_string(),
+                SECOND_FINGERPRINT.to_string(),
+            ));
+    }
+
+    #[test]
+    fn load_keys_from_cache() {
+        test::init();
+
+        let cache_file = env::temp_dir().join("github-watchtower-keyring-test.json");
+        let _ = fs::remove_file(&cache_file);
+        let keyring =
+            GitHubKeyring::new(vec![member(&[SECOND_FINGERPRINT])]).with_cache_file(&cache_file);
+        let unpinned = GitHubKeyring::new(vec![member(&[])]).with_cache_file(&cache_file);
+
+        let keys = keyring.load(&github());
+        asserting("Keys are fetched from GitHub")
+            .that(&keys)
+            .is_ok()
+            .has_length(1);
+
+        let keys = keyring.load(&FakeGitHub::new());
+        asserting("Keys are loaded from cache if GitHub is unavailable")
+            .that(&keys)
+            .is_ok()
+            .has_length(1);
+
+        let keys = unpinned.load(&FakeGitHub::new());
+        asserting("Cache is not used for users without pinned fingerprints")
+            .that(&keys)
+            .is_err();
+
+        let _ = fs::remove_file(&cache_file);
+    }
+
+    #[test]
+    fn load_keys_without_cache_failed() {
+        test::init();
+
+        let keyring = GitHubKeyring::new(vec![member(&[])]);
+        let keys = keyring.load(&FakeGitHub::new());
+
+        asserting("Loading fails if GitHub is unavailable and there is no cache")
+            .that(&keys)
+            .is_err();
+    }
+
+    #[test]
+    fn verifier_includes_github_keyring() {
+        test::init();
+
+        let mut config = GitHubWatchTowerConfig::from_file("tests/config.toml")
+            .expect("failed to load config file");
+        let without_keyring = verifier(&config, &github()).map(|x| x.finger_prints());
+        config.github_keyring = Some(GitHubKeyringConfig {
+            members: vec![member(&[])],
+            cache_file: None,
+        });
+        let with_keyring = verifier(&config, &github()).map(|x| x.finger_prints());
+        let unavailable = verifier(&config, &FakeGitHub::new());
+
+        asserting("keys of the configuration are trusted")
+            .that(&without_keyring)
+            .is_ok()
+            .does_not_contain(SECOND_FINGERPRINT.to_string());
+        asserting("keys of GitHub keyring members are trusted")
+            .that(&with_keyring)
+            .is_ok()
+            .contains(SECOND_FINGERPRINT.to_string());
+        asserting("verifier fails if keys of members cannot be loaded")
+            .that(&unavailable)
+            .is_err();
+    }
+
+    #[test]
+    fn load_directory_source() {
+        test::init();
+
+        let entries = DirectorySource::new("tests/keyring").load();
+
+        asserting("Keys of all .asc files are loaded")
+            .that(&entries)
+            .is_ok()
+            .has_length(2);
+        let entries = entries.unwrap();
+        asserting("Provenance is file")
+            .that(&entries[0].provenance)
+            .is_equal_to(&vec![Provenance::File(PathBuf::from(
+                "tests/keyring/lukas.pustina.asc",
+            ))]);
+    }
+
+    #[test]
+    fn load_keybox_source() {
+        test::init();
+
+        let entries = KeyboxSource::new("tests/pubring.kbx").load();
+
+        asserting("All keys of keybox are loaded")
+            .that(&entries)
+            .is_ok()
+            .has_length(6);
+    }
+
+    #[test]
+    fn load_keybox_source_from_export() {
+        test::init();
+
+        let entries = KeyboxSource::new("tests/second.pub").load();
+
+        asserting("Keys of exported file are loaded")
+            .that(&entries)
+            .is_ok()
+            .has_length(1);
+    }
+
+    #[test]
+    fn load_url_source() {
+        test::init();
+
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind listener");
+        let url = format!("http://{}/keys.asc", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("failed to accept connection");
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            let body = include_str!("../tests/second.pub");
+            let _ = write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+        });
+
+        let entries = UrlSource::new(url.as_str()).load();
+        let _ = server.join();
+
+        asserting("Keys served by URL are loaded")
+            .that(&entries)
+            .is_ok()
+            .has_length(1);
+        asserting("Provenance is URL")
+            .that(&entries.unwrap()[0].provenance)
+            .is_equal_to(&vec![Provenance::Url(url)]);
+    }
+
+    #[test]
+    fn merge_sources() {
+        test::init();
+
+        let config = GitHubWatchTowerConfig::from_file("tests/config.toml")
+            .expect("failed to load config file");
+        let config_source = ConfigSource::new(&config.pub_keys);
+        let directory_source = DirectorySource::new("tests/keyring");
+
+        let keyring = Keyring::from_sources(&[&config_source, &directory_source]);
+
+        asserting("Keys of all sources are merged")
+            .that(&keyring)
+            .is_ok()
+            .map(|x| &x.entries)
+            .has_length(2);
+        let keyring = keyring.unwrap();
+        asserting("Provenance of all sources is kept")
+            .that(
+                &keyring
+                    .provenance("4161 0C26 6853 C6D5 7FE1  9760 ECFB 5D03 2D82 9112")
+                    .map(|x| x.len()),
+            )
+            .is_equal_to(Some(2));
+    }
+
+    #[test]
+    fn watched_keyring_reloads_on_changes() {
+        test::init();
+
+        let directory = env::temp_dir().join("github-watchtower-watched-keyring-test");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).expect("failed to create directory");
+        fs::copy("tests/keyring/second.asc", directory.join("second.asc"))
+            .expect("failed to copy key");
+
+        let watched = WatchedKeyring::new(&directory, |cv| cv).expect("failed to load keys");
+        asserting("Keys are loaded initially")
+            .that(&watched.state.lock().unwrap().finger_prints.len())
+            .is_equal_to(&1);
+
+        asserting("Nothing is reloaded without changes")
+            .that(&watched.reload_if_changed())
+            .is_ok()
+            .is_false();
+
+        fs::copy(
+            "tests/keyring/lukas.pustina.asc",
+            directory.join("lukas.pustina.asc"),
+        )
+        .expect("failed to copy key");
+        asserting("Added key is loaded")
+            .that(&watched.reload_if_changed())
+            .is_ok()
+            .is_true();
+        asserting("Both keys are loaded")
+            .that(&watched.state.lock().unwrap().finger_prints.len())
+            .is_equal_to(&2);
+
+        fs::remove_file(directory.join("second.asc")).expect("failed to remove key");
+        asserting("Removed key is unloaded")
+            .that(&watched.reload_if_changed())
+            .is_ok()
+            .is_true();
+        asserting("Only remaining key is loaded")
+            .that(
+                &watched
+                    .state
+                    .lock()
+                    .unwrap()
+                    .finger_prints
+                    .contains("41610C266853C6D57FE19760ECFB5D032D829112"),
+            )
+            .is_true();
+
+        let _ = fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn watched_keyring_keeps_verifier_on_error() {
+        test::init();
+
+        let directory = env::temp_dir().join("github-watchtower-watched-keyring-error-test");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).expect("failed to create directory");
+        fs::copy("tests/keyring/second.asc", directory.join("second.asc"))
+            .expect("failed to copy key");
+
+        let watched = WatchedKeyring::new(&directory, |cv| cv).expect("failed to load keys");
+        let verifier = watched.verifier();
+
+        fs::write(directory.join("broken.asc"), "not a key").expect("failed to write file");
+        asserting("Broken key fails reload")
+            .that(&watched.reload_if_changed())
+            .is_err();
+        asserting("Previous verifier stays in place")
+            .that(&Arc::ptr_eq(&verifier, &watched.verifier()))
+            .is_true();
+
+        let _ = fs::remove_dir_all(&directory);
+    }
+}
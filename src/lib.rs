@@ -1,5 +1,17 @@
+pub mod audit_log;
+pub mod bootstrap;
+pub mod cache;
 pub mod config;
+pub mod config_check;
 pub mod errors;
+pub mod git;
 pub mod github;
 pub mod gpg;
+pub mod keyring;
+pub mod repo_policy;
+pub mod report;
+pub mod source;
+pub mod ssh;
 mod utils;
+pub mod verdict;
+pub mod x509;
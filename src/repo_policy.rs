@@ -0,0 +1,454 @@
+//! Per-repository policy files, e.g., `.github/watchtower.toml`
+//!
+//! A repository may tighten the central configuration, but never loosen it: policies are merged
+//! by taking the stricter setting and the set of trusted keys may only be narrowed. A policy file
+//! trying to weaken a setting is rejected as a whole.
+
+use crate::{
+    config::{GitHubWatchTowerConfig, MergeMode, Policy, RepoPolicyReference, SignatureMode},
+    errors::*,
+    github::{commits::Sha, GitHub, Repository},
+};
+
+use failure::Fail;
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use toml;
+
+/// Content of a per-repository policy file
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoConfig {
+    pub policy: RepoPolicy,
+    /// Names of the `pub_key` and `allowed_signer` entries trusted for this repository; all if
+    /// not set. Keys fetched from GitHub are not affected.
+    pub allowed_keys: Option<Vec<String>>,
+}
+
+/// Policy settings of a repository; unset settings are inherited from the central policy
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoPolicy {
+    pub signature_mode: Option<SignatureMode>,
+    pub trust_web_flow: Option<bool>,
+    pub merge_mode: Option<MergeMode>,
+    pub require_pusher_owns_key: Option<bool>,
+}
+
+impl RepoConfig {
+    pub fn from_toml(toml: &str) -> Result<RepoConfig> {
+        toml::from_str(toml).map_err(|e| {
+            let reason = e.to_string();
+            e.context(ErrorKind::InvalidConfig(reason)).into()
+        })
+    }
+
+    /// Merges this repository's settings into the central configuration
+    pub fn merge(&self, central: &GitHubWatchTowerConfig) -> Result<GitHubWatchTowerConfig> {
+        let mut config = central.clone();
+        config.policy = self.policy.merge(&central.policy)?;
+
+        if let Some(ref allowed_keys) = self.allowed_keys {
+            for (i, name) in allowed_keys.iter().enumerate() {
+                let known = central.pub_keys.iter().any(|x| &x.name == name)
+                    || central.allowed_signers.iter().any(|x| &x.name == name);
+                if !known {
+                    return Err(Error::from(ErrorKind::InvalidConfigField(
+                        format!("allowed_keys[{}]", i),
+                        format!("no pub_key or allowed_signer named '{}'", name),
+                    )));
+                }
+            }
+
+            config.pub_keys.retain(|x| allowed_keys.contains(&x.name));
+            config
+                .allowed_signers
+                .retain(|x| allowed_keys.contains(&x.name));
+            for team in &mut config.teams {
+                team.members.retain(|x| allowed_keys.contains(x));
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+impl RepoPolicy {
+    fn merge(&self, central: &Policy) -> Result<Policy> {
+        let mut policy = central.clone();
+
+        if let Some(signature_mode) = self.signature_mode {
+            if central.signature_mode == SignatureMode::All && signature_mode == SignatureMode::Any
+            {
+                return Err(weakened("policy.signature_mode"));
+            }
+            policy.signature_mode = signature_mode;
+        }
+        if let Some(trust_web_flow) = self.trust_web_flow {
+            if !central.trust_web_flow && trust_web_flow {
+                return Err(weakened("policy.trust_web_flow"));
+            }
+            policy.trust_web_flow = trust_web_flow;
+        }
+        if let Some(merge_mode) = self.merge_mode {
+            if central.merge_mode == MergeMode::IntroducedCommits
+                && merge_mode == MergeMode::CommitOnly
+            {
+                return Err(weakened("policy.merge_mode"));
+            }
+            policy.merge_mode = merge_mode;
+        }
+        if let Some(require_pusher_owns_key) = self.require_pusher_owns_key {
+            if central.require_pusher_owns_key && !require_pusher_owns_key {
+                return Err(weakened("policy.require_pusher_owns_key"));
+            }
+            policy.require_pusher_owns_key = require_pusher_owns_key;
+        }
+
+        Ok(policy)
+    }
+}
+
+fn weakened(field: &str) -> Error {
+    Error::from(ErrorKind::InvalidConfigField(
+        field.to_string(),
+        "must not weaken the central policy".to_string(),
+    ))
+}
+
+/// Effective configuration for verifying commit `sha` of `repository`
+///
+/// Returns a copy of the central configuration if per-repository policies are disabled or the
+/// repository does not have a policy file.
+pub fn effective_config<G: GitHub>(
+    github: &G,
+    repository: &Repository,
+    sha: &Sha,
+    central: &GitHubWatchTowerConfig,
+) -> Result<GitHubWatchTowerConfig> {
+    let settings = &central.repo_policy;
+    if !settings.enabled {
+        return Ok(central.clone());
+    }
+
+    let content = policy_file(github, repository, sha, central)?;
+    config_from_policy_file(repository, content.as_ref().map(String::as_str), central)
+}
+
+/// Effective configurations of a range of commits, cf. `effective_configs`
+#[derive(Debug, PartialEq)]
+pub struct EffectiveConfigs {
+    /// Distinct configurations
+    pub configs: Vec<GitHubWatchTowerConfig>,
+    /// Index into `configs` per commit
+    pub indices: Vec<usize>,
+}
+
+impl EffectiveConfigs {
+    /// The same configuration for `count` commits
+    pub fn uniform(config: GitHubWatchTowerConfig, count: usize) -> EffectiveConfigs {
+        EffectiveConfigs {
+            configs: vec![config],
+            indices: vec![0; count],
+        }
+    }
+}
+
+/// Effective configuration per commit of `repository`, cf. `effective_config`
+///
+/// The policy file is read once per commit if it is read at the commit, but merged once per
+/// distinct content only; otherwise, the configuration is the same for all commits.
+pub fn effective_configs<G: GitHub>(
+    github: &G,
+    repository: &Repository,
+    shas: &[&Sha],
+    central: &GitHubWatchTowerConfig,
+) -> Result<EffectiveConfigs> {
+    let settings = &central.repo_policy;
+    let first = match shas.first() {
+        Some(first) => first,
+        None => return Ok(EffectiveConfigs::uniform(central.clone(), 0)),
+    };
+    if !settings.enabled || settings.reference == RepoPolicyReference::DefaultBranch {
+        let config = effective_config(github, repository, first, central)?;
+        return Ok(EffectiveConfigs::uniform(config, shas.len()));
+    }
+
+    let mut contents: Vec<Option<String>> = Vec::new();
+    let mut indices = Vec::with_capacity(shas.len());
+    for sha in shas {
+        let content = policy_file(github, repository, sha, central)?;
+        let index = match contents.iter().position(|x| x == &content) {
+            Some(index) => index,
+            None => {
+                contents.push(content);
+                contents.len() - 1
+            }
+        };
+        indices.push(index);
+    }
+    let configs = contents
+        .iter()
+        .map(|x| config_from_policy_file(repository, x.as_ref().map(String::as_str), central))
+        .collect::<Result<_>>()?;
+
+    Ok(EffectiveConfigs { configs, indices })
+}
+
+/// Content of the policy file relevant for commit `sha`; `None` if there is none
+fn policy_file<G: GitHub>(
+    github: &G,
+    repository: &Repository,
+    sha: &Sha,
+    central: &GitHubWatchTowerConfig,
+) -> Result<Option<String>> {
+    let settings = &central.repo_policy;
+    let sha = sha.to_string();
+    let reference = match settings.reference {
+        RepoPolicyReference::Commit => Some(sha.as_str()),
+        RepoPolicyReference::DefaultBranch => None,
+    };
+
+    github.file_content(repository, &settings.path, reference)
+}
+
+fn config_from_policy_file(
+    repository: &Repository,
+    content: Option<&str>,
+    central: &GitHubWatchTowerConfig,
+) -> Result<GitHubWatchTowerConfig> {
+    let content = match content {
+        Some(content) => content,
+        None => {
+            debug!("Repository {:?} has no policy file", repository);
+            return Ok(central.clone());
+        }
+    };
+
+    let repo_config = RepoConfig::from_toml(content)?;
+    info!(
+        "Applying policy file '{}' of repository {:?}",
+        central.repo_policy.path, repository
+    );
+
+    repo_config.merge(central)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::RepoPolicyConfig,
+        utils::test::{self, FakeGitHub},
+    };
+
+    use spectral::prelude::*;
+
+    fn central_config() -> GitHubWatchTowerConfig {
+        toml::from_str(
+            r#"
+            [[pub_key]]
+            name = "Alice"
+            armored_key = "-----BEGIN PGP PUBLIC KEY BLOCK-----"
+
+            [[pub_key]]
+            name = "Bob"
+            armored_key = "-----BEGIN PGP PUBLIC KEY BLOCK-----"
+
+            [[team]]
+            name = "platform"
+            members = ["Alice", "Bob"]
+
+            [policy]
+            trust_web_flow = true
+            merge_mode = "introduced_commits"
+            "#,
+        )
+        .expect("failed to load config")
+    }
+
+    #[test]
+    fn merge_stricter_policy_okay() {
+        test::init();
+
+        let repo_config = RepoConfig::from_toml(
+            r#"
+            allowed_keys = ["Alice"]
+
+            [policy]
+            signature_mode = "all"
+            trust_web_flow = false
+            "#,
+        )
+        .expect("failed to load repo config");
+
+        let config = repo_config.merge(&central_config());
+
+        asserting("stricter policy is merged").that(&config).is_ok();
+        let config = config.unwrap();
+        asserting("signature mode is tightened")
+            .that(&config.policy.signature_mode)
+            .is_equal_to(&SignatureMode::All);
+        asserting("web-flow is not trusted anymore")
+            .that(&config.policy.trust_web_flow)
+            .is_false();
+        asserting("central merge mode is inherited")
+            .that(&config.policy.merge_mode)
+            .is_equal_to(&MergeMode::IntroducedCommits);
+        asserting("keys are narrowed")
+            .that(&config.pub_keys.len())
+            .is_equal_to(1);
+        asserting("team members are narrowed")
+            .that(&config.teams[0].members)
+            .is_equal_to(&vec!["Alice".to_string()]);
+    }
+
+    #[test]
+    fn merge_weaker_policy_failed() {
+        test::init();
+
+        let repo_config = RepoConfig::from_toml(
+            r#"
+            [policy]
+            merge_mode = "commit_only"
+            "#,
+        )
+        .expect("failed to load repo config");
+
+        let config = repo_config.merge(&central_config());
+
+        asserting("weaker policy is rejected")
+            .that(&config)
+            .is_err()
+            .map(|x| x.kind())
+            .is_equal_to(&ErrorKind::InvalidConfigField(
+                "policy.merge_mode".to_string(),
+                "must not weaken the central policy".to_string(),
+            ));
+    }
+
+    #[test]
+    fn merge_unknown_key_failed() {
+        test::init();
+
+        let repo_config = RepoConfig::from_toml(r#"allowed_keys = ["Mallory"]"#)
+            .expect("failed to load repo config");
+
+        let config = repo_config.merge(&central_config());
+
+        asserting("unknown key is rejected").that(&config).is_err();
+    }
+
+    #[test]
+    fn repo_config_cannot_add_keys() {
+        test::init();
+
+        let repo_config = RepoConfig::from_toml(
+            r#"
+            [[pub_key]]
+            name = "Mallory"
+            armored_key = "-----BEGIN PGP PUBLIC KEY BLOCK-----"
+            "#,
+        );
+
+        asserting("keys in repo config are rejected")
+            .that(&repo_config)
+            .is_err();
+    }
+
+    fn github(content: Option<&str>) -> FakeGitHub {
+        FakeGitHub::new().with_file_content(".github/watchtower.toml", content)
+    }
+
+    #[test]
+    fn effective_config_from_commit_okay() {
+        test::init();
+
+        let github = github(Some("[policy]\nsignature_mode = \"all\"\n"));
+        let repository = Repository::new("lukaspustina", "github-watchtower");
+        let sha = Sha::new("a7fb5ae8ad4b7a2e0b4a4d2a2b0d5b0a9f4c5e0e");
+        let mut central = central_config();
+        central.repo_policy = RepoPolicyConfig {
+            enabled: true,
+            ..Default::default()
+        };
+
+        let config = effective_config(&github, &repository, &sha, &central);
+
+        asserting("repo policy is applied")
+            .that(&config)
+            .is_ok()
+            .map(|x| &x.policy.signature_mode)
+            .is_equal_to(&SignatureMode::All);
+        asserting("policy file is read at the commit")
+            .that(&github.requested_references())
+            .is_equal_to(&vec![Some(sha.to_string())]);
+    }
+
+    #[test]
+    fn effective_config_from_default_branch_without_file_okay() {
+        test::init();
+
+        let github = github(None);
+        let repository = Repository::new("lukaspustina", "github-watchtower");
+        let sha = Sha::new("a7fb5ae8ad4b7a2e0b4a4d2a2b0d5b0a9f4c5e0e");
+        let mut central = central_config();
+        central.repo_policy = RepoPolicyConfig {
+            enabled: true,
+            reference: RepoPolicyReference::DefaultBranch,
+            ..Default::default()
+        };
+
+        let config = effective_config(&github, &repository, &sha, &central);
+
+        asserting("central config is used")
+            .that(&config)
+            .is_ok()
+            .is_equal_to(&central);
+        asserting("policy file is read at the default branch")
+            .that(&github.requested_references())
+            .is_equal_to(&vec![None]);
+    }
+
+    #[test]
+    fn effective_configs_per_policy_file_okay() {
+        test::init();
+
+        let github = github(Some("[policy]\nsignature_mode = \"all\"\n")).with_file_content_at(
+            ".github/watchtower.toml",
+            "0d5b0a9f4c5e0ea7fb5ae8ad4b7a2e0b4a4d2a2b",
+            None,
+        );
+        let repository = Repository::new("lukaspustina", "github-watchtower");
+        let head = Sha::new("a7fb5ae8ad4b7a2e0b4a4d2a2b0d5b0a9f4c5e0e");
+        let parent = Sha::new("b4a4d2a2b0d5b0a9f4c5e0ea7fb5ae8ad4b7a2e0");
+        let first = Sha::new("0d5b0a9f4c5e0ea7fb5ae8ad4b7a2e0b4a4d2a2b");
+        let mut central = central_config();
+        central.repo_policy = RepoPolicyConfig {
+            enabled: true,
+            ..Default::default()
+        };
+
+        let configs = effective_configs(&github, &repository, &[&head, &parent, &first], &central);
+
+        asserting("configs are determined")
+            .that(&configs)
+            .is_ok()
+            .map(|x| &x.indices)
+            .is_equal_to(&vec![0, 0, 1]);
+        let configs = configs.unwrap();
+        asserting("repo policy is applied where the file exists")
+            .that(&configs.configs[0].policy.signature_mode)
+            .is_equal_to(&SignatureMode::All);
+        asserting("central config is used where the file does not exist")
+            .that(&configs.configs[1])
+            .is_equal_to(&central);
+        asserting("policy file is read at each commit")
+            .that(&github.requested_references())
+            .is_equal_to(&vec![
+                Some(head.to_string()),
+                Some(parent.to_string()),
+                Some(first.to_string()),
+            ]);
+    }
+}
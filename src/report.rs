@@ -0,0 +1,422 @@
+//! Machine readable reports of commit verdicts
+//!
+//! Supported formats are JSON, JSON Lines, SARIF 2.1.0 for code scanning dashboards, and JUnit XML
+//! for CI systems. The human readable table is available as well, cf. `verdict::write_table`.
+
+use crate::{
+    errors::*,
+    github::commits::Reason,
+    verdict::{self, CommitVerdict, Rule},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{self, json};
+use std::{
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
+
+static TOOL_NAME: &str = "github-watchtower";
+static TOOL_URI: &str = "https://github.com/lukaspustina/github-watchtower";
+static SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Json,
+    JsonLines,
+    Sarif,
+    JUnit,
+}
+
+impl Format {
+    pub const NAMES: &'static [&'static str] = &["table", "json", "json-lines", "sarif", "junit"];
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Format> {
+        match s {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "json-lines" => Ok(Format::JsonLines),
+            "sarif" => Ok(Format::Sarif),
+            "junit" => Ok(Format::JUnit),
+            _ => Err(Error::from(ErrorKind::InvalidReportFormat(s.to_string()))),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Format::Table => "table",
+            Format::Json => "json",
+            Format::JsonLines => "json-lines",
+            Format::Sarif => "sarif",
+            Format::JUnit => "junit",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Verified,
+    Violation,
+}
+
+/// A verdict as reported
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReportEntry {
+    pub sha: String,
+    pub author: String,
+    /// Fingerprints of the keys the commit has been verified with
+    pub signers: Vec<String>,
+    pub source_reason: Option<Reason>,
+    pub decision: Decision,
+    pub rule: String,
+    pub details: String,
+}
+
+impl From<&CommitVerdict> for ReportEntry {
+    fn from(verdict: &CommitVerdict) -> ReportEntry {
+        let signers = match verdict.keys {
+            Ok(ref keys) => keys.iter().map(|x| x.finger_print().to_string()).collect(),
+            Err(_) => Vec::new(),
+        };
+
+        ReportEntry {
+            sha: verdict.sha.to_string(),
+            author: verdict.author.clone(),
+            signers,
+            source_reason: verdict.reason,
+            decision: if verdict.is_verified() {
+                Decision::Verified
+            } else {
+                Decision::Violation
+            },
+            rule: verdict.rule().id().to_string(),
+            details: verdict.details(),
+        }
+    }
+}
+
+/// Writes verdicts in `format`
+pub fn write<W: Write>(
+    writer: &mut W,
+    format: Format,
+    verdicts: &[CommitVerdict],
+) -> io::Result<()> {
+    let entries: Vec<_> = verdicts.iter().map(ReportEntry::from).collect();
+
+    match format {
+        Format::Table => verdict::write_table(writer, verdicts),
+        Format::Json => write_json(writer, &entries),
+        Format::JsonLines => write_json_lines(writer, &entries),
+        Format::Sarif => write_sarif(writer, &entries),
+        Format::JUnit => write_junit(writer, &entries),
+    }
+}
+
+fn write_json<W: Write>(writer: &mut W, entries: &[ReportEntry]) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut *writer, entries)?;
+    writeln!(writer)
+}
+
+fn write_json_lines<W: Write>(writer: &mut W, entries: &[ReportEntry]) -> io::Result<()> {
+    for entry in entries {
+        serde_json::to_writer(&mut *writer, entry)?;
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// See https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+fn write_sarif<W: Write>(writer: &mut W, entries: &[ReportEntry]) -> io::Result<()> {
+    let rules: Vec<_> = Rule::ALL
+        .iter()
+        .map(|x| {
+            json!({
+                "id": x.id(),
+                "shortDescription": { "text": x.description() },
+            })
+        })
+        .collect();
+    let results: Vec<_> = entries
+        .iter()
+        .map(|x| {
+            let (kind, level) = match x.decision {
+                Decision::Verified => ("pass", "none"),
+                Decision::Violation => ("fail", "error"),
+            };
+            json!({
+                "ruleId": x.rule,
+                "kind": kind,
+                "level": level,
+                "message": { "text": format!("Commit {} by {}: {}", x.sha, x.author, x.details) },
+                "locations": [{
+                    "logicalLocations": [{
+                        "name": x.sha,
+                        "fullyQualifiedName": format!("commit/{}", x.sha),
+                        "kind": "commit",
+                    }],
+                }],
+                "partialFingerprints": { "commitSha": x.sha },
+                "properties": {
+                    "author": x.author,
+                    "signers": x.signers,
+                    "sourceReason": x.source_reason,
+                },
+            })
+        })
+        .collect();
+
+    let sarif = json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": TOOL_NAME,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": TOOL_URI,
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    });
+
+    serde_json::to_writer_pretty(&mut *writer, &sarif)?;
+    writeln!(writer)
+}
+
+fn write_junit<W: Write>(writer: &mut W, entries: &[ReportEntry]) -> io::Result<()> {
+    let failures = entries
+        .iter()
+        .filter(|x| x.decision == Decision::Violation)
+        .count();
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<testsuites name="{name}" tests="{tests}" failures="{failures}">"#,
+        name = TOOL_NAME,
+        tests = entries.len(),
+        failures = failures
+    )?;
+    writeln!(
+        writer,
+        r#"  <testsuite name="commits" tests="{tests}" failures="{failures}">"#,
+        tests = entries.len(),
+        failures = failures
+    )?;
+    for entry in entries {
+        let name = xml_escape(&format!("{} ({})", entry.sha, entry.author));
+        match entry.decision {
+            Decision::Verified => writeln!(
+                writer,
+                r#"    <testcase classname="{}" name="{}"/>"#,
+                entry.rule, name
+            )?,
+            Decision::Violation => {
+                writeln!(
+                    writer,
+                    r#"    <testcase classname="{}" name="{}">"#,
+                    entry.rule, name
+                )?;
+                writeln!(
+                    writer,
+                    r#"      <failure type="{}" message="{}"/>"#,
+                    entry.rule,
+                    xml_escape(&entry.details)
+                )?;
+                writeln!(writer, "    </testcase>")?;
+            }
+        }
+    }
+    writeln!(writer, "  </testsuite>")?;
+    writeln!(writer, "</testsuites>")
+}
+
+/// Escapes an attribute value; characters XML 1.0 does not allow are replaced by U+FFFD
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // White space would be normalized to spaces in attribute values
+            '\t' | '\n' | '\r' => escaped.push_str(&format!("&#{};", c as u32)),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => escaped.push('\u{fffd}'),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{github::commits::Sha, gpg::VerificationKey, utils::test};
+
+    use serde_json::Value;
+    use spectral::prelude::*;
+
+    fn verdicts() -> Vec<CommitVerdict> {
+        vec![
+            CommitVerdict {
+                sha: Sha::new("c255ad2347d00cae3dd2d7a21e1357e50413fc4f"),
+                author: "Second Test <second@example.com>".to_string(),
+                signed: true,
+                reason: Some(Reason::Valid),
+                keys: Ok(vec![VerificationKey::from_ssh_key(
+                    "SHA256:Bc8bOb5eTLsyi6wiwJhuy5mbhwWGjOFq3bfOu6nvqE8".to_string(),
+                    vec!["second@example.com".to_string()],
+                )]),
+            },
+            CommitVerdict {
+                sha: Sha::new("5b1c1fa6dd0f4f3a9ab3a5cb9ef4c1f1e1ee9ff4"),
+                author: "Mallory <mallory@example.com>".to_string(),
+                signed: false,
+                reason: Some(Reason::Unsigned),
+                keys: Err(Error::from(ErrorKind::FailedToVerify(
+                    "verification object is invalid".to_string(),
+                ))),
+            },
+        ]
+    }
+
+    fn report(format: Format) -> String {
+        let mut report = Vec::new();
+        write(&mut report, format, &verdicts()).expect("failed to write report");
+
+        String::from_utf8(report).expect("report is not UTF-8")
+    }
+
+    #[test]
+    fn format_from_str() {
+        test::init();
+
+        let formats: Vec<_> = Format::NAMES.iter().map(|x| x.parse::<Format>()).collect();
+
+        asserting("all names are formats")
+            .that(&formats.iter().all(|x| x.is_ok()))
+            .is_true();
+        asserting("names are displayed")
+            .that(
+                &formats
+                    .iter()
+                    .map(|x| x.as_ref().unwrap().to_string())
+                    .collect::<Vec<_>>(),
+            )
+            .is_equal_to(
+                Format::NAMES
+                    .iter()
+                    .map(|x| x.to_string())
+                    .collect::<Vec<_>>(),
+            );
+        asserting("unknown format is rejected")
+            .that(&"xml".parse::<Format>())
+            .is_err();
+    }
+
+    #[test]
+    fn write_json_lines_okay() {
+        test::init();
+
+        let report = report(Format::JsonLines);
+        let lines: Vec<Value> = report
+            .lines()
+            .map(|x| serde_json::from_str(x).expect("line is not JSON"))
+            .collect();
+
+        asserting("one line per verdict").that(&lines).has_length(2);
+        asserting("verified entry")
+            .that(&lines[0])
+            .is_equal_to(&json!({
+                "sha": "c255ad2347d00cae3dd2d7a21e1357e50413fc4f",
+                "author": "Second Test <second@example.com>",
+                "signers": ["SHA256:Bc8bOb5eTLsyi6wiwJhuy5mbhwWGjOFq3bfOu6nvqE8"],
+                "source_reason": "valid",
+                "decision": "verified",
+                "rule": "trusted-signature",
+                "details": "SHA256:Bc8bOb5eTLsyi6wiwJhuy5mbhwWGjOFq3bfOu6nvqE8",
+            }));
+        asserting("violation entry")
+            .that(&lines[1]["rule"])
+            .is_equal_to(&json!("signed"));
+    }
+
+    #[test]
+    fn write_sarif_okay() {
+        test::init();
+
+        let sarif: Value = serde_json::from_str(&report(Format::Sarif)).expect("SARIF is not JSON");
+
+        asserting("version is 2.1.0")
+            .that(&sarif["version"])
+            .is_equal_to(&json!("2.1.0"));
+        asserting("all rules are described")
+            .that(
+                &sarif["runs"][0]["tool"]["driver"]["rules"]
+                    .as_array()
+                    .map(Vec::len),
+            )
+            .is_equal_to(&Some(Rule::ALL.len()));
+        asserting("violation is an error")
+            .that(&sarif["runs"][0]["results"][1]["level"])
+            .is_equal_to(&json!("error"));
+        asserting("violation refers to rule")
+            .that(&sarif["runs"][0]["results"][1]["ruleId"])
+            .is_equal_to(&json!("signed"));
+        asserting("violation is located at its commit")
+            .that(&sarif["runs"][0]["results"][1]["locations"][0]["logicalLocations"][0])
+            .is_equal_to(&json!({
+                "name": "5b1c1fa6dd0f4f3a9ab3a5cb9ef4c1f1e1ee9ff4",
+                "fullyQualifiedName": "commit/5b1c1fa6dd0f4f3a9ab3a5cb9ef4c1f1e1ee9ff4",
+                "kind": "commit",
+            }));
+    }
+
+    #[test]
+    fn write_junit_okay() {
+        test::init();
+
+        let junit = report(Format::JUnit);
+
+        asserting("counts failures")
+            .that(&junit.contains(r#"<testsuite name="commits" tests="2" failures="1">"#))
+            .is_true();
+        asserting("author is escaped")
+            .that(&junit.contains(
+                r#"name="5b1c1fa6dd0f4f3a9ab3a5cb9ef4c1f1e1ee9ff4 (Mallory &lt;mallory@example.com&gt;)""#,
+            ))
+            .is_true();
+        asserting("failure has rule and reason")
+            .that(&junit.contains(
+                r#"<failure type="signed" message="failed to verify GPG signature because verification object is invalid"/>"#,
+            ))
+            .is_true();
+    }
+
+    #[test]
+    fn xml_escape_invalid_characters() {
+        test::init();
+
+        let escaped = xml_escape("Mallory\u{0}\u{1b}[31m <evil>\nline\u{ffff}");
+
+        asserting("characters invalid in XML 1.0 are replaced, white space is kept")
+            .that(&escaped.as_str())
+            .is_equal_to("Mallory\u{fffd}\u{fffd}[31m &lt;evil&gt;&#10;line\u{fffd}");
+    }
+}
@@ -0,0 +1,273 @@
+//! Sources of commits to verify
+//!
+//! Verification, policies, and reporting work on `SignedCommit`s, so they do not depend on where
+//! commits have been retrieved from, e.g., GitHub's REST API, a local git repository, or a JSON
+//! fixture file.
+
+use crate::{
+    errors::*,
+    github::{
+        commits::{Commit, Params, PersonDetails, Reason, Sha},
+        GitHub, Repository,
+    },
+};
+
+use chrono::{DateTime, FixedOffset};
+use failure::Fail;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::{fs, path::Path};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Person {
+    pub name: String,
+    pub email: String,
+    pub date: DateTime<FixedOffset>,
+}
+
+impl From<&PersonDetails> for Person {
+    fn from(person: &PersonDetails) -> Person {
+        Person {
+            name: person.name.clone(),
+            email: person.email.clone(),
+            date: person.date,
+        }
+    }
+}
+
+/// A commit together with its signature and the signed payload
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SignedCommit {
+    pub sha: Sha,
+    #[serde(default)]
+    pub parents: Vec<Sha>,
+    pub author: Person,
+    pub committer: Person,
+    pub message: String,
+    pub signature: Option<String>,
+    pub payload: Option<String>,
+    /// Verification result of the source, e.g., GitHub's; `None` if the source does not verify
+    /// signatures itself
+    #[serde(default)]
+    pub source_reason: Option<Reason>,
+}
+
+impl SignedCommit {
+    pub fn is_signed(&self) -> bool {
+        self.signature.is_some()
+    }
+}
+
+impl From<&Commit> for SignedCommit {
+    fn from(commit: &Commit) -> SignedCommit {
+        let verification = &commit.commit.verification;
+        // GitHub reports the reason even if a commit has not been verified, but `valid` only if
+        // it has been
+        let source_reason = if verification.verified || verification.reason != Reason::Valid {
+            verification.reason
+        } else {
+            Reason::Invalid
+        };
+
+        SignedCommit {
+            sha: commit.sha.clone(),
+            parents: commit.parents.iter().map(|x| x.sha.clone()).collect(),
+            author: Person::from(&commit.commit.author),
+            committer: Person::from(&commit.commit.committer),
+            message: commit.commit.message.clone(),
+            signature: verification.signature.clone(),
+            payload: verification.payload.clone(),
+            source_reason: Some(source_reason),
+        }
+    }
+}
+
+pub trait CommitSource {
+    /// Commits from `to`, or the source's head if not set, back to and including `from`, or to the
+    /// first commit if not set; newest first
+    fn commits(&self, from: Option<&Sha>, to: Option<&Sha>) -> Result<Vec<SignedCommit>>;
+    /// A single commit, e.g., the parent of a commit outside of a range
+    fn commit(&self, sha: &Sha) -> Result<SignedCommit>;
+}
+
+/// Commits of a repository retrieved via GitHub's REST API
+pub struct GitHubSource<'a, G: GitHub> {
+    github: &'a G,
+    repository: &'a Repository<'a>,
+}
+
+impl<'a, G: GitHub> GitHubSource<'a, G> {
+    pub fn new(github: &'a G, repository: &'a Repository<'a>) -> GitHubSource<'a, G> {
+        GitHubSource { github, repository }
+    }
+}
+
+impl<'a, G: GitHub> CommitSource for GitHubSource<'a, G> {
+    fn commits(&self, from: Option<&Sha>, to: Option<&Sha>) -> Result<Vec<SignedCommit>> {
+        // GitHub lists commits starting at its `sha` parameter, i.e., the newest commit
+        let mut params = Params::new();
+        if let Some(to) = to {
+            params = params.from(to.clone());
+        }
+        if let Some(from) = from {
+            params = params.to(from.clone());
+        }
+        let commits = self.github.commits(self.repository, params)?;
+
+        Ok(commits.iter().map(SignedCommit::from).collect())
+    }
+
+    fn commit(&self, sha: &Sha) -> Result<SignedCommit> {
+        let commit = self.github.commit(self.repository, sha)?;
+
+        Ok(SignedCommit::from(&commit))
+    }
+}
+
+/// Commits read from a JSON file containing an array of `SignedCommit`s, newest first
+#[derive(Debug)]
+pub struct FixtureSource {
+    commits: Vec<SignedCommit>,
+}
+
+impl FixtureSource {
+    pub fn new(commits: Vec<SignedCommit>) -> FixtureSource {
+        FixtureSource { commits }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(file_path: P) -> Result<FixtureSource> {
+        let json = fs::read_to_string(file_path.as_ref()).map_err(|e| {
+            e.context(ErrorKind::InvalidCommitSource(format!(
+                "failed to read {}",
+                file_path.as_ref().display()
+            )))
+        })?;
+
+        FixtureSource::from_json(&json)
+    }
+
+    pub fn from_json(json: &str) -> Result<FixtureSource> {
+        let commits = serde_json::from_str(json).map_err(|e| {
+            let reason = e.to_string();
+            e.context(ErrorKind::InvalidCommitSource(reason))
+        })?;
+
+        Ok(FixtureSource::new(commits))
+    }
+
+    fn position(&self, sha: &Sha) -> Result<usize> {
+        self.commits
+            .iter()
+            .position(|x| &x.sha == sha)
+            .ok_or_else(|| {
+                Error::from(ErrorKind::InvalidCommitSource(format!(
+                    "commit {} not found",
+                    sha
+                )))
+            })
+    }
+}
+
+impl CommitSource for FixtureSource {
+    fn commits(&self, from: Option<&Sha>, to: Option<&Sha>) -> Result<Vec<SignedCommit>> {
+        let start = match to {
+            Some(to) => self.position(to)?,
+            None => 0,
+        };
+        let end = match from {
+            Some(from) => self.position(from)? + 1,
+            None => self.commits.len(),
+        };
+        if start >= end {
+            return Ok(Vec::new());
+        }
+
+        Ok(self.commits[start..end].to_vec())
+    }
+
+    fn commit(&self, sha: &Sha) -> Result<SignedCommit> {
+        let position = self.position(sha)?;
+
+        Ok(self.commits[position].clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test;
+
+    use spectral::prelude::*;
+
+    fn signed_commit(sha: &str) -> SignedCommit {
+        let person = Person {
+            name: "Second Test".to_string(),
+            email: "second@example.com".to_string(),
+            date: DateTime::parse_from_rfc3339("2019-06-25T12:00:00+02:00").unwrap(),
+        };
+
+        SignedCommit {
+            sha: Sha::new(sha),
+            parents: Vec::new(),
+            author: person.clone(),
+            committer: person,
+            message: "Test commit".to_string(),
+            signature: None,
+            payload: None,
+            source_reason: None,
+        }
+    }
+
+    #[test]
+    fn fixture_source_commits_okay() {
+        test::init();
+
+        let commits = vec![signed_commit("3"), signed_commit("2"), signed_commit("1")];
+        let json = serde_json::to_string(&commits).expect("failed to serialize commits");
+        let source = FixtureSource::from_json(&json).expect("failed to load fixture");
+
+        asserting("all commits")
+            .that(&source.commits(None, None))
+            .is_ok()
+            .is_equal_to(&commits);
+        asserting("range includes both ends")
+            .that(&source.commits(Some(&Sha::new("2")), Some(&Sha::new("3"))))
+            .is_ok()
+            .has_length(2);
+        asserting("unknown commit is rejected")
+            .that(&source.commits(Some(&Sha::new("4")), None))
+            .is_err();
+        asserting("single commit")
+            .that(&source.commit(&Sha::new("2")))
+            .is_ok()
+            .is_equal_to(&commits[1]);
+    }
+
+    #[test]
+    fn from_unverified_github_commit() {
+        test::init();
+
+        let commit: Commit = serde_json::from_str(
+            r#"{
+                "sha": "c255ad2347d00cae3dd2d7a21e1357e50413fc4f",
+                "commit": {
+                    "author": { "name": "Second Test", "email": "second@example.com", "date": "2019-06-25T12:00:00Z" },
+                    "committer": { "name": "Second Test", "email": "second@example.com", "date": "2019-06-25T12:00:00Z" },
+                    "message": "Test commit",
+                    "verification": { "verified": false, "reason": "unknown_key", "signature": "sig", "payload": "payload" }
+                },
+                "parents": [ { "sha": "5b1c1fa6dd0f4f3a9ab3a5cb9ef4c1f1e1ee9ff4" } ]
+            }"#,
+        )
+        .expect("failed to deserialize commit");
+
+        let signed_commit = SignedCommit::from(&commit);
+
+        asserting("GitHub's reason is kept")
+            .that(&signed_commit.source_reason)
+            .is_equal_to(&Some(Reason::UnknownKey));
+        asserting("parents are kept")
+            .that(&signed_commit.parents)
+            .is_equal_to(&vec![Sha::new("5b1c1fa6dd0f4f3a9ab3a5cb9ef4c1f1e1ee9ff4")]);
+    }
+}
@@ -0,0 +1,666 @@
+//! Verification of SSH commit signatures as created by Git with `gpg.format=ssh`
+//!
+//! See https://github.com/openssh/openssh-portable/blob/master/PROTOCOL.sshsig for the signature
+//! format and `ssh-keygen(1)`, section "ALLOWED SIGNERS", for the format of allowed signers.
+
+use crate::{
+    config::AllowedSigner,
+    errors::*,
+    gpg::VerificationKey,
+    utils::{armor, sha256},
+};
+
+use base64;
+use failure::Fail;
+use log::debug;
+use ring::{
+    digest,
+    signature::{self, primitive, RsaParameters, VerificationAlgorithm},
+};
+use std::convert::TryFrom;
+use untrusted::Input;
+
+pub(crate) static SSH_SIGNATURE_ARMOR_BEGIN: &str = "-----BEGIN SSH SIGNATURE-----";
+static SSH_SIGNATURE_ARMOR_END: &str = "-----END SSH SIGNATURE-----";
+static SSH_SIGNATURE_MAGIC: &[u8] = b"SSHSIG";
+static SSH_SIGNATURE_VERSION: u32 = 1;
+static GIT_NAMESPACE: &str = "git";
+
+#[derive(Debug, Default)]
+pub struct SshVerifier {
+    signers: Vec<SshSigner>,
+}
+
+/// A public key together with the principals, i.e., e-mail addresses, it is allowed to sign for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshSigner {
+    principals: Vec<String>,
+    namespaces: Option<Vec<String>>,
+    public_key: PublicKey,
+}
+
+impl SshSigner {
+    pub fn new<T: Into<String>>(principals: Vec<String>, public_key: T) -> Result<SshSigner> {
+        let public_key = PublicKey::try_from(public_key.into().as_str())?;
+
+        Ok(SshSigner {
+            principals,
+            namespaces: None,
+            public_key,
+        })
+    }
+
+    fn allows_namespace(&self, namespace: &str) -> bool {
+        match self.namespaces {
+            Some(ref namespaces) => namespaces.iter().any(|x| x == namespace),
+            None => true,
+        }
+    }
+}
+
+impl TryFrom<&AllowedSigner> for SshSigner {
+    type Error = Error;
+
+    fn try_from(allowed_signer: &AllowedSigner) -> Result<SshSigner> {
+        let signer = SshSigner::new(
+            allowed_signer.principals.clone(),
+            allowed_signer.public_key.as_str(),
+        )?;
+
+        Ok(SshSigner {
+            namespaces: allowed_signer.namespaces.clone(),
+            ..signer
+        })
+    }
+}
+
+/// An SSH public key in wire format
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PublicKey {
+    algorithm: String,
+    blob: Vec<u8>,
+}
+
+impl PublicKey {
+    /// Fingerprint in the format `ssh-keygen -l` prints it, e.g., `SHA256:XJfxWWQsn7mf...`
+    fn finger_print(&self) -> String {
+        let sha = digest::digest(&digest::SHA256, &self.blob);
+        format!(
+            "SHA256:{}",
+            base64::encode_config(sha.as_ref(), base64::STANDARD_NO_PAD)
+        )
+    }
+}
+
+/// Parses a public key in `authorized_keys` format, i.e., `<algorithm> <base64 blob> [comment]`
+impl TryFrom<&str> for PublicKey {
+    type Error = Error;
+
+    fn try_from(value: &str) -> Result<PublicKey> {
+        let mut fields = value.split_whitespace();
+        let (algorithm, blob) = match (fields.next(), fields.next()) {
+            (Some(algorithm), Some(blob)) => (algorithm, blob),
+            _ => return Err(Error::from(ErrorKind::FailedToLoadKey)),
+        };
+        let blob = base64::decode(blob).map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
+
+        let mut reader = WireReader::new(&blob);
+        if reader.read_string()? != algorithm.as_bytes() {
+            return Err(Error::from(ErrorKind::FailedToLoadKey));
+        }
+
+        Ok(PublicKey {
+            algorithm: algorithm.to_string(),
+            blob,
+        })
+    }
+}
+
+impl SshVerifier {
+    pub fn new(signers: Vec<SshSigner>) -> SshVerifier {
+        SshVerifier { signers }
+    }
+
+    /// Parses signers from the contents of an `allowed_signers` file
+    pub fn from_allowed_signers(allowed_signers: &str) -> Result<SshVerifier> {
+        let signers: Result<Vec<_>> = allowed_signers
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty() && !x.starts_with('#'))
+            .map(parse_allowed_signer)
+            .collect();
+
+        Ok(SshVerifier::new(signers?))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signers.is_empty()
+    }
+
+    pub fn finger_prints(&self) -> Vec<String> {
+        self.signers
+            .iter()
+            .map(|x| x.public_key.finger_print())
+            .collect()
+    }
+
+    /// SHA-256 over all signers including their principals and options
+    pub(crate) fn version(&self) -> String {
+        sha256::from_bytes_as_str(format!("{:?}", self.signers).as_bytes())
+    }
+
+    pub fn verify(&self, message: &[u8], armored_signature: &str) -> Result<VerificationKey> {
+        let signature = dearmor(armored_signature)?;
+        let signature = SshSignature::try_from(signature.as_slice())?;
+
+        if signature.namespace != GIT_NAMESPACE.as_bytes() {
+            return Err(Error::from(ErrorKind::FailedToVerify(
+                "SSH signature has not been created for git".to_string(),
+            )));
+        }
+
+        let signer = self
+            .signers
+            .iter()
+            .find(|x| x.public_key.blob == signature.public_key)
+            .ok_or_else(|| {
+                Error::from(ErrorKind::FailedToVerify(
+                    "Missing key to verify signature".to_string(),
+                ))
+            })?;
+        if !signer.allows_namespace(GIT_NAMESPACE) {
+            return Err(Error::from(ErrorKind::FailedToVerify(
+                "SSH key is not allowed to sign for git".to_string(),
+            )));
+        }
+
+        let signed_data = signature.signed_data(message)?;
+        verify_signature(&signer.public_key, &signed_data, signature.signature)?;
+
+        let key = VerificationKey::from_ssh_key(
+            signer.public_key.finger_print(),
+            signer.principals.clone(),
+        );
+        debug!("Message successfully verified with key {:?}", key);
+
+        Ok(key)
+    }
+}
+
+/// Parses a line of an `allowed_signers` file: `<principals> [options] <algorithm> <base64 blob>`
+///
+/// Only the `namespaces` option is supported. Lines with other options, e.g., `valid-before` or
+/// `cert-authority`, are rejected, because ignoring a restriction would widen trust.
+fn parse_allowed_signer(line: &str) -> Result<SshSigner> {
+    let tokens = split_unquoted(line, char::is_whitespace);
+    let key_pos = tokens
+        .iter()
+        .position(|x| is_key_algorithm(x))
+        .ok_or_else(|| Error::from(ErrorKind::FailedToLoadKey))?;
+    if key_pos == 0 {
+        return Err(Error::from(ErrorKind::FailedToLoadKey));
+    }
+
+    let principals = tokens[0].split(',').map(ToString::to_string).collect();
+    let mut signer = SshSigner::new(principals, tokens[key_pos..].join(" "))?;
+
+    for option in tokens[1..key_pos]
+        .iter()
+        .flat_map(|x| split_unquoted(x, |c| c == ','))
+    {
+        let mut kv = option.splitn(2, '=');
+        match (kv.next(), kv.next()) {
+            (Some(key), Some(value)) if key.eq_ignore_ascii_case("namespaces") => {
+                let namespaces = value.trim_matches('"').split(',');
+                signer.namespaces = Some(namespaces.map(ToString::to_string).collect());
+            }
+            _ => {
+                return Err(failure::err_msg(format!(
+                    "unsupported allowed signers option '{}'",
+                    option
+                ))
+                .context(ErrorKind::FailedToLoadKey)
+                .into())
+            }
+        }
+    }
+
+    Ok(signer)
+}
+
+/// Splits at separators unless quoted
+fn split_unquoted<F: Fn(char) -> bool>(line: &str, is_separator: F) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            c if is_separator(c) && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(current);
+                    current = String::new();
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+fn is_key_algorithm(token: &str) -> bool {
+    token.starts_with("ssh-") || token.starts_with("ecdsa-") || token.starts_with("sk-")
+}
+
+fn dearmor(armored_signature: &str) -> Result<Vec<u8>> {
+    armor::dearmor(
+        SSH_SIGNATURE_ARMOR_BEGIN,
+        SSH_SIGNATURE_ARMOR_END,
+        armored_signature,
+        "SSH signature",
+    )
+}
+
+#[derive(Debug)]
+struct SshSignature<'a> {
+    public_key: &'a [u8],
+    namespace: &'a [u8],
+    reserved: &'a [u8],
+    hash_algorithm: &'a [u8],
+    signature: &'a [u8],
+}
+
+impl<'a> TryFrom<&'a [u8]> for SshSignature<'a> {
+    type Error = Error;
+
+    fn try_from(bytes: &'a [u8]) -> Result<SshSignature<'a>> {
+        let mut reader = WireReader::new(bytes);
+        if reader.read_bytes(SSH_SIGNATURE_MAGIC.len())? != SSH_SIGNATURE_MAGIC {
+            return Err(malformed_signature());
+        }
+        if reader.read_u32()? != SSH_SIGNATURE_VERSION {
+            return Err(Error::from(ErrorKind::FailedToVerify(
+                "SSH signature version is not supported".to_string(),
+            )));
+        }
+
+        Ok(SshSignature {
+            public_key: reader.read_string()?,
+            namespace: reader.read_string()?,
+            reserved: reader.read_string()?,
+            hash_algorithm: reader.read_string()?,
+            signature: reader.read_string()?,
+        })
+    }
+}
+
+impl<'a> SshSignature<'a> {
+    /// Reconstructs the blob that actually has been signed
+    fn signed_data(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let algorithm = match self.hash_algorithm {
+            b"sha256" => &digest::SHA256,
+            b"sha512" => &digest::SHA512,
+            _ => {
+                return Err(Error::from(ErrorKind::FailedToVerify(
+                    "SSH signature hash algorithm is not supported".to_string(),
+                )))
+            }
+        };
+        let hash = digest::digest(algorithm, message);
+
+        let mut data = SSH_SIGNATURE_MAGIC.to_vec();
+        for field in &[
+            self.namespace,
+            self.reserved,
+            self.hash_algorithm,
+            hash.as_ref(),
+        ] {
+            write_string(&mut data, field);
+        }
+
+        Ok(data)
+    }
+}
+
+fn verify_signature(public_key: &PublicKey, data: &[u8], signature: &[u8]) -> Result<()> {
+    let mut key_reader = WireReader::new(&public_key.blob);
+    let _ = key_reader.read_string()?;
+    let mut signature_reader = WireReader::new(signature);
+    let signature_algorithm = signature_reader.read_string()?;
+    let signature = signature_reader.read_string()?;
+
+    let res = match (public_key.algorithm.as_str(), signature_algorithm) {
+        ("ssh-ed25519", b"ssh-ed25519") => {
+            let key = key_reader.read_string()?;
+            signature::ED25519.verify(Input::from(key), Input::from(data), Input::from(signature))
+        }
+        ("ssh-rsa", b"rsa-sha2-256") => verify_rsa(
+            &mut key_reader,
+            &signature::RSA_PKCS1_2048_8192_SHA256,
+            data,
+            signature,
+        )?,
+        ("ssh-rsa", b"rsa-sha2-512") => verify_rsa(
+            &mut key_reader,
+            &signature::RSA_PKCS1_2048_8192_SHA512,
+            data,
+            signature,
+        )?,
+        ("ecdsa-sha2-nistp256", b"ecdsa-sha2-nistp256") => verify_ecdsa(
+            &mut key_reader,
+            &signature::ECDSA_P256_SHA256_FIXED,
+            32,
+            data,
+            signature,
+        )?,
+        ("ecdsa-sha2-nistp384", b"ecdsa-sha2-nistp384") => verify_ecdsa(
+            &mut key_reader,
+            &signature::ECDSA_P384_SHA384_FIXED,
+            48,
+            data,
+            signature,
+        )?,
+        _ => {
+            return Err(Error::from(ErrorKind::FailedToVerify(
+                "SSH signature algorithm is not supported".to_string(),
+            )))
+        }
+    };
+
+    res.map_err(|_| Error::from(ErrorKind::FailedToVerify("Bad signature".to_string())))
+}
+
+fn verify_rsa(
+    key_reader: &mut WireReader,
+    parameters: &RsaParameters,
+    data: &[u8],
+    signature: &[u8],
+) -> Result<::std::result::Result<(), ring::error::Unspecified>> {
+    let e = strip_leading_zeros(key_reader.read_string()?);
+    let n = strip_leading_zeros(key_reader.read_string()?);
+
+    Ok(primitive::verify_rsa(
+        parameters,
+        (Input::from(n), Input::from(e)),
+        Input::from(data),
+        Input::from(signature),
+    ))
+}
+
+fn verify_ecdsa(
+    key_reader: &mut WireReader,
+    algorithm: &dyn VerificationAlgorithm,
+    scalar_len: usize,
+    data: &[u8],
+    signature: &[u8],
+) -> Result<::std::result::Result<(), ring::error::Unspecified>> {
+    let _curve = key_reader.read_string()?;
+    let point = key_reader.read_string()?;
+
+    // SSH encodes r and s as mpints, but ring expects them as fixed length, big endian integers.
+    let mut signature_reader = WireReader::new(signature);
+    let mut fixed = Vec::with_capacity(2 * scalar_len);
+    for _ in 0..2 {
+        let scalar = strip_leading_zeros(signature_reader.read_string()?);
+        if scalar.len() > scalar_len {
+            return Err(malformed_signature());
+        }
+        fixed.resize(fixed.len() + scalar_len - scalar.len(), 0);
+        fixed.extend_from_slice(scalar);
+    }
+
+    Ok(algorithm.verify(Input::from(point), Input::from(data), Input::from(&fixed)))
+}
+
+fn strip_leading_zeros(bytes: &[u8]) -> &[u8] {
+    let first_non_zero = bytes.iter().position(|x| *x != 0).unwrap_or(bytes.len());
+    &bytes[first_non_zero..]
+}
+
+fn write_string(buf: &mut Vec<u8>, bytes: &[u8]) {
+    buf.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+fn malformed_signature() -> Error {
+    Error::from(ErrorKind::FailedToVerify(
+        "SSH signature is malformed".to_string(),
+    ))
+}
+
+/// Reads the SSH wire format as defined in RFC 4251, section 5
+struct WireReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> WireReader<'a> {
+    fn new(bytes: &'a [u8]) -> WireReader<'a> {
+        WireReader { bytes }
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(malformed_signature());
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+
+        Ok(head)
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        let bytes = self.read_bytes(4)?;
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(bytes);
+
+        Ok(u32::from_be_bytes(buf))
+    }
+
+    fn read_string(&mut self) -> Result<&'a [u8]> {
+        let len = self.read_u32()? as usize;
+        self.read_bytes(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test;
+
+    use spectral::prelude::*;
+
+    fn verifier() -> SshVerifier {
+        SshVerifier::from_allowed_signers(include_str!("../tests/allowed_signers"))
+            .expect("failed to load allowed signers")
+    }
+
+    #[test]
+    fn load_allowed_signers() {
+        test::init();
+
+        let verifier = verifier();
+
+        asserting("all signers have been loaded")
+            .that(&verifier.signers)
+            .has_length(3);
+        asserting("multiple principals and namespaces are parsed")
+            .that(&verifier.signers[1])
+            .matches(|x| {
+                x.principals == vec!["bob@example.com", "robert@example.com"]
+                    && x.namespaces == Some(vec!["git".to_string()])
+            });
+    }
+
+    #[test]
+    fn signer_from_config_keeps_namespaces() {
+        test::init();
+
+        let allowed_signer = AllowedSigner {
+            name: "Bob".to_string(),
+            principals: vec!["bob@example.com".to_string()],
+            public_key:
+                "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINMyCckQPVLWAgZeiuWTp3kjKqCN1Z/PUH2yNnxYsLh2"
+                    .to_string(),
+            namespaces: Some(vec!["file".to_string()]),
+        };
+
+        let signer = SshSigner::try_from(&allowed_signer);
+
+        asserting("namespaces are kept")
+            .that(&signer)
+            .is_ok()
+            .matches(|x| x.namespaces == Some(vec!["file".to_string()]));
+        asserting("other namespaces are not allowed")
+            .that(&signer)
+            .is_ok()
+            .matches(|x| x.allows_namespace("file") && !x.allows_namespace("git"));
+    }
+
+    #[test]
+    fn load_allowed_signers_failed_unsupported_options() {
+        test::init();
+
+        let key =
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAINMyCckQPVLWAgZeiuWTp3kjKqCN1Z/PUH2yNnxYsLh2";
+        for options in &[
+            r#"valid-after="20190101""#,
+            r#"valid-before="20190101""#,
+            "cert-authority",
+            r#"namespaces="git",valid-before="20190101""#,
+        ] {
+            let line = format!("alice@example.com {} {}", options, key);
+            let res = SshVerifier::from_allowed_signers(&line);
+
+            asserting(&format!("signer with option {} is rejected", options))
+                .that(&res)
+                .is_err()
+                .map(|x| x.kind())
+                .is_equal_to(&ErrorKind::FailedToLoadKey);
+        }
+    }
+
+    #[test]
+    fn finger_print() {
+        let key = PublicKey::try_from(include_str!("../tests/ssh-ed25519.pub"))
+            .expect("failed to load public key");
+
+        asserting("fingerprint is computed like ssh-keygen does")
+            .that(&key.finger_print())
+            .is_equal_to("SHA256:XJfxWWQsn7mfjwza2gauEFnU0XM6fQZ58cQfBLePNMI".to_string());
+    }
+
+    #[test]
+    fn verify_ed25519_okay() {
+        test::init();
+
+        let signature = include_str!("../tests/ssh-ed25519.sig");
+        let message = include_str!("../tests/test-commit.txt");
+        let expected_key = VerificationKey::from_ssh_key(
+            "SHA256:XJfxWWQsn7mfjwza2gauEFnU0XM6fQZ58cQfBLePNMI".to_string(),
+            vec!["alice@example.com".to_string()],
+        );
+
+        let res = verifier().verify(message.as_ref(), signature);
+
+        asserting("Signature is valid")
+            .that(&res)
+            .is_ok()
+            .is_equal_to(&expected_key);
+    }
+
+    #[test]
+    fn verify_rsa_okay() {
+        test::init();
+
+        let signature = include_str!("../tests/ssh-rsa.sig");
+        let message = include_str!("../tests/test-commit.txt");
+
+        let res = verifier().verify(message.as_ref(), signature);
+
+        asserting("Signature is valid").that(&res).is_ok();
+    }
+
+    #[test]
+    fn verify_ecdsa_okay() {
+        test::init();
+
+        let signature = include_str!("../tests/ssh-ecdsa.sig");
+        let message = include_str!("../tests/test-commit.txt");
+
+        let res = verifier().verify(message.as_ref(), signature);
+
+        asserting("Signature is valid").that(&res).is_ok();
+    }
+
+    #[test]
+    fn verify_failed_invalid_message() {
+        test::init();
+
+        let signature = include_str!("../tests/ssh-ed25519.sig");
+        let message = include_str!("../tests/test-commit.txt").replace("Test commit", "Evil");
+
+        let res = verifier().verify(message.as_ref(), signature);
+
+        asserting("Signature is not verified").that(&res).is_err();
+    }
+
+    #[test]
+    fn verify_failed_wrong_namespace() {
+        test::init();
+
+        let signature = include_str!("../tests/ssh-ed25519-file.sig");
+        let message = include_str!("../tests/test-commit.txt");
+
+        let res = verifier().verify(message.as_ref(), signature);
+
+        asserting("Signature for other namespace is not verified")
+            .that(&res)
+            .is_err();
+    }
+
+    #[test]
+    fn verify_failed_missing_key() {
+        test::init();
+
+        let signature = include_str!("../tests/ssh-ed25519.sig");
+        let message = include_str!("../tests/test-commit.txt");
+        let verifier = SshVerifier::new(vec![SshSigner::new(
+            vec!["bob@example.com".to_string()],
+            include_str!("../tests/ssh-rsa.pub"),
+        )
+        .expect("failed to load public key")]);
+
+        let res = verifier.verify(message.as_ref(), signature);
+
+        asserting("Signature by unknown key is not verified")
+            .that(&res)
+            .is_err();
+    }
+
+    #[test]
+    fn verify_failed_overlapping_armor() {
+        test::init();
+
+        let message = include_str!("../tests/test-commit.txt");
+
+        let res = verifier().verify(
+            message.as_ref(),
+            "-----BEGIN SSH SIGNATURE-----END SSH SIGNATURE-----",
+        );
+
+        asserting("Signature with overlapping armor is rejected")
+            .that(&res)
+            .is_err()
+            .map(|x| x.kind())
+            .is_equal_to(&ErrorKind::FailedToVerify(
+                "SSH signature armor is invalid".to_string(),
+            ));
+    }
+}
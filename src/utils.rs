@@ -22,13 +22,11 @@ pub(crate) mod sha256 {
         from_file(file_path).map(|sha| hex::encode(sha.as_slice()))
     }
 
-    #[allow(dead_code)] // Not yet used anywhere but tests
     pub(crate) fn from_bytes_as_str(bytes: &[u8]) -> String {
         let sha = from_bytes(bytes);
         hex::encode(sha.as_slice())
     }
 
-    #[allow(dead_code)] // Not yet used anywhere but tests
     pub(crate) fn from_bytes(bytes: &[u8]) -> Vec<u8> {
         ring::digest::digest(&ring::digest::SHA256, bytes)
             .as_ref()
@@ -69,6 +67,83 @@ pub(crate) mod sha256 {
     }
 }
 
+pub(crate) mod armor {
+    use crate::errors::*;
+
+    use base64;
+    use failure::Fail;
+
+    /// Decodes the base64 encoded data between the `begin` and `end` lines of an armored
+    /// signature; `name` describes the signature in error messages
+    pub(crate) fn dearmor(begin: &str, end: &str, armored: &str, name: &str) -> Result<Vec<u8>> {
+        let armored = armored.trim();
+        // Begin and end must not overlap, e.g., in `-----BEGIN X-----END X-----`
+        let encoded = if armored.len() >= begin.len() + end.len()
+            && armored.starts_with(begin)
+            && armored.ends_with(end)
+        {
+            armored.get(begin.len()..armored.len() - end.len())
+        } else {
+            None
+        };
+        let encoded: String = encoded
+            .ok_or_else(|| {
+                Error::from(ErrorKind::FailedToVerify(format!(
+                    "{} armor is invalid",
+                    name
+                )))
+            })?
+            .split_whitespace()
+            .collect();
+
+        base64::decode(&encoded).map_err(|e| {
+            e.context(ErrorKind::FailedToVerify(format!(
+                "{} is not base64 encoded",
+                name
+            )))
+            .into()
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        use spectral::prelude::*;
+
+        static BEGIN: &str = "-----BEGIN TEST SIGNATURE-----";
+        static END: &str = "-----END TEST SIGNATURE-----";
+
+        #[test]
+        fn dearmor_okay() {
+            let armored = format!("{}\naGVs\nbG8=\n{}\n", BEGIN, END);
+
+            asserting("data between begin and end is decoded")
+                .that(&dearmor(BEGIN, END, &armored, "test signature"))
+                .is_ok()
+                .is_equal_to(&b"hello".to_vec());
+        }
+
+        #[test]
+        fn dearmor_failed_overlapping_begin_and_end() {
+            let res = dearmor(
+                BEGIN,
+                END,
+                "-----BEGIN TEST SIGNATURE-----END TEST SIGNATURE-----",
+                "test signature",
+            );
+
+            asserting("overlapping armor is rejected")
+                .that(&res)
+                .is_err()
+                .map(|x| x.kind())
+                .is_equal_to(&ErrorKind::FailedToVerify(
+                    "test signature armor is invalid".to_string(),
+                ));
+        }
+    }
+}
+
 pub(crate) mod http {
     use crate::errors::*;
 
@@ -115,9 +190,153 @@ pub(crate) mod http {
 
 #[cfg(test)]
 pub(crate) mod test {
+    use crate::{
+        errors::*,
+        github::{
+            commits::{Commit, Params, Sha},
+            tags::TagRef,
+            Endpoints, GitHub, GpgKey, Repository, Tag,
+        },
+    };
+
     use env_logger;
+    use reqwest::StatusCode;
+    use std::{cell::RefCell, collections::HashMap};
 
     pub(crate) fn init() {
         let _ = env_logger::builder().is_test(true).try_init();
     }
+
+    /// GitHub answering with configured responses; all other calls fail like an unavailable API
+    #[derive(Default)]
+    pub(crate) struct FakeGitHub {
+        commits: Option<fn() -> Vec<Commit>>,
+        comparison: Option<fn() -> Vec<Commit>>,
+        file_contents: HashMap<String, Option<String>>,
+        file_contents_at: HashMap<(String, String), Option<String>>,
+        user_gpg_keys: Option<HashMap<String, Vec<GpgKey>>>,
+        requested_references: RefCell<Vec<Option<String>>>,
+    }
+
+    impl FakeGitHub {
+        pub(crate) fn new() -> FakeGitHub {
+            FakeGitHub::default()
+        }
+
+        /// `commit` returns the commit with the requested sha among the commits created by
+        /// `commits`
+        pub(crate) fn with_commits(self, commits: fn() -> Vec<Commit>) -> Self {
+            FakeGitHub {
+                commits: Some(commits),
+                ..self
+            }
+        }
+
+        /// `compare` returns the commits created by `comparison`
+        pub(crate) fn with_comparison(self, comparison: fn() -> Vec<Commit>) -> Self {
+            FakeGitHub {
+                comparison: Some(comparison),
+                ..self
+            }
+        }
+
+        /// `file_content` returns `content` for `path` at any reference; `None` if the file does
+        /// not exist
+        pub(crate) fn with_file_content(mut self, path: &str, content: Option<&str>) -> Self {
+            self.file_contents
+                .insert(path.to_string(), content.map(ToString::to_string));
+            self
+        }
+
+        /// `file_content` returns `content` for `path` at `reference`, taking precedence over
+        /// `with_file_content`
+        pub(crate) fn with_file_content_at(
+            mut self,
+            path: &str,
+            reference: &str,
+            content: Option<&str>,
+        ) -> Self {
+            self.file_contents_at.insert(
+                (path.to_string(), reference.to_string()),
+                content.map(ToString::to_string),
+            );
+            self
+        }
+
+        /// `user_gpg_keys` returns `keys` for `login` and no keys for any other user
+        pub(crate) fn with_user_gpg_keys(mut self, login: &str, keys: Vec<GpgKey>) -> Self {
+            self.user_gpg_keys
+                .get_or_insert_with(HashMap::new)
+                .insert(login.to_string(), keys);
+            self
+        }
+
+        /// References `file_content` has been called with in order
+        pub(crate) fn requested_references(&self) -> Vec<Option<String>> {
+            self.requested_references.borrow().clone()
+        }
+
+        fn unavailable(call: &str) -> Error {
+            Error::from(ErrorKind::ApiCallFailed(
+                StatusCode::SERVICE_UNAVAILABLE,
+                format!("fake GitHub has no response for {}", call),
+            ))
+        }
+    }
+
+    impl GitHub for FakeGitHub {
+        fn commits<T: Into<Option<Params>>>(&self, _: &Repository, _: T) -> Result<Vec<Commit>> {
+            Err(FakeGitHub::unavailable("commits"))
+        }
+
+        fn commit(&self, _: &Repository, sha: &Sha) -> Result<Commit> {
+            self.commits
+                .and_then(|commits| commits().into_iter().find(|x| &x.sha == sha))
+                .ok_or_else(|| FakeGitHub::unavailable(&format!("commit {}", sha)))
+        }
+
+        fn compare(&self, _: &Repository, _: &Sha, _: &Sha) -> Result<Vec<Commit>> {
+            self.comparison
+                .map(|comparison| comparison())
+                .ok_or_else(|| FakeGitHub::unavailable("compare"))
+        }
+
+        fn endpoints(&self) -> Result<Endpoints> {
+            Err(FakeGitHub::unavailable("endpoints"))
+        }
+
+        fn file_content(
+            &self,
+            _: &Repository,
+            path: &str,
+            reference: Option<&str>,
+        ) -> Result<Option<String>> {
+            self.requested_references
+                .borrow_mut()
+                .push(reference.map(ToString::to_string));
+            reference
+                .and_then(|x| {
+                    self.file_contents_at
+                        .get(&(path.to_string(), x.to_string()))
+                })
+                .or_else(|| self.file_contents.get(path))
+                .cloned()
+                .ok_or_else(|| FakeGitHub::unavailable(&format!("file_content of {}", path)))
+        }
+
+        fn tags(&self, _: &Repository) -> Result<Vec<TagRef>> {
+            Err(FakeGitHub::unavailable("tags"))
+        }
+
+        fn tag(&self, _: &Repository, _: &Sha) -> Result<Tag> {
+            Err(FakeGitHub::unavailable("tag"))
+        }
+
+        fn user_gpg_keys(&self, login: &str) -> Result<Vec<GpgKey>> {
+            self.user_gpg_keys
+                .as_ref()
+                .map(|x| x.get(login).cloned().unwrap_or_default())
+                .ok_or_else(|| FakeGitHub::unavailable("user_gpg_keys"))
+        }
+    }
 }
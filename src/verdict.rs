@@ -0,0 +1,327 @@
+//! Per-commit verdicts for auditing a range of commits
+
+use crate::{
+    cache::{self, VerificationCache},
+    errors::*,
+    github::commits::{Reason, Sha},
+    gpg::{CommitVerifier, VerificationKey},
+    source::{CommitSource, SignedCommit},
+};
+
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+/// Outcome of verifying a single commit
+#[derive(Debug)]
+pub struct CommitVerdict {
+    pub sha: Sha,
+    pub author: String,
+    pub signed: bool,
+    /// Verification result of the commit's source, e.g., GitHub's; `None` if the source does not
+    /// verify signatures itself
+    pub reason: Option<Reason>,
+    pub keys: Result<Vec<VerificationKey>>,
+}
+
+impl CommitVerdict {
+    pub fn is_verified(&self) -> bool {
+        self.keys.is_ok()
+    }
+
+    /// The policy rule this verdict is based on
+    pub fn rule(&self) -> Rule {
+        let e = match self.keys {
+            Ok(_) => return Rule::TrustedSignature,
+            Err(ref e) => e,
+        };
+        if !self.signed {
+            return Rule::Signed;
+        }
+        match self.reason {
+            Some(Reason::Valid) | None => {}
+            Some(_) => return Rule::VerifiedBySource,
+        }
+
+        match e.kind() {
+            ErrorKind::KeyExpired(_) => Rule::KeyNotExpired,
+            ErrorKind::KeyRevoked(_) => Rule::KeyNotRevoked,
+            ErrorKind::SignatureBeforeKeyCreation(_) => Rule::SignatureAfterKeyCreation,
+            ErrorKind::SubkeyNotAllowed(_) => Rule::SubkeyAllowed,
+            ErrorKind::PusherDoesNotOwnKey(_) => Rule::PusherOwnsKey,
+            _ => Rule::TrustedSignature,
+        }
+    }
+
+    /// Owners, or fingerprints if unknown, of the keys the commit has been verified with; the
+    /// reason if not verified
+    pub fn details(&self) -> String {
+        match self.keys {
+            Ok(ref keys) => keys
+                .iter()
+                .map(|x| match x.owner() {
+                    Some(owner) => format!("{} ({})", owner, x.finger_print()),
+                    None => x.finger_print().to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            Err(ref e) => e.to_string(),
+        }
+    }
+}
+
+/// Policy rules a commit is checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    Signed,
+    /// The source, e.g., GitHub, must have verified the signature
+    VerifiedBySource,
+    TrustedSignature,
+    KeyNotExpired,
+    KeyNotRevoked,
+    SignatureAfterKeyCreation,
+    SubkeyAllowed,
+    PusherOwnsKey,
+}
+
+impl Rule {
+    pub const ALL: &'static [Rule] = &[
+        Rule::Signed,
+        Rule::VerifiedBySource,
+        Rule::TrustedSignature,
+        Rule::KeyNotExpired,
+        Rule::KeyNotRevoked,
+        Rule::SignatureAfterKeyCreation,
+        Rule::SubkeyAllowed,
+        Rule::PusherOwnsKey,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            Rule::Signed => "signed",
+            Rule::VerifiedBySource => "verified-by-source",
+            Rule::TrustedSignature => "trusted-signature",
+            Rule::KeyNotExpired => "key-not-expired",
+            Rule::KeyNotRevoked => "key-not-revoked",
+            Rule::SignatureAfterKeyCreation => "signature-after-key-creation",
+            Rule::SubkeyAllowed => "subkey-allowed",
+            Rule::PusherOwnsKey => "pusher-owns-key",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Rule::Signed => "Commits must be signed",
+            Rule::VerifiedBySource => "The commit source must have verified the signature",
+            Rule::TrustedSignature => "Commits must be signed by a trusted key",
+            Rule::KeyNotExpired => "Signing keys must not be expired at signature creation time",
+            Rule::KeyNotRevoked => "Signing keys must not be revoked",
+            Rule::SignatureAfterKeyCreation => "Signatures must not predate their signing key",
+            Rule::SubkeyAllowed => "Signing subkeys must be allowed by the subkey policy",
+            Rule::PusherOwnsKey => "The pushing user must own a signing key",
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// Verifies commits as a batch, cf. `CommitVerifier::verify_signed_commits`
+pub fn audit(
+    verifier: &CommitVerifier,
+    commits: &[SignedCommit],
+    source: Option<&dyn CommitSource>,
+) -> Vec<CommitVerdict> {
+    verdicts(commits, verifier.verify_signed_commits(commits, source))
+}
+
+/// Verifies commits of `repository` as a batch reusing cached results, cf.
+/// `cache::verify_signed_commits`
+pub fn audit_cached<C: VerificationCache + ?Sized>(
+    verifier: &CommitVerifier,
+    cache: &mut C,
+    repository: &str,
+    commits: &[SignedCommit],
+    source: Option<&dyn CommitSource>,
+) -> Vec<CommitVerdict> {
+    verdicts(
+        commits,
+        cache::verify_signed_commits(verifier, cache, repository, commits, source),
+    )
+}
+
+fn verdicts(
+    commits: &[SignedCommit],
+    results: Vec<Result<Vec<VerificationKey>>>,
+) -> Vec<CommitVerdict> {
+    commits
+        .iter()
+        .zip(results)
+        .map(|(commit, keys)| CommitVerdict {
+            sha: commit.sha.clone(),
+            author: format!("{} <{}>", commit.author.name, commit.author.email),
+            signed: commit.is_signed(),
+            reason: commit.source_reason,
+            keys,
+        })
+        .collect()
+}
+
+/// Writes one line per verdict with the abbreviated sha, the verdict, the author, and details
+pub fn write_table<W: Write>(writer: &mut W, verdicts: &[CommitVerdict]) -> io::Result<()> {
+    let author_width = verdicts
+        .iter()
+        .map(|x| x.author.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("AUTHOR".len());
+
+    writeln!(
+        writer,
+        "{:<7}  {:<9}  {:<width$}  DETAILS",
+        "SHA",
+        "VERDICT",
+        "AUTHOR",
+        width = author_width
+    )?;
+    for verdict in verdicts {
+        // Shas of untrusted sources are not necessarily hex, so they are shortened by chars
+        let sha: String = verdict.sha.to_string().chars().take(7).collect();
+        writeln!(
+            writer,
+            "{:<7}  {:<9}  {:<width$}  {}",
+            sha,
+            if verdict.is_verified() {
+                "verified"
+            } else {
+                "violation"
+            },
+            verdict.author,
+            verdict.details(),
+            width = author_width
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{github::commits::Commit, utils::test};
+
+    use serde_json;
+    use spectral::prelude::*;
+
+    fn commit(sha: &str, signature: Option<&str>) -> SignedCommit {
+        let commit = format!(
+            r#"{{
+                "sha": "{sha}",
+                "commit": {{
+                    "author": {{ "name": "Second Test", "email": "second@example.com", "date": "2019-06-25T12:00:00Z" }},
+                    "committer": {{ "name": "Second Test", "email": "second@example.com", "date": "2019-06-25T12:00:00Z" }},
+                    "message": "Test commit",
+                    "verification": {{
+                        "verified": {verified},
+                        "reason": "{reason}",
+                        "signature": {signature},
+                        "payload": {payload}
+                    }}
+                }}
+            }}"#,
+            sha = sha,
+            verified = signature.is_some(),
+            reason = if signature.is_some() {
+                "valid"
+            } else {
+                "unsigned"
+            },
+            signature = serde_json::to_string(&signature).unwrap(),
+            payload =
+                serde_json::to_string(&signature.map(|_| include_str!("../tests/test-commit.txt")))
+                    .unwrap(),
+        );
+
+        let commit: Commit = serde_json::from_str(&commit).expect("failed to deserialize commit");
+
+        SignedCommit::from(&commit)
+    }
+
+    #[test]
+    fn audit_and_write_table() {
+        test::init();
+
+        let verifier =
+            CommitVerifier::from_key_file("tests/subkey.pub").expect("failed to load public key");
+        let commits = vec![
+            commit(
+                "c255ad2347d00cae3dd2d7a21e1357e50413fc4f",
+                Some(include_str!("../tests/subkey.sig")),
+            ),
+            commit("5b1c1fa6dd0f4f3a9ab3a5cb9ef4c1f1e1ee9ff4", None),
+        ];
+
+        let verdicts = audit(&verifier, &commits, None);
+        let mut table = Vec::new();
+        write_table(&mut table, &verdicts).expect("failed to write table");
+        let table = String::from_utf8(table).expect("table is not UTF-8");
+        let lines: Vec<_> = table.lines().collect();
+
+        asserting("signed commit is verified")
+            .that(&verdicts[0].is_verified())
+            .is_true();
+        asserting("unsigned commit is a violation")
+            .that(&verdicts[1].is_verified())
+            .is_false();
+        asserting("unsigned commit violates signed rule")
+            .that(&verdicts[1].rule())
+            .is_equal_to(&Rule::Signed);
+        asserting("GitHub's reason is kept")
+            .that(&verdicts[1].reason)
+            .is_equal_to(&Some(Reason::Unsigned));
+        asserting("table has header and one line per commit")
+            .that(&lines.len())
+            .is_equal_to(3);
+        asserting("verified commit line")
+            .that(
+                &lines[1].starts_with("c255ad2  verified   Second Test <second@example.com>  78B3"),
+            )
+            .is_true();
+        asserting("violation line")
+            .that(&lines[2].starts_with("5b1c1fa  violation  Second Test <second@example.com>  "))
+            .is_true();
+    }
+
+    #[test]
+    fn write_table_non_ascii_sha() {
+        test::init();
+
+        let verdicts = vec![CommitVerdict {
+            sha: Sha::new("c255ad\u{e4}2347d00"),
+            author: "Mallory <mallory@example.com>".to_string(),
+            signed: false,
+            reason: None,
+            keys: Err(Error::from(ErrorKind::FailedToVerify(
+                "unsigned".to_string(),
+            ))),
+        }];
+
+        let mut table = Vec::new();
+        let res = write_table(&mut table, &verdicts);
+        let table = String::from_utf8(table).expect("table is not UTF-8");
+
+        asserting("table is written").that(&res).is_ok();
+        asserting("sha is shortened to seven characters")
+            .that(
+                &table
+                    .lines()
+                    .nth(1)
+                    .map(|x| x.starts_with("c255ad\u{e4}  violation")),
+            )
+            .is_equal_to(&Some(true));
+    }
+}
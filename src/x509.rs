@@ -0,0 +1,352 @@
+//! Verification of X.509 / S/MIME commit signatures as created by smimesign or gitsign
+//!
+//! These tools create detached CMS signatures which contain the signer's certificate. The
+//! certificate chain is validated against configured root CAs.
+//!
+//! Certificates are validated at the committer or tagger date of the signed commit or tag, because
+//! gitsign's certificates expire minutes after signing. Other messages are validated at
+//! verification time.
+
+use crate::{errors::*, gpg::VerificationKey, utils::armor};
+
+use failure::Fail;
+use log::debug;
+use openssl::{
+    hash::MessageDigest,
+    nid::Nid,
+    pkcs7::{Pkcs7, Pkcs7Flags},
+    stack::Stack,
+    x509::{
+        store::{X509Store, X509StoreBuilder},
+        verify::X509VerifyParam,
+        X509NameRef, X509Ref, X509,
+    },
+};
+use std::{convert::TryInto, fmt, fs, path::Path, str};
+
+pub(crate) static X509_SIGNATURE_ARMOR_BEGIN: &str = "-----BEGIN SIGNED MESSAGE-----";
+static X509_SIGNATURE_ARMOR_END: &str = "-----END SIGNED MESSAGE-----";
+
+pub struct X509Verifier {
+    roots: Vec<X509>,
+    /// Fingerprints of the root CAs
+    finger_prints: Vec<String>,
+}
+
+impl fmt::Debug for X509Verifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("X509Verifier")
+            .field("roots", &self.roots.len())
+            .finish()
+    }
+}
+
+impl X509Verifier {
+    pub fn new(roots: Vec<X509>) -> Result<X509Verifier> {
+        let finger_prints = roots
+            .iter()
+            .map(|x| finger_print(x))
+            .collect::<Result<_>>()?;
+
+        Ok(X509Verifier {
+            roots,
+            finger_prints,
+        })
+    }
+
+    /// Loads root CA certificates from PEM files; each file may contain multiple certificates
+    pub fn from_pem_files<P: AsRef<Path>>(file_paths: &[P]) -> Result<X509Verifier> {
+        let mut roots = Vec::new();
+        for p in file_paths {
+            let pem = fs::read(p).map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
+            let certs =
+                X509::stack_from_pem(&pem).map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
+            roots.extend(certs);
+        }
+
+        X509Verifier::new(roots)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    pub fn finger_prints(&self) -> &[String] {
+        &self.finger_prints
+    }
+
+    pub fn verify(&self, message: &[u8], armored_signature: &str) -> Result<VerificationKey> {
+        let signature = dearmor(armored_signature)?;
+        let pkcs7 = Pkcs7::from_der(&signature).map_err(|e| {
+            e.context(ErrorKind::FailedToVerify(
+                "X.509 signature is malformed".to_string(),
+            ))
+        })?;
+
+        // The signer's certificate is part of the signature, so no additional certificates are
+        // required.
+        let certs = Stack::new().map_err(|e| e.context(ErrorKind::FailedToCreateVerifier))?;
+        let store = self.store(check_time(message))?;
+        pkcs7
+            .verify(&certs, &store, Some(message), None, Pkcs7Flags::BINARY)
+            .map_err(|e| {
+                e.context(ErrorKind::FailedToVerify(
+                    "X.509 signature could not be verified".to_string(),
+                ))
+            })?;
+
+        let signers = pkcs7.signers(&certs, Pkcs7Flags::empty()).map_err(|e| {
+            e.context(ErrorKind::FailedToVerify(
+                "X.509 signer could not be determined".to_string(),
+            ))
+        })?;
+        let signer = signers.iter().next().ok_or_else(|| {
+            Error::from(ErrorKind::FailedToVerify(
+                "no signer found; this should not happen".to_string(),
+            ))
+        })?;
+
+        let key = VerificationKey::from_x509_certificate(
+            finger_print(signer)?,
+            subject(signer.subject_name()),
+            e_mails(signer),
+        );
+        debug!("Message successfully verified with certificate {:?}", key);
+
+        Ok(key)
+    }
+
+    /// Store of the root CAs validating chains at `check_time`, or the current time if not set
+    fn store(&self, check_time: Option<i64>) -> Result<X509Store> {
+        let mut builder =
+            X509StoreBuilder::new().map_err(|e| e.context(ErrorKind::FailedToCreateVerifier))?;
+        for root in &self.roots {
+            builder
+                .add_cert(root.clone())
+                .map_err(|e| e.context(ErrorKind::FailedToLoadKey))?;
+        }
+        if let Some(check_time) = check_time {
+            let mut param =
+                X509VerifyParam::new().map_err(|e| e.context(ErrorKind::FailedToCreateVerifier))?;
+            // `time_t` has 32 bits on some platforms
+            #[allow(clippy::useless_conversion)]
+            let check_time = check_time.try_into().map_err(|_| {
+                Error::from(ErrorKind::FailedToVerify(format!(
+                    "date {} is out of range",
+                    check_time
+                )))
+            })?;
+            // Also sets `X509_V_FLAG_USE_CHECK_TIME`
+            param.set_time(check_time);
+            builder
+                .set_param(&param)
+                .map_err(|e| e.context(ErrorKind::FailedToCreateVerifier))?;
+        }
+
+        Ok(builder.build())
+    }
+}
+
+/// Seconds since the epoch of the committer or tagger line of a commit or tag object
+fn check_time(message: &[u8]) -> Option<i64> {
+    let message = str::from_utf8(message).ok()?;
+    let line = message
+        .lines()
+        .take_while(|x| !x.is_empty())
+        .find(|x| x.starts_with("committer ") || x.starts_with("tagger "))?;
+    let mut fields = line.rsplitn(3, ' ');
+    let _timezone = fields.next()?;
+
+    fields.next()?.parse().ok()
+}
+
+fn dearmor(armored_signature: &str) -> Result<Vec<u8>> {
+    armor::dearmor(
+        X509_SIGNATURE_ARMOR_BEGIN,
+        X509_SIGNATURE_ARMOR_END,
+        armored_signature,
+        "X.509 signature",
+    )
+}
+
+/// SHA-256 fingerprint in the format `openssl x509 -fingerprint` prints it
+fn finger_print(cert: &X509Ref) -> Result<String> {
+    let digest = cert
+        .digest(MessageDigest::sha256())
+        .map_err(|e| e.context(ErrorKind::GeneralError))?;
+    let hex: Vec<_> = digest.iter().map(|x| format!("{:02X}", x)).collect();
+
+    Ok(hex.join(":"))
+}
+
+/// One line distinguished name, e.g., `O=Watchtower Test, CN=Dave Developer`
+fn subject(name: &X509NameRef) -> String {
+    let entries: Vec<_> = name
+        .entries()
+        .filter_map(|x| {
+            let key = x.object().nid().short_name().ok()?;
+            let value = String::from_utf8_lossy(x.data().as_slice());
+            Some(format!("{}={}", key, value))
+        })
+        .collect();
+
+    entries.join(", ")
+}
+
+/// E-mail addresses from the subject alternative names and the subject itself
+fn e_mails(cert: &X509Ref) -> Vec<String> {
+    let mut e_mails: Vec<String> = cert
+        .subject_alt_names()
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|x| x.email().map(ToString::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let subject_e_mails = cert
+        .subject_name()
+        .entries_by_nid(Nid::PKCS9_EMAILADDRESS)
+        .map(|x| String::from_utf8_lossy(x.data().as_slice()).to_string());
+    for e_mail in subject_e_mails {
+        if !e_mails.contains(&e_mail) {
+            e_mails.push(e_mail);
+        }
+    }
+
+    e_mails
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test;
+
+    use spectral::prelude::*;
+
+    #[test]
+    fn load_roots_from_pem_files() {
+        test::init();
+
+        let verifier =
+            X509Verifier::from_pem_files(&["tests/x509-root.pem", "tests/x509-other-root.pem"]);
+
+        asserting("Root CAs have been successfully loaded")
+            .that(&verifier)
+            .is_ok()
+            .matches(|x| x.roots.len() == 2);
+    }
+
+    #[test]
+    fn verify_okay() {
+        test::init();
+
+        let signature = include_str!("../tests/x509.sig");
+        let message = include_str!("../tests/test-commit.txt");
+        let expected_key = VerificationKey::from_x509_certificate(
+            "FE:16:3F:69:0F:1A:47:DD:33:BD:97:82:D7:51:69:37:9D:1A:CD:40:E4:4A:AD:D4:C4:14:FE:EC:C6:33:4D:8B"
+                .to_string(),
+            "O=Watchtower Test, CN=Dave Developer, emailAddress=dave@example.com".to_string(),
+            vec!["dave@example.com".to_string()],
+        );
+
+        let verifier =
+            X509Verifier::from_pem_files(&["tests/x509-root.pem"]).expect("failed to load root CA");
+        let res = verifier.verify(message.as_ref(), signature);
+
+        asserting("Signature is valid")
+            .that(&res)
+            .is_ok()
+            .is_equal_to(&expected_key);
+    }
+
+    #[test]
+    fn verify_expired_certificate_okay() {
+        test::init();
+
+        // The certificate was valid for ten minutes around the committer date only
+        let signature = include_str!("../tests/x509-expired.sig");
+        let message = include_str!("../tests/test-commit.txt");
+        let expected_key = VerificationKey::from_x509_certificate(
+            "DA:0D:40:ED:50:7E:7F:07:F7:9F:CD:24:E4:87:6E:5D:6C:E9:7D:B8:54:EB:2A:5A:66:BD:50:2C:7E:48:D0:CD"
+                .to_string(),
+            "O=Watchtower Test, CN=Erin Ephemeral, emailAddress=erin@example.com".to_string(),
+            vec!["erin@example.com".to_string()],
+        );
+
+        let verifier =
+            X509Verifier::from_pem_files(&["tests/x509-root.pem"]).expect("failed to load root CA");
+        let res = verifier.verify(message.as_ref(), signature);
+
+        asserting("Signature made while the certificate was valid is valid")
+            .that(&res)
+            .is_ok()
+            .is_equal_to(&expected_key);
+    }
+
+    #[test]
+    fn check_time_from_committer_or_tagger() {
+        asserting("committer date is used")
+            .that(&check_time(include_bytes!("../tests/test-commit.txt")))
+            .is_equal_to(&Some(1_561_466_095));
+        asserting("tagger date is used")
+            .that(&check_time(include_bytes!("../tests/test-tag.txt")))
+            .is_equal_to(&Some(1_561_460_000));
+        asserting("other messages have no date")
+            .that(&check_time(b"audit log checkpoint"))
+            .is_none();
+    }
+
+    #[test]
+    fn verify_failed_invalid_message() {
+        test::init();
+
+        let signature = include_str!("../tests/x509.sig");
+        let message = include_str!("../tests/test-commit.txt").replace("Test commit", "Evil");
+
+        let verifier =
+            X509Verifier::from_pem_files(&["tests/x509-root.pem"]).expect("failed to load root CA");
+        let res = verifier.verify(message.as_ref(), signature);
+
+        asserting("Signature is not verified").that(&res).is_err();
+    }
+
+    #[test]
+    fn verify_failed_untrusted_root() {
+        test::init();
+
+        let signature = include_str!("../tests/x509.sig");
+        let message = include_str!("../tests/test-commit.txt");
+
+        let verifier = X509Verifier::from_pem_files(&["tests/x509-other-root.pem"])
+            .expect("failed to load root CA");
+        let res = verifier.verify(message.as_ref(), signature);
+
+        asserting("Signature by certificate of untrusted CA is not verified")
+            .that(&res)
+            .is_err();
+    }
+
+    #[test]
+    fn verify_failed_overlapping_armor() {
+        test::init();
+
+        let message = include_str!("../tests/test-commit.txt");
+
+        let verifier =
+            X509Verifier::from_pem_files(&["tests/x509-root.pem"]).expect("failed to load root CA");
+        let res = verifier.verify(
+            message.as_ref(),
+            "-----BEGIN SIGNED MESSAGE-----END SIGNED MESSAGE-----",
+        );
+
+        asserting("Signature with overlapping armor is rejected")
+            .that(&res)
+            .is_err()
+            .map(|x| x.kind())
+            .is_equal_to(&ErrorKind::FailedToVerify(
+                "X.509 signature armor is invalid".to_string(),
+            ));
+    }
+}
@@ -145,3 +145,33 @@ fn github_commits_until() {
         .that(&amount)
         .is_equal_to(&4);
 }
+
+#[test]
+#[ignore]
+fn github_compare() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let token = env::var_os("GITHUB_TOKEN")
+        .expect("Environment variable 'GITHUB_TOKEN' is not set.")
+        .to_string_lossy()
+        .to_string();
+
+    let token = OAuthToken(token);
+    let client = Client::with_oauth_token(&token);
+
+    let repository = Repository::new("lukaspustina", "github-watchtower");
+
+    let commits = client
+        .compare(
+            &repository,
+            &Sha::new("10b1bf9f34fcab001615cb6a9fa7b3ca71d7d5ca"),
+            &Sha::new("a01d2a79a8a0e740e81ae2a0de31362219b33f50"),
+        )
+        .expect("Failed to compare '10b1bf9f34fcab001615cb6a9fa7b3ca71d7d5ca' and 'a01d2a79a8a0e740e81ae2a0de31362219b33f50'");
+
+    debug!("Compare: {:#?}", commits);
+
+    asserting("there are commits")
+        .that(&commits)
+        .matches(|x| !x.is_empty());
+}
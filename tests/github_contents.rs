@@ -0,0 +1,52 @@
+use github_watchtower::github::{Client, GitHub, OAuthToken, Repository};
+
+use env_logger;
+use log::debug;
+use spectral::prelude::*;
+use std::env;
+
+#[test]
+#[ignore]
+fn github_file_content() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let token = env::var_os("GITHUB_TOKEN")
+        .expect("Environment variable 'GITHUB_TOKEN' is not set.")
+        .to_string_lossy()
+        .to_string();
+
+    let token = OAuthToken(token);
+    let client = Client::with_oauth_token(&token);
+
+    let repository = Repository::new("lukaspustina", "github-watchtower");
+    let content = client.file_content(&repository, "Cargo.toml", None);
+
+    debug!("Content: {:#?}", content);
+
+    asserting("file content can be retrieved")
+        .that(&content)
+        .is_ok()
+        .is_some();
+}
+
+#[test]
+#[ignore]
+fn github_file_content_not_found() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let token = env::var_os("GITHUB_TOKEN")
+        .expect("Environment variable 'GITHUB_TOKEN' is not set.")
+        .to_string_lossy()
+        .to_string();
+
+    let token = OAuthToken(token);
+    let client = Client::with_oauth_token(&token);
+
+    let repository = Repository::new("lukaspustina", "github-watchtower");
+    let content = client.file_content(&repository, ".github/watchtower.toml", None);
+
+    asserting("missing file is reported as none")
+        .that(&content)
+        .is_ok()
+        .is_none();
+}
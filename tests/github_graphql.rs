@@ -0,0 +1,61 @@
+use github_watchtower::github::{
+    commits::{Params, Sha},
+    Client, GitHub, OAuthToken, Repository,
+};
+
+use env_logger;
+use log::debug;
+use spectral::prelude::*;
+use std::env;
+
+#[test]
+#[ignore]
+fn github_graphql_commits() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let token = env::var_os("GITHUB_TOKEN")
+        .expect("Environment variable 'GITHUB_TOKEN' is not set.")
+        .to_string_lossy()
+        .to_string();
+
+    let token = OAuthToken(token);
+    let client = Client::with_oauth_token(&token);
+    let client = client.graphql();
+
+    let repository = Repository::new("lukaspustina", "github-watchtower");
+    let commits = client.commits(&repository, None);
+
+    asserting("there are commits")
+        .that(&commits)
+        .is_ok()
+        .matches(|x| !x.is_empty());
+}
+
+#[test]
+#[ignore]
+fn github_graphql_commits_from_sha() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let token = env::var_os("GITHUB_TOKEN")
+        .expect("Environment variable 'GITHUB_TOKEN' is not set.")
+        .to_string_lossy()
+        .to_string();
+
+    let token = OAuthToken(token);
+    let client = Client::with_oauth_token(&token);
+    let client = client.graphql();
+
+    let repository = Repository::new("lukaspustina", "github-watchtower");
+
+    let params = Params::new().from(Sha::new("10b1bf9f34fcab001615cb6a9fa7b3ca71d7d5ca"));
+    let commits = client
+        .commits(&repository, params)
+        .expect("Failed to retrieve commits from '10b1bf9f34fcab001615cb6a9fa7b3ca71d7d5ca'");
+
+    debug!("From: {:#?}", commits);
+
+    let amount = commits.len();
+    asserting("number of commit is 3")
+        .that(&amount)
+        .is_equal_to(&3);
+}
@@ -0,0 +1,53 @@
+use github_watchtower::github::{Client, GitHub, OAuthToken, Repository};
+
+use env_logger;
+use log::debug;
+use spectral::prelude::*;
+use std::env;
+
+#[test]
+#[ignore]
+fn github_tags() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let token = env::var_os("GITHUB_TOKEN")
+        .expect("Environment variable 'GITHUB_TOKEN' is not set.")
+        .to_string_lossy()
+        .to_string();
+
+    let token = OAuthToken(token);
+    let client = Client::with_oauth_token(&token);
+
+    let repository = Repository::new("lukaspustina", "github-watchtower");
+    let tags = client.tags(&repository);
+
+    debug!("Tags: {:#?}", tags);
+
+    asserting("tags can be listed").that(&tags).is_ok();
+}
+
+#[test]
+#[ignore]
+fn github_annotated_tags() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let token = env::var_os("GITHUB_TOKEN")
+        .expect("Environment variable 'GITHUB_TOKEN' is not set.")
+        .to_string_lossy()
+        .to_string();
+
+    let token = OAuthToken(token);
+    let client = Client::with_oauth_token(&token);
+
+    let repository = Repository::new("lukaspustina", "github-watchtower");
+    let tags = client.tags(&repository).expect("Failed to retrieve tags");
+
+    for t in tags.iter().filter(|x| x.is_annotated()) {
+        let tag = client.tag(&repository, &t.object.sha);
+        debug!("Tag: {:#?}", tag);
+
+        asserting("annotated tag can be retrieved")
+            .that(&tag)
+            .is_ok();
+    }
+}
@@ -0,0 +1,29 @@
+use github_watchtower::github::{Client, GitHub, OAuthToken};
+
+use env_logger;
+use log::debug;
+use spectral::prelude::*;
+use std::env;
+
+#[test]
+#[ignore]
+fn github_user_gpg_keys() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let token = env::var_os("GITHUB_TOKEN")
+        .expect("Environment variable 'GITHUB_TOKEN' is not set.")
+        .to_string_lossy()
+        .to_string();
+
+    let token = OAuthToken(token);
+    let client = Client::with_oauth_token(&token);
+
+    let keys = client.user_gpg_keys("lukaspustina");
+
+    debug!("GPG keys: {:#?}", keys);
+
+    asserting("there are GPG keys")
+        .that(&keys)
+        .is_ok()
+        .matches(|x| !x.is_empty());
+}